@@ -0,0 +1,229 @@
+// Benchmarks of the rasterizer on the bundled meshes, run with
+//     cargo bench --bench rasterizer
+//
+// "draw_scene" times whole frames, "anti_aliasing" the same with MSAA and SSAA.
+// "coverage" only finds the covered pixels of the meshes' screen triangles, once with
+// the edge functions recomputed for every pixel of the bounding box (how the rasterizer
+// used to do it) and once with TriangleEdges (set up once, stepped per quad, tested
+// with SIMD and whole blocks rejected early)
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::num::NonZeroUsize;
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::edge_equation::TriangleEdges;
+use haywire_rasterizer::custom_data_types::game_object::GameObject;
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::mesh::Mesh;
+use haywire_rasterizer::custom_data_types::multisample::{AntiAliasing, SampleCount};
+use haywire_rasterizer::custom_data_types::rasterizer::{
+    Rasterizer, SUBPIXEL_BITS, SUBPIXEL_HALF, ScreenVertex,
+};
+use haywire_rasterizer::custom_data_types::render_target::RenderTarget;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::shader::{DefaultFragmentShader, DefaultVertexShader};
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+const WIDTH: usize = 1280;
+const HEIGHT: usize = 720;
+
+// (name, path, translation, scale), placed to fill a good part of the screen
+const MESHES: [(&str, &str, [f32; 3], f32); 3] = [
+    ("cube", "assets/cube.obj", [-0.5, -0.5, -2.0], 1.0),
+    ("suzzane", "assets/suzzane.obj", [2.5, -1.25, -7.5], 1.0),
+    ("bunny", "assets/bunny.obj", [0.35, -2.2, -3.0], 20.0),
+];
+
+fn scene(path: &str, translation: [f32; 3], scale: f32) -> Scene {
+    let camera = Camera::new(
+        ModelMatrix::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ),
+        60.0f32.to_radians(),
+        WIDTH as f32 / HEIGHT as f32,
+        0.5,
+        100.0,
+    );
+    let [x, y, z] = translation;
+    let model = ModelMatrix::new(
+        Vec3::new(x, y, z),
+        Vec3::new(0.3, 0.5, 0.0),
+        Vec3::new(scale, scale, scale),
+    );
+
+    let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path);
+    Scene::new(
+        vec![Mesh::new(&path).unwrap()],
+        vec![GameObject::new(0, model)],
+        camera,
+    )
+}
+
+// The front-facing screen triangles of the scene (no varyings, only the positions)
+fn screen_triangles(rasterizer: &Rasterizer, scene: &Scene) -> Vec<[ScreenVertex; 3]> {
+    let mesh = &scene.meshes[0];
+    let mvp = scene.camera.get_projection_matrix()
+        * scene.camera.get_view_matrix()
+        * scene.objects[0].model_matrix.get_model_matrix();
+
+    let mut triangles = vec![];
+    for corners in mesh.indices.chunks(3) {
+        let [v0, v1, v2] = [0, 1, 2].map(|k| (mvp * Vec4::point(mesh.vertices[corners[k]]), ()));
+        let polygon = rasterizer.world_to_screen(v0, v1, v2);
+
+        for k in 1..polygon.len().saturating_sub(1) {
+            let triangle = [polygon[0].0, polygon[k].0, polygon[k + 1].0];
+            if TriangleEdges::new(triangle[0], triangle[1], triangle[2]).area() > 0 {
+                triangles.push(triangle);
+            }
+        }
+    }
+    triangles
+}
+
+fn bounds([a, b, c]: [ScreenVertex; 3]) -> (i32, i32, i32, i32) {
+    let to_pixel = |v: i32| (v - SUBPIXEL_HALF) >> SUBPIXEL_BITS;
+    (
+        to_pixel(a.x.min(b.x).min(c.x)).max(0),
+        to_pixel(a.y.min(b.y).min(c.y)).max(0),
+        (to_pixel(a.x.max(b.x).max(c.x)) + 1).min(WIDTH as i32),
+        (to_pixel(a.y.max(b.y).max(c.y)) + 1).min(HEIGHT as i32),
+    )
+}
+
+// Three edge functions (and the top-left rule biases) for every pixel of the bounding box
+fn coverage_per_pixel(triangles: &[[ScreenVertex; 3]]) -> usize {
+    let edge = |a: ScreenVertex, b: ScreenVertex, (x, y): (i64, i64)| {
+        (b.x - a.x) as i64 * (y - a.y as i64) - (b.y - a.y) as i64 * (x - a.x as i64)
+    };
+    let bias = |a: ScreenVertex, b: ScreenVertex| {
+        let is_top = a.y == b.y && b.x < a.x;
+        if is_top || b.y < a.y { 0 } else { -1 }
+    };
+
+    let mut covered = 0;
+    for &[a, b, c] in triangles {
+        let (min_x, min_y, max_x, max_y) = bounds([a, b, c]);
+        let biases = [bias(b, c), bias(c, a), bias(a, b)];
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let center = (
+                    ((x << SUBPIXEL_BITS) + SUBPIXEL_HALF) as i64,
+                    ((y << SUBPIXEL_BITS) + SUBPIXEL_HALF) as i64,
+                );
+                let weights = [edge(b, c, center), edge(c, a, center), edge(a, b, center)];
+                if (0..3).all(|k| weights[k] + biases[k] >= 0) {
+                    covered += 1;
+                }
+            }
+        }
+    }
+    covered
+}
+
+// The same walk as Rasterizer::fill_triangle, without the shading
+fn coverage_stepped(triangles: &[[ScreenVertex; 3]]) -> usize {
+    let mut covered = 0;
+    for &[a, b, c] in triangles {
+        TriangleEdges::new(a, b, c).for_each_quad(
+            bounds([a, b, c]),
+            SampleCount::X1,
+            |_, _, quad| {
+                // One sample, so every pixel is 0 or 1
+                covered += u32::from_ne_bytes(quad).count_ones() as usize;
+            },
+        );
+    }
+    covered
+}
+
+fn coverage(c: &mut Criterion) {
+    let rasterizer = Rasterizer::new(RenderTarget::new(WIDTH, HEIGHT));
+    let mut group = c.benchmark_group("coverage");
+
+    for (name, path, translation, scale) in MESHES {
+        let triangles = screen_triangles(&rasterizer, &scene(path, translation, scale));
+        assert_eq!(
+            coverage_per_pixel(&triangles),
+            coverage_stepped(&triangles),
+            "the two coverage tests disagree on {}",
+            name
+        );
+
+        group.bench_function(format!("{}/per_pixel", name), |bench| {
+            bench.iter(|| coverage_per_pixel(black_box(&triangles)))
+        });
+        group.bench_function(format!("{}/stepped", name), |bench| {
+            bench.iter(|| coverage_stepped(black_box(&triangles)))
+        });
+    }
+    group.finish();
+}
+
+fn draw_scene(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_scene");
+    group.sample_size(20);
+
+    let mut threads = vec![1];
+    let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if available > 1 {
+        threads.push(available);
+    }
+    for (name, path, translation, scale) in MESHES {
+        let scene = scene(path, translation, scale);
+
+        for &threads in &threads {
+            let mut rasterizer = Rasterizer::new(RenderTarget::new(WIDTH, HEIGHT));
+            rasterizer.threads = threads;
+
+            group.bench_function(format!("{}/{}_threads", name, threads), |bench| {
+                bench.iter(|| {
+                    rasterizer.target.clear(Color::new(0, 0, 0, 255));
+                    rasterizer
+                        .draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new())
+                        .unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+// Whole frames on one thread with the anti-aliasing modes, to compare with draw_scene
+fn anti_aliasing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("anti_aliasing");
+    group.sample_size(10);
+
+    let modes = [
+        ("msaa_4x", AntiAliasing::Msaa(SampleCount::X4)),
+        ("msaa_8x", AntiAliasing::Msaa(SampleCount::X8)),
+        ("ssaa_2x", AntiAliasing::Ssaa(NonZeroUsize::new(2).unwrap())),
+    ];
+    for (name, path, translation, scale) in MESHES {
+        let scene = scene(path, translation, scale);
+
+        for (mode_name, mode) in modes {
+            let mut rasterizer = Rasterizer::new(RenderTarget::new(WIDTH, HEIGHT));
+            rasterizer.threads = 1;
+            rasterizer.anti_aliasing = mode;
+
+            group.bench_function(format!("{}/{}", name, mode_name), |bench| {
+                bench.iter(|| {
+                    rasterizer.target.clear(Color::new(0, 0, 0, 255));
+                    rasterizer
+                        .draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new())
+                        .unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, coverage, draw_scene, anti_aliasing);
+criterion_main!(benches);
@@ -1,86 +1,201 @@
+use super::mat3::Mat3;
 use super::matrices::{Matrix4x4, ModelMatrix};
+use super::quat::Quat;
+use super::vec3::Vec3;
+
+// How the view volume is flattened onto the screen. Every variant maps z_near to -1.0
+// and z_far to 1.0 in NDC, so the clipper and the depth buffer work the same for all
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // fov is the vertical field of view in radians
+    Perspective {
+        fov: f32,
+    },
+    // Parallel lines stay parallel and the size doesn't change with the distance.
+    // height is how much of the world fits vertically on screen
+    Orthographic {
+        height: f32,
+    },
+    // Orthographic, but the depth is drawn slanted: going away from the camera moves
+    // things along angle (radians, counterclockwise from the x axis), by depth_scale per
+    // unit of distance (0.5 is the cabinet projection, 1.0 the cavalier one).
+    // The plane at distance focus from the camera isn't moved
+    Oblique {
+        height: f32,
+        angle: f32,
+        depth_scale: f32,
+        focus: f32,
+    },
+}
+
+impl Projection {
+    // The height of the world the projection shows at this distance from the camera.
+    // Switching to an orthographic projection of that height keeps the things at that
+    // distance the same size on screen
+    pub fn view_height(&self, distance: f32) -> f32 {
+        match *self {
+            Projection::Perspective { fov } => 2.0 * distance * (fov / 2.0).tan(),
+            Projection::Orthographic { height } | Projection::Oblique { height, .. } => height,
+        }
+    }
+
+    pub fn matrix(&self, aspect_ratio: f32, z_near: f32, z_far: f32) -> Matrix4x4 {
+        match *self {
+            Projection::Perspective { fov } => {
+                Self::perspective_matrix(fov, aspect_ratio, z_near, z_far)
+            }
+            Projection::Orthographic { height } => {
+                Self::orthographic_matrix(height, aspect_ratio, z_near, z_far)
+            }
+            Projection::Oblique {
+                height,
+                angle,
+                depth_scale,
+                focus,
+            } => {
+                // Shears x and y by the distance in front of the focus plane (-z - focus
+                // in view space), then projects orthographically. z isn't touched, so the
+                // depth is the same as the orthographic one
+                let mut shear = Matrix4x4::identity();
+                let (sin, cos) = angle.sin_cos();
+                shear.data[0][2] = -depth_scale * cos;
+                shear.data[0][3] = -depth_scale * cos * focus;
+                shear.data[1][2] = -depth_scale * sin;
+                shear.data[1][3] = -depth_scale * sin * focus;
+
+                Self::orthographic_matrix(height, aspect_ratio, z_near, z_far) * shear
+            }
+        }
+    }
+
+    fn perspective_matrix(fov: f32, aspect_ratio: f32, z_near: f32, z_far: f32) -> Matrix4x4 {
+        let mut projection_matrix = Matrix4x4::zero();
+        let tangent_fov = (fov / 2.0).tan();
+
+        projection_matrix.data[0][0] = 1.0 / (aspect_ratio * tangent_fov);
+        projection_matrix.data[1][1] = 1.0 / tangent_fov;
+
+        projection_matrix.data[2][2] = -(z_far + z_near) / (z_far - z_near);
+        projection_matrix.data[2][3] = -(2.0 * z_far * z_near) / (z_far - z_near);
+
+        projection_matrix.data[3][2] = -1.0;
+
+        projection_matrix
+    }
+
+    // w stays 1.0, so the perspective divide does nothing and the depth is linear
+    fn orthographic_matrix(height: f32, aspect_ratio: f32, z_near: f32, z_far: f32) -> Matrix4x4 {
+        let mut projection_matrix = Matrix4x4::identity();
+
+        projection_matrix.data[0][0] = 2.0 / (height * aspect_ratio);
+        projection_matrix.data[1][1] = 2.0 / height;
+
+        projection_matrix.data[2][2] = -2.0 / (z_far - z_near);
+        projection_matrix.data[2][3] = -(z_far + z_near) / (z_far - z_near);
+
+        projection_matrix
+    }
+}
 
 pub struct Camera {
     pub model: ModelMatrix,
-    pub fov: f32,
+    pub projection: Projection,
     pub aspect_ratio: f32,
     pub z_near: f32,
     pub z_far: f32,
 }
 
 impl Camera {
+    // A perspective camera with a vertical field of view of fov radians
     pub fn new(model: ModelMatrix, fov: f32, aspect_ratio: f32, z_near: f32, z_far: f32) -> Self {
+        Self::with_projection(
+            model,
+            Projection::Perspective { fov },
+            aspect_ratio,
+            z_near,
+            z_far,
+        )
+    }
+
+    pub fn with_projection(
+        model: ModelMatrix,
+        projection: Projection,
+        aspect_ratio: f32,
+        z_near: f32,
+        z_far: f32,
+    ) -> Self {
         Self {
             model,
-            fov,
+            projection,
             aspect_ratio,
             z_near,
             z_far,
         }
     }
 
-    pub fn get_view_matrix(&self) -> Matrix4x4 {
-        let mut view_matrix = Matrix4x4::identity();
-
-        let (sx, cx) = self.model.angle().x.sin_cos();
-        let (sy, cy) = self.model.angle().y.sin_cos();
-        let (sz, cz) = self.model.angle().z.sin_cos();
-
-        // This is the Camera's Rotation Matrix
-        let r00 = cy * cz;
-        let r01 = -cx * sz + sx * sy * cz;
-        let r02 = sx * sz + cx * sy * cz;
-
-        let r10 = cy * sz;
-        let r11 = cx * cz + sx * sy * sz;
-        let r12 = -sx * cz + cx * sy * sz;
-
-        let r20 = -sy;
-        let r21 = sx * cy;
-        let r22 = cx * cy;
+    // Moves the camera to eye and turns it towards target, keeping up as close to the
+    // top of the screen as it can. Nothing changes if eye and target are the same point,
+    // and another up is picked if it points straight at target
+    pub fn look_at(&mut self, eye: Vec3, target: Vec3, up: Vec3) {
+        let direction = target - eye;
+        if direction.magnitude_squared() <= f32::EPSILON {
+            return;
+        }
+        let forward = direction.normalize();
+
+        let mut right = forward.cross(up);
+        if right.magnitude_squared() <= f32::EPSILON {
+            // Any other up that isn't parallel to forward works
+            let fallback = if forward.x.abs() < 0.9 {
+                Vec3::new(1.0, 0.0, 0.0)
+            } else {
+                Vec3::new(0.0, 0.0, 1.0)
+            };
+            right = forward.cross(fallback);
+        }
+        let right = right.normalize();
+        let up = right.cross(forward);
+
+        // The camera looks down its -z axis
+        let rotation = Mat3::from_columns(right, up, -forward);
+        self.model.update_translate(eye);
+        self.model
+            .update_rotation(Quat::from_rotation_matrix(&rotation));
+    }
 
-        // Building the View Matrix (The Inverse)
+    pub fn position(&self) -> Vec3 {
+        self.model.translation()
+    }
 
-        let x = self.model.translation().x;
-        let y = self.model.translation().y;
-        let z = self.model.translation().z;
+    // The direction the camera looks in
+    pub fn forward(&self) -> Vec3 {
+        self.model.rotation().rotate(Vec3::new(0.0, 0.0, -1.0))
+    }
 
-        view_matrix.data[0][0] = r00;
-        view_matrix.data[0][1] = r10;
-        view_matrix.data[0][2] = r20;
-        view_matrix.data[0][3] = -(r00 * x + r10 * y + r20 * z); // Dot product
+    pub fn right(&self) -> Vec3 {
+        self.model.rotation().rotate(Vec3::new(1.0, 0.0, 0.0))
+    }
 
-        view_matrix.data[1][0] = r01;
-        view_matrix.data[1][1] = r11;
-        view_matrix.data[1][2] = r21;
-        view_matrix.data[1][3] = -(r01 * x + r11 * y + r21 * z); // Dot product
+    pub fn up(&self) -> Vec3 {
+        self.model.rotation().rotate(Vec3::new(0.0, 1.0, 0.0))
+    }
 
-        view_matrix.data[2][0] = r02;
-        view_matrix.data[2][1] = r12;
-        view_matrix.data[2][2] = r22;
-        view_matrix.data[2][3] = -(r02 * x + r12 * y + r22 * z); // Dot product
+    // The inverse of the camera's model matrix (without scale): the opposite
+    // rotation, and the opposite translation rotated by it
+    pub fn get_view_matrix(&self) -> Matrix4x4 {
+        let inverse_rotation = self.model.rotation().conjugate();
+        let mut view_matrix = inverse_rotation.to_matrix();
 
-        view_matrix.data[3][0] = 0.0;
-        view_matrix.data[3][1] = 0.0;
-        view_matrix.data[3][2] = 0.0;
-        view_matrix.data[3][3] = 1.0;
+        let translation = inverse_rotation.rotate(self.model.translation());
+        view_matrix.data[0][3] = -translation.x;
+        view_matrix.data[1][3] = -translation.y;
+        view_matrix.data[2][3] = -translation.z;
 
         view_matrix
     }
 
     pub fn get_projection_matrix(&self) -> Matrix4x4 {
-        let mut projection_matrix = Matrix4x4::zero();
-        let tangent_fov = (self.fov / 2.0).tan();
-
-        projection_matrix.data[0][0] = 1.0 / (self.aspect_ratio * tangent_fov);
-        projection_matrix.data[1][1] = 1.0 / tangent_fov;
-
-        projection_matrix.data[2][2] = -(self.z_far + self.z_near) / (self.z_far - self.z_near);
-        projection_matrix.data[2][3] =
-            -(2.0 * self.z_far * self.z_near) / (self.z_far - self.z_near);
-
-        projection_matrix.data[3][2] = -1.0;
-
-        projection_matrix
+        self.projection
+            .matrix(self.aspect_ratio, self.z_near, self.z_far)
     }
 }
@@ -0,0 +1,186 @@
+use std::f32::consts::FRAC_PI_2;
+
+use super::camera::Camera;
+use super::quat::{EulerOrder, Quat};
+use super::vec3::Vec3;
+
+// The input since the last update, filled from whatever window library is in use
+// (see main.rs for minifb), so the controllers don't depend on one.
+// The mouse movement and the wheel are events: they are applied once, however long the
+// frame took. The buttons and the movement keys are held, and scaled by the delta time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ControllerInput {
+    // How far the mouse moved since the last update, in pixels (y going down the screen)
+    pub mouse_delta: (f32, f32),
+    // Dragging with the rotate button (left) or the pan button (right)
+    pub rotating: bool,
+    pub panning: bool,
+    // Mouse wheel steps, positive away from the user
+    pub scroll: f32,
+    // From -1.0 to 1.0 along the camera's right, up and forward (WASD, space and ctrl)
+    pub movement: Vec3,
+}
+
+impl ControllerInput {
+    // Forgets the mouse movement and the wheel once an update has used them, and keeps
+    // what is held. With a fixed timestep, the events of the frames without an update
+    // are added up until the next one
+    pub fn clear_events(&mut self) {
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = 0.0;
+    }
+}
+
+// Turns the input into a new camera position and orientation every frame
+pub trait CameraController {
+    fn name(&self) -> &str;
+
+    // Takes over the current state of the camera, so switching to this controller
+    // doesn't make the camera jump
+    fn sync(&mut self, camera: &Camera);
+
+    // delta_time is the time since the last update, in seconds
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta_time: f32);
+}
+
+// Pitch is kept a bit under 90 degrees, so up and the view direction never line up
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+// The pitch (around x) and the yaw (around y) of the direction the camera looks in,
+// the roll is dropped
+fn pitch_yaw(camera: &Camera) -> (f32, f32) {
+    let forward = camera.forward();
+    let pitch = forward.y.clamp(-1.0, 1.0).asin();
+    let yaw = (-forward.x).atan2(-forward.z);
+    (pitch.clamp(-MAX_PITCH, MAX_PITCH), yaw)
+}
+
+fn rotation(pitch: f32, yaw: f32) -> Quat {
+    Quat::from_euler(Vec3::new(pitch, yaw, 0.0), EulerOrder::XYZ)
+}
+
+// First person: dragging turns the camera, WASD moves it on the horizontal plane
+// and space / ctrl move it up and down
+pub struct FlyController {
+    pub position: Vec3,
+    pub pitch: f32,
+    pub yaw: f32,
+    // World units per second
+    pub speed: f32,
+    // Radians per pixel
+    pub sensitivity: f32,
+}
+
+impl FlyController {
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        Self {
+            position: Vec3::zero(),
+            pitch: 0.0,
+            yaw: 0.0,
+            speed,
+            sensitivity,
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn name(&self) -> &str {
+        "fly"
+    }
+
+    fn sync(&mut self, camera: &Camera) {
+        self.position = camera.position();
+        (self.pitch, self.yaw) = pitch_yaw(camera);
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta_time: f32) {
+        if input.rotating {
+            let (dx, dy) = input.mouse_delta;
+            self.pitch = (self.pitch + dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+            self.yaw -= dx * self.sensitivity;
+        }
+
+        // Moving forward doesn't change the height, whatever the pitch
+        let (sin, cos) = self.yaw.sin_cos();
+        let forward = Vec3::new(-sin, 0.0, -cos);
+        let right = Vec3::new(cos, 0.0, -sin);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let movement = input.movement;
+        self.position = self.position
+            + (right * movement.x + up * movement.y + forward * movement.z)
+                * (self.speed * delta_time);
+
+        camera.model.update_translate(self.position);
+        camera.model.update_rotation(rotation(self.pitch, self.yaw));
+    }
+}
+
+// Turns around a target (turntable style, the horizon stays level): dragging orbits,
+// dragging with the right button pans the target, and the wheel zooms in and out
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    // Radians per pixel
+    pub sensitivity: f32,
+    // How much one wheel step scales the distance (0.1 is 10% closer)
+    pub zoom_step: f32,
+    pub min_distance: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32, sensitivity: f32) -> Self {
+        Self {
+            target,
+            distance,
+            pitch: 0.0,
+            yaw: 0.0,
+            sensitivity,
+            zoom_step: 0.1,
+            min_distance: 0.01,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn name(&self) -> &str {
+        "orbit"
+    }
+
+    // Keeps the target and the distance, and only takes the direction of the camera:
+    // it is then moved to look at the target from there
+    fn sync(&mut self, camera: &Camera) {
+        (self.pitch, self.yaw) = pitch_yaw(camera);
+    }
+
+    // Everything here follows the mouse, which doesn't depend on the delta time
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, _delta_time: f32) {
+        let (dx, dy) = input.mouse_delta;
+
+        if input.rotating {
+            // Dragging right turns the scene right, so the camera goes left
+            self.yaw -= dx * self.sensitivity;
+            self.pitch = (self.pitch - dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        let rotation = rotation(self.pitch, self.yaw);
+
+        if input.panning {
+            // The target follows the mouse, at the same speed as what is at its distance
+            let scale = self.distance * self.sensitivity;
+            let right = rotation.rotate(Vec3::new(1.0, 0.0, 0.0));
+            let up = rotation.rotate(Vec3::new(0.0, 1.0, 0.0));
+            self.target = self.target - right * (dx * scale) + up * (dy * scale);
+        }
+
+        if input.scroll != 0.0 {
+            self.distance *= (1.0 - self.zoom_step).powf(input.scroll);
+            self.distance = self.distance.max(self.min_distance);
+        }
+
+        let eye = self.target + rotation.rotate(Vec3::new(0.0, 0.0, self.distance));
+        camera.model.update_translate(eye);
+        camera.model.update_rotation(rotation);
+    }
+}
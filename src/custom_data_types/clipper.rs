@@ -0,0 +1,115 @@
+use super::varying::Varying;
+use super::vec4::Vec4;
+
+// A clip space vertex together with its attributes
+pub type ClipVertex<V> = (Vec4, V);
+
+// Clips triangles against the 6 planes of the view frustum, in homogeneous
+// clip space (before the perspective divide), with Sutherland-Hodgman.
+// A point is inside the frustum if -w <= x, y, z <= w
+pub struct Clipper {
+    // The side planes are pushed out to -g * w <= x, y <= g * w. Triangles that
+    // only poke out of the screen a bit are then left to the bounding box
+    // clamping in fill_triangle instead of being cut. 1.0 means no guard band
+    guard_band: f32,
+}
+
+impl Clipper {
+    pub fn new(guard_band: f32) -> Self {
+        Self {
+            guard_band: guard_band.max(1.0),
+        }
+    }
+
+    pub fn guard_band(&self) -> f32 {
+        self.guard_band
+    }
+
+    pub fn set_guard_band(&mut self, guard_band: f32) {
+        self.guard_band = guard_band.max(1.0);
+    }
+
+    // Each plane is stored as the coefficients of its (signed) distance function,
+    // so the distance of a vertex is just a 4D dot product (>= 0 means inside)
+    fn planes(&self) -> [Vec4; 6] {
+        let g = self.guard_band;
+        [
+            Vec4::new(1.0, 0.0, 0.0, g),    // Left
+            Vec4::new(-1.0, 0.0, 0.0, g),   // Right
+            Vec4::new(0.0, 1.0, 0.0, g),    // Bottom
+            Vec4::new(0.0, -1.0, 0.0, g),   // Top
+            Vec4::new(0.0, 0.0, 1.0, 1.0),  // Near
+            Vec4::new(0.0, 0.0, -1.0, 1.0), // Far
+        ]
+    }
+
+    // Returns the clipped polygon as a list of vertices in the same (CCW) order
+    // as the input triangle. Empty if the triangle is fully outside,
+    // otherwise between 3 and 9 vertices
+    pub fn clip_triangle<V: Varying>(
+        &self,
+        v0: ClipVertex<V>,
+        v1: ClipVertex<V>,
+        v2: ClipVertex<V>,
+    ) -> Vec<ClipVertex<V>> {
+        let planes = self.planes();
+
+        // Trivial accept / reject, which is the case for most triangles
+        let mut outcodes = [0u8; 3];
+        for (outcode, v) in outcodes.iter_mut().zip([v0.0, v1.0, v2.0]) {
+            for (i, plane) in planes.iter().enumerate() {
+                if plane.dot(v) < 0.0 {
+                    *outcode |= 1 << i;
+                }
+            }
+        }
+
+        if outcodes[0] & outcodes[1] & outcodes[2] != 0 {
+            return vec![];
+        }
+
+        let mut polygon = vec![v0, v1, v2];
+        if outcodes[0] | outcodes[1] | outcodes[2] == 0 {
+            return polygon;
+        }
+
+        for plane in planes {
+            polygon = Self::clip_against_plane(&polygon, plane);
+            if polygon.is_empty() {
+                break;
+            }
+        }
+
+        polygon
+    }
+
+    // One Sutherland-Hodgman pass: keeps the vertices inside the plane and adds
+    // the intersection points of every edge that crosses it
+    fn clip_against_plane<V: Varying>(
+        polygon: &[ClipVertex<V>],
+        plane: Vec4,
+    ) -> Vec<ClipVertex<V>> {
+        let mut result = Vec::with_capacity(polygon.len() + 1);
+
+        for (i, &current) in polygon.iter().enumerate() {
+            let next = polygon[(i + 1) % polygon.len()];
+
+            let d_current = plane.dot(current.0);
+            let d_next = plane.dot(next.0);
+
+            if d_current >= 0.0 {
+                result.push(current);
+            }
+
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                result.push((
+                    current.0 + (next.0 - current.0) * t,
+                    current.1.lerp(next.1, t),
+                ));
+            }
+        }
+
+        result
+    }
+}
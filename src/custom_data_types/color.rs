@@ -1,3 +1,5 @@
+use super::vec4::Vec4;
+
 #[derive(Clone, Copy, Debug)]
 pub struct Color {
     r: u8,
@@ -36,7 +38,28 @@ impl Color {
         Color::new(r, g, b, a)
     }
 
+    // From (r, g, b, a) with every channel in [0.0, 1.0]
+    pub fn from_vec4(color: Vec4) -> Color {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        Color::new(
+            channel(color.x),
+            channel(color.y),
+            channel(color.z),
+            channel(color.w),
+        )
+    }
+
+    pub fn to_vec4(self) -> Vec4 {
+        Vec4::new(
+            self.r as f32 / 255.0,
+            self.g as f32 / 255.0,
+            self.b as f32 / 255.0,
+            self.a as f32 / 255.0,
+        )
+    }
+
     pub fn format_as_u32(&self) -> u32 {
-        0 | ((self.r() as u32) << 16) | ((self.g() as u32) << 8) | self.b() as u32
+        ((self.r() as u32) << 16) | ((self.g() as u32) << 8) | self.b() as u32
     }
 }
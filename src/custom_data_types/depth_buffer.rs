@@ -1,11 +1,15 @@
-use minifb::Window;
+use std::path::Path;
 
-use crate::custom_data_types::draw_buffer::DrawBuffer;
+use super::color::Color;
+use super::image::Image;
 
+// With MSAA every pixel has a depth per sample, stored next to each other
+// (pixel index * samples + sample)
 pub struct DepthBuffer {
     buffer: Vec<f32>,
     buffer_width: usize,
     buffer_height: usize,
+    samples: usize,
 }
 impl DepthBuffer {
     pub fn new(buffer: Vec<f32>, buffer_width: usize, buffer_height: usize) -> Self {
@@ -14,12 +18,14 @@ impl DepthBuffer {
                 buffer,
                 buffer_width,
                 buffer_height,
+                samples: 1,
             };
         }
         Self {
             buffer,
             buffer_width,
             buffer_height,
+            samples: 1,
         }
     }
 
@@ -29,35 +35,148 @@ impl DepthBuffer {
     pub fn buffer_height(&self) -> usize {
         self.buffer_height
     }
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
     pub fn buffer(&self) -> &Vec<f32> {
         &self.buffer
     }
-
-    fn clear(&mut self) {
-        self.buffer.fill(1.0f32);
+    pub fn buffer_mut(&mut self) -> &mut [f32] {
+        &mut self.buffer
     }
 
-    pub fn handle_clear(&mut self, window: &Window) -> () {
-        let (new_width, new_height) = window.get_size();
-
-        if self.buffer_width() != new_width || self.buffer_height() != new_height {
-            self.resize(new_width, new_height);
-        } else {
-            self.clear();
-        }
+    // Back to the far plane
+    pub fn clear(&mut self) {
+        self.buffer.fill(1.0f32);
     }
 
     pub fn resize(&mut self, buffer_width: usize, buffer_height: usize) {
         self.buffer_width = buffer_width;
         self.buffer_height = buffer_height;
-        self.buffer = vec![1.0f32; self.buffer_width() * self.buffer_height()];
+        self.buffer = vec![1.0f32; self.buffer_width() * self.buffer_height() * self.samples];
+    }
+
+    // Changes the number of depths per pixel, every sample of a pixel
+    // starting with its closest depth so nothing drawn is lost
+    pub fn set_samples(&mut self, samples: usize) {
+        if samples == self.samples {
+            return;
+        }
+        self.buffer = self
+            .pixel_depths()
+            .flat_map(|depth| std::iter::repeat_n(depth, samples))
+            .collect();
+        self.samples = samples;
     }
 
+    // Sets every sample of the pixel
     pub fn set(&mut self, x: usize, y: usize, depth: f32) {
-        self.buffer[(self.buffer_height - x - 1) * self.buffer_width + y] = depth;
+        let index = ((self.buffer_height - x - 1) * self.buffer_width + y) * self.samples;
+        self.buffer[index..index + self.samples].fill(depth);
     }
 
+    // The closest sample of the pixel
     pub fn get(&self, x: usize, y: usize) -> f32 {
-        self.buffer[(self.buffer_height - x - 1) * self.buffer_width + y]
+        let index = ((self.buffer_height - x - 1) * self.buffer_width + y) * self.samples;
+        self.buffer[index..index + self.samples]
+            .iter()
+            .fold(f32::INFINITY, |closest, &depth| closest.min(depth))
+    }
+
+    // The depth of every pixel (its closest sample), top row first
+    pub fn pixel_depths(&self) -> impl Iterator<Item = f32> + '_ {
+        self.buffer.chunks(self.samples).map(|samples| {
+            samples
+                .iter()
+                .fold(f32::INFINITY, |closest, &depth| closest.min(depth))
+        })
+    }
+
+    // The depth range actually covered by the drawn pixels (the cleared ones are
+    // left out), so that the exported images use the whole range of colors
+    fn drawn_range(&self) -> Option<(f32, f32)> {
+        self.pixel_depths()
+            .filter(|&depth| depth < 1.0)
+            .fold(None, |range, depth| match range {
+                None => Some((depth, depth)),
+                Some((min, max)) => Some((min.min(depth), max.max(depth))),
+            })
+    }
+
+    // Maps the drawn depths to [0.0, 1.0] with 0.0 the closest, the cleared pixels to None
+    fn normalized(&self) -> impl Iterator<Item = Option<f32>> + '_ {
+        let (min, max) = self.drawn_range().unwrap_or((0.0, 1.0));
+        let range = (max - min).max(f32::EPSILON);
+
+        self.pixel_depths().map(move |depth| {
+            if depth < 1.0 {
+                Some(((depth - min) / range).clamp(0.0, 1.0))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Close pixels are white, far ones dark gray and the background black
+    pub fn to_grayscale_image(&self) -> Image {
+        let pixels = self
+            .normalized()
+            .map(|depth| match depth {
+                Some(depth) => {
+                    let value = (255.0 - depth * 223.0).round() as u8;
+                    Color::new(value, value, value, 255)
+                }
+                None => Color::new(0, 0, 0, 255),
+            })
+            .collect();
+
+        Image::new(pixels, self.buffer_width, self.buffer_height)
+    }
+
+    // Close pixels are red, going through yellow and green to blue for the far ones,
+    // which makes small depth differences easier to see than in grayscale
+    pub fn to_false_color_image(&self) -> Image {
+        let pixels = self
+            .normalized()
+            .map(|depth| match depth {
+                Some(depth) => {
+                    let hue = depth * 4.0;
+                    let (r, g, b) = match hue {
+                        h if h < 1.0 => (1.0, h, 0.0),
+                        h if h < 2.0 => (2.0 - h, 1.0, 0.0),
+                        h if h < 3.0 => (0.0, 1.0, h - 2.0),
+                        h => (0.0, 4.0 - h, 1.0),
+                    };
+                    Color::new(
+                        (r * 255.0f32).round() as u8,
+                        (g * 255.0f32).round() as u8,
+                        (b * 255.0f32).round() as u8,
+                        255,
+                    )
+                }
+                None => Color::new(0, 0, 0, 255),
+            })
+            .collect();
+
+        Image::new(pixels, self.buffer_width, self.buffer_height)
+    }
+
+    // Saves the raw depths as a grayscale PFM (little endian), which keeps the full precision.
+    // PFM stores the bottom row first
+    pub fn save_pfm(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut bytes =
+            format!("Pf\n{} {}\n-1.0\n", self.buffer_width, self.buffer_height).into_bytes();
+        bytes.reserve(self.buffer_width * self.buffer_height * 4);
+
+        if self.buffer_width > 0 {
+            let depths: Vec<f32> = self.pixel_depths().collect();
+            for row in depths.chunks(self.buffer_width).rev() {
+                for depth in row {
+                    bytes.extend_from_slice(&depth.to_le_bytes());
+                }
+            }
+        }
+
+        std::fs::write(path, bytes)
     }
 }
@@ -1,5 +1,7 @@
+use std::path::Path;
+
 use super::color::Color;
-use minifb::Window;
+use super::image::{Image, ImageError};
 
 pub struct DrawBuffer {
     buffer: Vec<u32>,
@@ -32,19 +34,12 @@ impl DrawBuffer {
     pub fn buffer(&self) -> &Vec<u32> {
         &self.buffer
     }
-
-    fn clear(&mut self, clear_color: Color) {
-        self.buffer.fill(clear_color.format_as_u32());
+    pub fn buffer_mut(&mut self) -> &mut [u32] {
+        &mut self.buffer
     }
 
-    pub fn handle_clear(&mut self, window: &Window) -> () {
-        let (new_width, new_height) = window.get_size();
-
-        if self.buffer_width() != new_width || self.buffer_height() != new_height {
-            self.resize(new_width, new_height);
-        } else {
-            self.clear(Color::new(0, 0, 0, 255));
-        }
+    pub fn clear(&mut self, clear_color: Color) {
+        self.buffer.fill(clear_color.format_as_u32());
     }
 
     pub fn resize(&mut self, buffer_width: usize, buffer_height: usize) {
@@ -56,4 +51,20 @@ impl DrawBuffer {
     pub fn set(&mut self, x: usize, y: usize, color: Color) {
         self.buffer[(self.buffer_height - x - 1) * self.buffer_width + y] = color.format_as_u32();
     }
+
+    // The buffer rows are already stored top row first, like the Image pixels
+    pub fn to_image(&self) -> Image {
+        let pixels = self
+            .buffer
+            .iter()
+            .map(|&pixel| Color::new((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8, 255))
+            .collect();
+
+        Image::new(pixels, self.buffer_width, self.buffer_height)
+    }
+
+    // Saves as PNG or PPM, depending on the extension
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        self.to_image().save(path)
+    }
 }
@@ -0,0 +1,357 @@
+use super::multisample::{SampleCount, sample_offsets};
+use super::rasterizer::{SUBPIXEL_HALF, SUBPIXEL_ONE, ScreenVertex};
+
+// Size in pixels of the square blocks the triangles are walked in (see for_each_quad).
+// The coverage of a block fits in a u64 (bit row * BLOCK_SIZE + column)
+pub const BLOCK_SIZE: i32 = 8;
+
+// How a block of pixels lies relative to a triangle (see TriangleEdges::block_coverage)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockCoverage {
+    // No pixel center of the block is in the triangle
+    Outside,
+    // Some might be, the pixels have to be tested
+    Partial,
+    // Every pixel center of the block is in the triangle
+    Inside,
+}
+
+// The three edge equations of a triangle, set up once instead of recomputing the
+// cross products for every pixel. For the edge opposite to vertex k (in the order a, b, c)
+// the value at the center of pixel (x, y) is
+//     origin[k] + x * step_x[k] + y * step_y[k]
+// which is exactly the fixed point edge function, so stepping gives the same weights.
+// The values are the (not normalized) barycentric weights of the pixel
+#[derive(Clone, Copy, Debug)]
+pub struct TriangleEdges {
+    origin: [i64; 3],
+    step_x: [i64; 3],
+    step_y: [i64; 3],
+    // A pixel is inside if every weight is above its threshold, which encodes
+    // the top-left fill rule (see edge_bias)
+    threshold: [i64; 3],
+    // column * step_x, added to the start of a row of a block
+    row_offsets: [[i64; BLOCK_SIZE as usize]; 3],
+    // Twice the signed area of the triangle, which is the sum of the weights
+    area: i64,
+    simd: bool,
+}
+
+impl TriangleEdges {
+    #[inline]
+    pub fn new(a: ScreenVertex, b: ScreenVertex, c: ScreenVertex) -> Self {
+        let edges = [(b, c), (c, a), (a, b)];
+        let one = SUBPIXEL_ONE as i64;
+        let center = SUBPIXEL_HALF as i64;
+
+        // Edge function of the point p against the edge from -> to:
+        //     (to.x - from.x) * (p.y - from.y) - (to.y - from.y) * (p.x - from.x)
+        let step_x = edges.map(|(from, to)| -((to.y - from.y) as i64) * one);
+        let step_y = edges.map(|(from, to)| (to.x - from.x) as i64 * one);
+        let origin = edges.map(|(from, to)| {
+            (to.x - from.x) as i64 * (center - from.y as i64)
+                - (to.y - from.y) as i64 * (center - from.x as i64)
+        });
+        let threshold = edges.map(|(from, to)| -Self::edge_bias(from, to) - 1);
+        let row_offsets = step_x.map(|step| std::array::from_fn(|column| column as i64 * step));
+
+        let area = origin.iter().sum::<i64>();
+
+        Self {
+            origin,
+            step_x,
+            step_y,
+            threshold,
+            row_offsets,
+            area,
+            simd: simd_available(),
+        }
+    }
+
+    // Top-left fill rule: a sample that lies exactly on an edge shared by two
+    // triangles must be drawn by only one of them. With CCW triangles and y going up,
+    // the interior is on the left of every edge, so a top edge is a horizontal edge
+    // going in -x and a left edge is one going down. Other edges get a bias of -1,
+    // which turns their ">= 0" test into "> 0"
+    #[inline]
+    fn edge_bias(a: ScreenVertex, b: ScreenVertex) -> i64 {
+        let is_top = a.y == b.y && b.x < a.x;
+        let is_left = b.y < a.y;
+
+        if is_top || is_left { 0 } else { -1 }
+    }
+
+    #[inline]
+    pub fn area(&self) -> i64 {
+        self.area
+    }
+
+    // The weights at the center of the pixel
+    #[inline]
+    pub fn at(&self, x: i32, y: i32) -> [i64; 3] {
+        [0, 1, 2].map(|k| self.origin[k] + x as i64 * self.step_x[k] + y as i64 * self.step_y[k])
+    }
+
+    // What has to be added to the weights at a pixel center to get the ones at
+    // the sample offset (in fixed point) from it. Exact, as step_x and step_y
+    // are multiples of SUBPIXEL_ONE
+    #[inline]
+    pub fn sample_delta(&self, (dx, dy): (i32, i32)) -> [i64; 3] {
+        let one = SUBPIXEL_ONE as i64;
+        [0, 1, 2].map(|k| (dx as i64 * self.step_x[k] + dy as i64 * self.step_y[k]) / one)
+    }
+
+    // The weights of the 4 pixels of the quad whose bottom-left pixel is (x, y), in the
+    // order bottom-left, bottom-right, top-left, top-right
+    #[inline]
+    pub fn quad_weights(&self, x: i32, y: i32) -> [[i64; 3]; 4] {
+        let bottom_left = self.at(x, y);
+        let offsets = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+        offsets.map(|(dx, dy)| {
+            [0, 1, 2].map(|k| bottom_left[k] + dx * self.step_x[k] + dy * self.step_y[k])
+        })
+    }
+
+    // Tests the block of pixels [x0, x1) x [y0, y1) as a whole: the edge functions
+    // are linear, so their extremes over the block are at its corners.
+    // origin replaces self.origin, to test a sample instead of the pixel centers
+    #[inline]
+    fn block_coverage(
+        &self,
+        origin: &[i64; 3],
+        (x0, y0, x1, y1): (i32, i32, i32, i32),
+    ) -> BlockCoverage {
+        let mut inside = true;
+
+        for (k, &start) in origin.iter().enumerate() {
+            let (low_x, high_x) = if self.step_x[k] >= 0 {
+                (x0, x1 - 1)
+            } else {
+                (x1 - 1, x0)
+            };
+            let (low_y, high_y) = if self.step_y[k] >= 0 {
+                (y0, y1 - 1)
+            } else {
+                (y1 - 1, y0)
+            };
+
+            let value =
+                |x: i32, y: i32| start + x as i64 * self.step_x[k] + y as i64 * self.step_y[k];
+
+            if value(high_x, high_y) <= self.threshold[k] {
+                return BlockCoverage::Outside;
+            }
+            if value(low_x, low_y) <= self.threshold[k] {
+                inside = false;
+            }
+        }
+
+        if inside {
+            BlockCoverage::Inside
+        } else {
+            BlockCoverage::Partial
+        }
+    }
+
+    // Which pixels of the first rows of the block whose bottom-left pixel is (x0, y0) are
+    // in the triangle, bit row * BLOCK_SIZE + column for the pixel (x0 + column, y0 + row).
+    // Like block_coverage, origin is where the pixels are sampled
+    #[inline]
+    fn block_mask(&self, origin: &[i64; 3], x0: i32, y0: i32, rows: i32) -> u64 {
+        let value = |k: usize| origin[k] + x0 as i64 * self.step_x[k] + y0 as i64 * self.step_y[k];
+        let row_start = [value(0), value(1), value(2)];
+
+        #[cfg(target_arch = "x86_64")]
+        if self.simd {
+            // SAFETY: simd is only set when the CPU supports AVX2
+            return unsafe { self.block_mask_avx2(row_start, rows) };
+        }
+
+        self.block_mask_scalar(row_start, rows)
+    }
+
+    fn block_mask_scalar(&self, mut row_start: [i64; 3], rows: i32) -> u64 {
+        let mut mask = 0;
+        for row in 0..rows as usize {
+            for column in 0..BLOCK_SIZE as usize {
+                let inside =
+                    (0..3).all(|k| row_start[k] + self.row_offsets[k][column] > self.threshold[k]);
+                mask |= (inside as u64) << (row * BLOCK_SIZE as usize + column);
+            }
+
+            for (start, step) in row_start.iter_mut().zip(self.step_y) {
+                *start += step;
+            }
+        }
+        mask
+    }
+
+    // A row of 8 pixels is two registers of 4 i64 lanes per edge. The rows are stepped
+    // by adding step_y, so there are no multiplications in the loop
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    fn block_mask_avx2(&self, row_start: [i64; 3], rows: i32) -> u64 {
+        use std::arch::x86_64::*;
+
+        let mut values = [_mm256_setzero_si256(); 3];
+        let mut low = [_mm256_setzero_si256(); 3];
+        let mut high = [_mm256_setzero_si256(); 3];
+        let mut step = [_mm256_setzero_si256(); 3];
+        let mut threshold = [_mm256_setzero_si256(); 3];
+        for k in 0..3 {
+            let o = &self.row_offsets[k];
+            values[k] = _mm256_set1_epi64x(row_start[k]);
+            low[k] = _mm256_set_epi64x(o[3], o[2], o[1], o[0]);
+            high[k] = _mm256_set_epi64x(o[7], o[6], o[5], o[4]);
+            step[k] = _mm256_set1_epi64x(self.step_y[k]);
+            threshold[k] = _mm256_set1_epi64x(self.threshold[k]);
+        }
+
+        let mut mask = 0;
+        for row in 0..rows as usize {
+            let mut inside_low = _mm256_set1_epi64x(-1);
+            let mut inside_high = _mm256_set1_epi64x(-1);
+            for k in 0..3 {
+                let above_low =
+                    _mm256_cmpgt_epi64(_mm256_add_epi64(values[k], low[k]), threshold[k]);
+                let above_high =
+                    _mm256_cmpgt_epi64(_mm256_add_epi64(values[k], high[k]), threshold[k]);
+                inside_low = _mm256_and_si256(inside_low, above_low);
+                inside_high = _mm256_and_si256(inside_high, above_high);
+                values[k] = _mm256_add_epi64(values[k], step[k]);
+            }
+
+            // One bit per lane, from the sign bits
+            let bits = _mm256_movemask_pd(_mm256_castsi256_pd(inside_low))
+                | (_mm256_movemask_pd(_mm256_castsi256_pd(inside_high)) << 4);
+            mask |= (bits as u64) << (row * BLOCK_SIZE as usize);
+        }
+        mask
+    }
+
+    // Walks the bounding box [min_x, max_x) x [min_y, max_y) in blocks of BLOCK_SIZE pixels,
+    // starting from its bottom-left quad, testing the pixels at every sample position of
+    // the pattern (see sample_offsets). For every sample, the blocks
+    // outside of the triangle are skipped without looking at their pixels, the ones fully
+    // inside aren't tested any further, and the others get a block_mask. Then
+    // visit(quad_x, quad_y, coverage) is called for every 2x2 quad (aligned to even
+    // coordinates) with a covered sample, bit s of coverage[k] for the sample s of the
+    // pixel k of quad_weights
+    #[inline]
+    pub fn for_each_quad(
+        &self,
+        bounds: (i32, i32, i32, i32),
+        samples: SampleCount,
+        visit: impl FnMut(i32, i32, [u8; 4]),
+    ) {
+        // The sample count is a constant in walk_blocks, so the loops over
+        // the samples disappear (which matters most without MSAA)
+        let offsets = sample_offsets(samples);
+        match samples {
+            SampleCount::X1 => self.walk_blocks::<1>(bounds, offsets, visit),
+            SampleCount::X2 => self.walk_blocks::<2>(bounds, offsets, visit),
+            SampleCount::X4 => self.walk_blocks::<4>(bounds, offsets, visit),
+            SampleCount::X8 => self.walk_blocks::<8>(bounds, offsets, visit),
+        }
+    }
+
+    #[inline]
+    fn walk_blocks<const SAMPLES: usize>(
+        &self,
+        (min_x, min_y, max_x, max_y): (i32, i32, i32, i32),
+        samples: &[(i32, i32)],
+        mut visit: impl FnMut(i32, i32, [u8; 4]),
+    ) {
+        const SIZE: i32 = BLOCK_SIZE;
+        // The bits of the first column and of the first row of a block
+        const COLUMN: u64 = 0x0101_0101_0101_0101;
+        const ROW: u64 = 0xff;
+        // The bottom-left pixels of the quads of a block
+        const QUAD_CORNERS: u64 = 0x0055_0055_0055_0055;
+
+        let origins: [[i64; 3]; SAMPLES] = std::array::from_fn(|sample| {
+            let delta = self.sample_delta(samples[sample]);
+            [0, 1, 2].map(|k| self.origin[k] + delta[k])
+        });
+
+        let start_x = min_x & !1;
+        let start_y = min_y & !1;
+        // Most triangles of detailed meshes fit in one block, which is
+        // almost never fully inside, so the block test isn't worth it
+        let single_block = max_x - start_x <= SIZE && max_y - start_y <= SIZE;
+
+        for block_y in (start_y..max_y).step_by(SIZE as usize) {
+            // The rows of the block that are in the bounding box
+            let first_row = (min_y - block_y).max(0);
+            let last_row = (max_y - block_y).min(SIZE);
+            let rows = (u64::MAX >> (64 - SIZE * (last_row - first_row))) << (first_row * SIZE);
+
+            for block_x in (start_x..max_x).step_by(SIZE as usize) {
+                let block = (block_x, block_y, block_x + SIZE, block_y + last_row);
+
+                // One mask per sample, and the pixels with any sample covered
+                let mut masks = [0; SAMPLES];
+                let mut mask = 0;
+                for (sample_mask, origin) in masks.iter_mut().zip(&origins) {
+                    let coverage = if single_block {
+                        BlockCoverage::Partial
+                    } else {
+                        self.block_coverage(origin, block)
+                    };
+                    *sample_mask = match coverage {
+                        BlockCoverage::Outside => continue,
+                        BlockCoverage::Inside => u64::MAX,
+                        BlockCoverage::Partial => {
+                            self.block_mask(origin, block_x, block_y, last_row)
+                        }
+                    };
+                    mask |= *sample_mask;
+                }
+                if mask == 0 {
+                    continue;
+                }
+
+                let first_column = (min_x - block_x).max(0);
+                let last_column = (max_x - block_x).min(SIZE);
+                let columns =
+                    ((ROW >> (SIZE - (last_column - first_column))) << first_column) * COLUMN;
+                let mask = mask & rows & columns;
+
+                // A bit at the bottom-left pixel of every quad with a covered pixel
+                let mut quads =
+                    (mask | (mask >> 1) | (mask >> SIZE) | (mask >> (SIZE + 1))) & QUAD_CORNERS;
+                while quads != 0 {
+                    let bit = quads.trailing_zeros() as i32;
+                    quads &= quads - 1;
+
+                    // The covered samples of a pixel (the bounds are already in mask)
+                    let samples_at = |pixel: i32| {
+                        let mut coverage = 0;
+                        for (sample, sample_mask) in masks.iter().enumerate() {
+                            coverage |= ((((sample_mask & mask) >> pixel) & 1) as u8) << sample;
+                        }
+                        coverage
+                    };
+                    let coverage = [
+                        samples_at(bit),
+                        samples_at(bit + 1),
+                        samples_at(bit + SIZE),
+                        samples_at(bit + SIZE + 1),
+                    ];
+                    visit(block_x + bit % SIZE, block_y + bit / SIZE, coverage);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn simd_available() -> bool {
+    std::arch::is_x86_feature_detected!("avx2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_available() -> bool {
+    false
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+use super::image::ImageError;
+
+// Everything loading and drawing can fail with. Assets that are only partly broken
+// (a missing texture or MTL file) still load, with a message, instead of failing
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    // The file could be read, but not understood
+    Parse(String),
+    // The file type (or a variant of it) isn't handled
+    UnsupportedFormat(String),
+    // An index past the end of what it points into, e.g. a face using a vertex
+    // the mesh doesn't have, or an object using a mesh the scene doesn't have
+    InvalidIndex {
+        what: &'static str,
+        index: usize,
+        len: usize,
+    },
+    // Geometry that can't be drawn, e.g. a mesh without any triangle
+    DegenerateGeometry(String),
+    Image(ImageError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Parse(what) => write!(f, "Parse error: {}", what),
+            Error::UnsupportedFormat(what) => write!(f, "Unsupported format: {}", what),
+            Error::InvalidIndex { what, index, len } => {
+                write!(f, "Invalid {} index {} (there are {})", what, index, len)
+            }
+            Error::DegenerateGeometry(what) => write!(f, "Degenerate geometry: {}", what),
+            Error::Image(err) => write!(f, "Image error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(err: ImageError) -> Self {
+        Error::Image(err)
+    }
+}
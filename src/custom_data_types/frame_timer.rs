@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Measures how long every frame takes: the delta time to scale movement by, a smoothed
+// FPS for display, and percentiles of the last frames to spot stutters
+pub struct FrameTimer {
+    last: Instant,
+    delta: Duration,
+    elapsed: Duration,
+    frames: u64,
+    // Exponential moving average of the frame time, in seconds
+    smoothed: f32,
+    // The newest frames, up to history_length of them
+    history: VecDeque<Duration>,
+    history_length: usize,
+    // Longer frames (a breakpoint, dragging the window...) are cut to this in delta,
+    // so nothing jumps across the scene afterwards. The statistics keep the real time
+    pub max_delta: Duration,
+    // How much the newest frame counts in the smoothed FPS (1.0 is no smoothing)
+    pub smoothing: f32,
+}
+
+impl FrameTimer {
+    pub fn new(history_length: usize) -> Self {
+        let history_length = history_length.max(1);
+        Self {
+            last: Instant::now(),
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frames: 0,
+            smoothed: 0.0,
+            history: VecDeque::with_capacity(history_length),
+            history_length,
+            max_delta: Duration::from_millis(250),
+            smoothing: 0.1,
+        }
+    }
+
+    // Call once per frame: records the time since the previous call and returns
+    // the delta time in seconds
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let frame_time = now - self.last;
+        self.last = now;
+        self.record(frame_time);
+        self.delta_seconds()
+    }
+
+    // Adds a frame that took frame_time, without looking at the clock (tick uses it,
+    // and it can replay recorded frame times)
+    pub fn record(&mut self, frame_time: Duration) {
+        self.delta = frame_time.min(self.max_delta);
+        self.elapsed += frame_time;
+
+        let seconds = frame_time.as_secs_f32();
+        self.smoothed = if self.frames == 0 {
+            seconds
+        } else {
+            self.smoothed + (seconds - self.smoothed) * self.smoothing
+        };
+        self.frames += 1;
+
+        if self.history.len() == self.history_length {
+            self.history.pop_front();
+        }
+        self.history.push_back(frame_time);
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    // The total time of all the recorded frames
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    // Frames per second from the smoothed frame time, 0.0 before the first frame
+    pub fn fps(&self) -> f32 {
+        if self.smoothed > 0.0 {
+            1.0 / self.smoothed
+        } else {
+            0.0
+        }
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        if self.history.is_empty() {
+            return Duration::ZERO;
+        }
+        self.history.iter().sum::<Duration>() / self.history.len() as u32
+    }
+
+    // The frame time that percentile percent of the recent frames are at most
+    // (nearest rank, 50.0 is the median and 100.0 the slowest frame)
+    pub fn frame_time_percentile(&self, percentile: f32) -> Duration {
+        if self.history.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let mut sorted: Vec<Duration> = self.history.iter().copied().collect();
+        sorted.sort_unstable();
+
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f32).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+impl Default for FrameTimer {
+    fn default() -> Self {
+        Self::new(240)
+    }
+}
+
+// Runs the updates at a fixed rate whatever the frame rate: every frame advance says how
+// many steps of step() to run, and the time left over is carried to the next frame
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    // When the frames are so slow that more steps than this would be needed, the rest
+    // is dropped. Otherwise the updates take longer and longer to catch up
+    pub max_steps: u32,
+}
+
+impl FixedTimestep {
+    // Between one update every 100 seconds and 10000 per second
+    pub const MIN_RATE: f32 = 0.01;
+    pub const MAX_RATE: f32 = 10_000.0;
+
+    // rate is in updates per second. It is clamped to MIN_RATE..=MAX_RATE, and a NaN
+    // rate is MIN_RATE, so the step is never zero, negative or infinite
+    pub fn new(rate: f32) -> Self {
+        let rate = if rate.is_nan() {
+            Self::MIN_RATE
+        } else {
+            rate.clamp(Self::MIN_RATE, Self::MAX_RATE)
+        };
+
+        Self {
+            step: Duration::from_secs_f32(1.0 / rate),
+            accumulator: Duration::ZERO,
+            max_steps: 8,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    // Adds the time of a frame and returns the number of steps to run for it
+    pub fn advance(&mut self, frame_time: Duration) -> u32 {
+        self.accumulator += frame_time;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_steps {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    // How far the time is between the last step and the next one (0.0 to 1.0),
+    // to interpolate what is drawn between the two
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
@@ -0,0 +1,956 @@
+mod json;
+
+use std::path::Path;
+
+use self::json::Json;
+use super::error::Error;
+use super::image::Image;
+use super::material::{AlphaMode, Material};
+use super::matrices::{Matrix4x4, ModelMatrix};
+use super::mesh::{Mesh, MeshGroup};
+use super::quat::Quat;
+use super::texture::{AddressMode, Filter, Texture};
+use super::vec2::Vec2;
+use super::vec3::Vec3;
+use super::vec4::Vec4;
+
+// A node of the file that draws a mesh. The node tree is flattened: parent holds the
+// transforms of all the node's parents, applied over the node's own one
+pub struct GltfNode {
+    pub name: String,
+    // Index in GltfScene::meshes
+    pub mesh: usize,
+    // The node's own translation, rotation and scale (or matrix)
+    pub local: Matrix4x4,
+    // Identity for the nodes at the root of the scene
+    pub parent: Matrix4x4,
+}
+
+impl GltfNode {
+    // Where the node ends up in the scene
+    pub fn transform(&self) -> Matrix4x4 {
+        self.parent * self.local
+    }
+}
+
+// What a glTF 2.0 file (.gltf or .glb) holds that can be drawn: every mesh, with its
+// primitives merged and its materials and textures, and the nodes that place them.
+// Animations, skins, morph targets and cameras are left out
+pub struct GltfScene {
+    pub meshes: Vec<Mesh>,
+    pub nodes: Vec<GltfNode>,
+}
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+// Primitive modes (the others are points and lines, which aren't drawn)
+const TRIANGLES: usize = 4;
+const TRIANGLE_STRIP: usize = 5;
+const TRIANGLE_FAN: usize = 6;
+
+// Sampler values, from OpenGL
+const NEAREST: usize = 9728;
+const LINEAR: usize = 9729;
+const NEAREST_MIPMAP_NEAREST: usize = 9984;
+const LINEAR_MIPMAP_NEAREST: usize = 9985;
+const CLAMP_TO_EDGE: usize = 33071;
+const MIRRORED_REPEAT: usize = 33648;
+
+fn parse_error(what: String) -> Error {
+    Error::Parse(format!("glTF: {}", what))
+}
+
+// The array called key, empty if there isn't one
+fn array<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).and_then(Json::as_array).unwrap_or(&[])
+}
+
+// The item of the array called key that index points to
+fn item<'a>(
+    json: &'a Json,
+    key: &str,
+    what: &'static str,
+    index: usize,
+) -> Result<&'a Json, Error> {
+    let items = array(json, key);
+    items.get(index).ok_or(Error::InvalidIndex {
+        what,
+        index,
+        len: items.len(),
+    })
+}
+
+// N numbers (or default if there aren't exactly N), e.g. a color or a matrix
+fn numbers<const N: usize>(json: Option<&Json>, default: [f32; N]) -> [f32; N] {
+    let Some(values) = json.and_then(Json::as_array) else {
+        return default;
+    };
+    if values.len() != N {
+        return default;
+    }
+    let mut result = default;
+    for (value, json) in result.iter_mut().zip(values) {
+        *value = json.as_f32().unwrap_or(*value);
+    }
+    result
+}
+
+impl GltfScene {
+    // Missing or undecodable images are only reported, the materials load without
+    // them. Everything else that is missing or broken fails the whole file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+
+        let (text, binary_chunk) = if bytes.starts_with(GLB_MAGIC) {
+            parse_glb(&bytes)?
+        } else {
+            (&bytes[..], None)
+        };
+        let text = std::str::from_utf8(text)
+            .map_err(|_| parse_error(String::from("the JSON isn't valid text")))?;
+        // Some editors start the file with a byte order mark
+        let json = Json::parse(text.trim_start_matches('\u{feff}'))?;
+
+        let version = json
+            .get("asset")
+            .and_then(|asset| asset.get("version"))
+            .and_then(Json::as_str)
+            .unwrap_or("");
+        if !version.starts_with("2.") {
+            return Err(Error::UnsupportedFormat(format!(
+                "glTF version \"{}\" ({})",
+                version,
+                path.display()
+            )));
+        }
+        let required: Vec<&str> = array(&json, "extensionsRequired")
+            .iter()
+            .filter_map(Json::as_str)
+            .collect();
+        if !required.is_empty() {
+            return Err(Error::UnsupportedFormat(format!(
+                "glTF extensions {} ({})",
+                required.join(", "),
+                path.display()
+            )));
+        }
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let document = Document::new(json, directory, binary_chunk)?;
+        document.scene()
+    }
+}
+
+// The JSON chunk and the binary chunk (if there is one) of a .glb file
+fn parse_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), Error> {
+    let u32_at = |position: usize| {
+        bytes
+            .get(position..position + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| parse_error(String::from("GLB ends in the middle of a header")))
+    };
+
+    let version = u32_at(4)?;
+    if version != 2 {
+        return Err(Error::UnsupportedFormat(format!("GLB version {}", version)));
+    }
+    let length = u32_at(8)? as usize;
+    if length > bytes.len() {
+        return Err(parse_error(format!(
+            "GLB of {} bytes, but only {} were read",
+            length,
+            bytes.len()
+        )));
+    }
+
+    let mut json = None;
+    let mut binary = None;
+    let mut position = 12;
+    while position < length {
+        let chunk_length = u32_at(position)? as usize;
+        let chunk_type = u32_at(position + 4)?;
+        let start = position + 8;
+        let chunk = bytes
+            .get(start..start + chunk_length)
+            .filter(|_| start + chunk_length <= length)
+            .ok_or_else(|| parse_error(String::from("GLB chunk past the end of the file")))?;
+
+        match chunk_type {
+            GLB_JSON_CHUNK if json.is_none() => json = Some(chunk),
+            GLB_BIN_CHUNK if binary.is_none() => binary = Some(chunk),
+            // Chunks of extensions
+            _ => {}
+        }
+        // Chunks are padded to 4 bytes
+        position = start + chunk_length.next_multiple_of(4);
+    }
+
+    let json = json.ok_or_else(|| parse_error(String::from("GLB without a JSON chunk")))?;
+    Ok((json, binary))
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+
+    for c in text.bytes() {
+        // The URL safe alphabet is accepted too
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\n' | b'\r' => continue,
+            _ => return Err(parse_error(String::from("invalid base64 data"))),
+        };
+
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+// URIs of files may have escaped characters (%20 for spaces...)
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Where an accessor's values are: every element is components values of
+// component_type, and elements are stride bytes apart
+struct AccessorData<'a> {
+    bytes: &'a [u8],
+    count: usize,
+    components: usize,
+    component_type: usize,
+    normalized: bool,
+    stride: usize,
+}
+
+impl AccessorData<'_> {
+    fn component_size(component_type: usize) -> Option<usize> {
+        match component_type {
+            // BYTE, UNSIGNED_BYTE
+            5120 | 5121 => Some(1),
+            // SHORT, UNSIGNED_SHORT
+            5122 | 5123 => Some(2),
+            // UNSIGNED_INT, FLOAT
+            5125 | 5126 => Some(4),
+            _ => None,
+        }
+    }
+
+    fn component(&self, element: usize, component: usize) -> f64 {
+        let size = Self::component_size(self.component_type).unwrap_or(1);
+        let at = element * self.stride + component * size;
+        let b = &self.bytes[at..at + size];
+
+        match self.component_type {
+            5120 => b[0] as i8 as f64,
+            5121 => b[0] as f64,
+            5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        }
+    }
+
+    // Normalized integers are brought to [0.0, 1.0] (or [-1.0, 1.0] when signed)
+    fn floats(&self) -> Vec<f32> {
+        let scale = match (self.normalized, self.component_type) {
+            (true, 5120) => 1.0 / i8::MAX as f64,
+            (true, 5121) => 1.0 / u8::MAX as f64,
+            (true, 5122) => 1.0 / i16::MAX as f64,
+            (true, 5123) => 1.0 / u16::MAX as f64,
+            (true, 5125) => 1.0 / u32::MAX as f64,
+            _ => 1.0,
+        };
+
+        let mut values = Vec::with_capacity(self.count * self.components);
+        for element in 0..self.count {
+            for component in 0..self.components {
+                let value = self.component(element, component) * scale;
+                // The most negative integer would be a bit below -1.0
+                values.push(if self.normalized {
+                    value.max(-1.0)
+                } else {
+                    value
+                } as f32);
+            }
+        }
+        values
+    }
+}
+
+// The parsed file, with its buffers loaded
+struct Document<'a> {
+    json: Json,
+    directory: &'a Path,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Document<'a> {
+    fn new(json: Json, directory: &'a Path, binary_chunk: Option<&[u8]>) -> Result<Self, Error> {
+        let mut document = Self {
+            json,
+            directory,
+            buffers: vec![],
+        };
+
+        let mut buffers = vec![];
+        for (index, buffer) in array(&document.json, "buffers").iter().enumerate() {
+            let bytes = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) => document.read_uri(uri)?,
+                // Only the first buffer of a .glb can be its binary chunk
+                None if index == 0 => binary_chunk
+                    .ok_or_else(|| parse_error(String::from("buffer 0 has no data")))?
+                    .to_vec(),
+                None => return Err(parse_error(format!("buffer {} has no data", index))),
+            };
+
+            let length = buffer
+                .get("byteLength")
+                .and_then(Json::as_usize)
+                .unwrap_or(bytes.len());
+            if bytes.len() < length {
+                return Err(parse_error(format!(
+                    "buffer {} has {} bytes instead of {}",
+                    index,
+                    bytes.len(),
+                    length
+                )));
+            }
+            buffers.push(bytes);
+        }
+
+        document.buffers = buffers;
+        Ok(document)
+    }
+
+    // Data URIs (base64 only) or files relative to the glTF file
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, Error> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (header, payload) = data
+                .split_once(',')
+                .ok_or_else(|| parse_error(String::from("data URI without data")))?;
+            if !header.ends_with(";base64") {
+                return Err(Error::UnsupportedFormat(String::from(
+                    "data URIs that aren't base64",
+                )));
+            }
+            decode_base64(payload)
+        } else {
+            Ok(std::fs::read(self.directory.join(percent_decode(uri)))?)
+        }
+    }
+
+    // The bytes of a buffer view
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), Error> {
+        let view = item(&self.json, "bufferViews", "buffer view", index)?;
+
+        let buffer_index = view
+            .get("buffer")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| parse_error(format!("buffer view {} has no buffer", index)))?;
+        let buffer = self.buffers.get(buffer_index).ok_or(Error::InvalidIndex {
+            what: "buffer",
+            index: buffer_index,
+            len: self.buffers.len(),
+        })?;
+
+        let offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let length = view
+            .get("byteLength")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| parse_error(format!("buffer view {} has no length", index)))?;
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| parse_error(format!("buffer view {} is past its buffer", index)))?;
+
+        let stride = view.get("byteStride").and_then(Json::as_usize);
+        Ok((bytes, stride))
+    }
+
+    // count is the number of elements the accessor must have, when the caller knows it
+    // (e.g. one per vertex). Only those accessors may go without a buffer view: the
+    // others would make as many zeros as the file asks for
+    fn accessor(&self, index: usize, count: Option<usize>) -> Result<AccessorData<'_>, Error> {
+        let accessor = item(&self.json, "accessors", "accessor", index)?;
+        let error = |what: &str| parse_error(format!("accessor {} {}", index, what));
+
+        if accessor.get("sparse").is_some() {
+            return Err(Error::UnsupportedFormat(String::from(
+                "sparse glTF accessors",
+            )));
+        }
+
+        let expected_count = count;
+        let count = accessor
+            .get("count")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| error("has no count"))?;
+        if let Some(expected) = expected_count.filter(|&expected| expected != count) {
+            return Err(error(&format!(
+                "has {} elements instead of {}",
+                count, expected
+            )));
+        }
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| error("has no component type"))?;
+        let component_size = AccessorData::component_size(component_type)
+            .ok_or_else(|| error("has an unknown component type"))?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return Err(error("has an unknown type")),
+        };
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        let element_size = components * component_size;
+        if count.checked_mul(element_size).is_none() {
+            return Err(error("is too large"));
+        }
+
+        // Without a buffer view, every value is 0
+        let Some(view) = accessor.get("bufferView").and_then(Json::as_usize) else {
+            if expected_count.is_none() {
+                return Err(error("has no buffer view"));
+            }
+            return Ok(AccessorData {
+                bytes: &[0; 16 * 4],
+                count,
+                components,
+                component_type,
+                normalized,
+                stride: 0,
+            });
+        };
+
+        let (bytes, stride) = self.buffer_view(view)?;
+        let stride = stride.unwrap_or(element_size);
+        let offset = accessor
+            .get("byteOffset")
+            .and_then(Json::as_usize)
+            .unwrap_or(0);
+
+        // The end of the last element
+        let end = count
+            .checked_sub(1)
+            .map_or(Some(0), |last| {
+                last.checked_mul(stride)?
+                    .checked_add(offset)?
+                    .checked_add(element_size)
+            })
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| error("is past its buffer view"))?;
+
+        Ok(AccessorData {
+            bytes: &bytes[offset.min(end)..end],
+            count,
+            components,
+            component_type,
+            normalized,
+            stride,
+        })
+    }
+
+    // The values of an accessor, which must have one of the given numbers of components
+    // (and count elements, see accessor)
+    fn floats(
+        &self,
+        index: usize,
+        components: &[usize],
+        count: Option<usize>,
+    ) -> Result<(usize, Vec<f32>), Error> {
+        let accessor = self.accessor(index, count)?;
+        if !components.contains(&accessor.components) {
+            return Err(parse_error(format!(
+                "accessor {} has {} components instead of {:?}",
+                index, accessor.components, components
+            )));
+        }
+        Ok((accessor.components, accessor.floats()))
+    }
+
+    fn indices(&self, index: usize) -> Result<Vec<usize>, Error> {
+        let accessor = self.accessor(index, None)?;
+        if accessor.components != 1 || accessor.component_type == 5126 {
+            return Err(parse_error(format!(
+                "accessor {} can't hold indices",
+                index
+            )));
+        }
+        Ok((0..accessor.count)
+            .map(|element| accessor.component(element, 0) as usize)
+            .collect())
+    }
+
+    fn scene(&self) -> Result<GltfScene, Error> {
+        // Every image is decoded once, even if several textures use it
+        let images: Vec<Option<Image>> = array(&self.json, "images")
+            .iter()
+            .enumerate()
+            .map(|(index, image)| self.load_image(index, image))
+            .collect();
+        let textures: Vec<Option<Texture>> = array(&self.json, "textures")
+            .iter()
+            .map(|texture| self.load_texture(texture, &images))
+            .collect();
+        let materials: Vec<Material> = array(&self.json, "materials")
+            .iter()
+            .enumerate()
+            .map(|(index, material)| load_material(index, material, &textures))
+            .collect();
+
+        let meshes = array(&self.json, "meshes")
+            .iter()
+            .enumerate()
+            .map(|(index, mesh)| self.load_mesh(index, mesh, &materials))
+            .collect::<Result<Vec<Mesh>, Error>>()?;
+
+        let nodes = array(&self.json, "nodes");
+        let roots: Vec<usize> = match self.json.get("scenes") {
+            Some(_) => {
+                let scene = self.json.get("scene").and_then(Json::as_usize).unwrap_or(0);
+                array(item(&self.json, "scenes", "scene", scene)?, "nodes")
+                    .iter()
+                    .filter_map(Json::as_usize)
+                    .collect()
+            }
+            // Without scenes, the nodes that aren't the child of any other one
+            None => (0..nodes.len())
+                .filter(|&index| {
+                    !nodes.iter().any(|node| {
+                        array(node, "children")
+                            .iter()
+                            .any(|child| child.as_usize() == Some(index))
+                    })
+                })
+                .collect(),
+        };
+
+        let mut scene = GltfScene {
+            meshes,
+            nodes: vec![],
+        };
+        // Nodes form separate trees, so a node reached twice has a second parent
+        // (or is part of a cycle). Walked with a stack, as the trees can be deep
+        let mut visited = vec![false; nodes.len()];
+        let mut stack: Vec<(usize, Matrix4x4)> = roots
+            .into_iter()
+            .rev()
+            .map(|root| (root, Matrix4x4::identity()))
+            .collect();
+
+        while let Some((index, parent)) = stack.pop() {
+            let node = item(&self.json, "nodes", "node", index)?;
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(parse_error(format!(
+                    "node {} is in the tree more than once",
+                    index
+                )));
+            }
+
+            let local = node_matrix(node);
+            let transform = parent * local;
+
+            if let Some(mesh) = node.get("mesh").and_then(Json::as_usize) {
+                if mesh >= scene.meshes.len() {
+                    return Err(Error::InvalidIndex {
+                        what: "mesh",
+                        index: mesh,
+                        len: scene.meshes.len(),
+                    });
+                }
+                scene.nodes.push(GltfNode {
+                    name: node
+                        .get("name")
+                        .and_then(Json::as_str)
+                        .map_or_else(|| format!("node {}", index), String::from),
+                    mesh,
+                    local,
+                    parent,
+                });
+            }
+
+            // Reversed, so the children come out of the stack in the order of the file
+            let children = array(node, "children").iter().filter_map(Json::as_usize);
+            stack.extend(children.rev().map(|child| (child, transform)));
+        }
+
+        Ok(scene)
+    }
+
+    fn load_image(&self, index: usize, image: &Json) -> Option<Image> {
+        let bytes = if let Some(uri) = image.get("uri").and_then(Json::as_str) {
+            self.read_uri(uri)
+        } else if let Some(view) = image.get("bufferView").and_then(Json::as_usize) {
+            self.buffer_view(view).map(|(bytes, _)| bytes.to_vec())
+        } else {
+            Err(parse_error(String::from("image without data")))
+        };
+
+        match bytes.and_then(|bytes| Ok(Image::decode(&bytes)?)) {
+            Ok(image) => Some(image),
+            Err(err) => {
+                eprintln!("Failed to load the glTF image {}: {}", index, err);
+                None
+            }
+        }
+    }
+
+    fn load_texture(&self, texture: &Json, images: &[Option<Image>]) -> Option<Texture> {
+        let image = texture
+            .get("source")
+            .and_then(Json::as_usize)
+            .and_then(|source| images.get(source))?
+            .as_ref()?;
+        let mut texture_data = Texture::new(image.pixels().clone(), image.width(), image.height());
+
+        let sampler = texture
+            .get("sampler")
+            .and_then(Json::as_usize)
+            .and_then(|sampler| array(&self.json, "samplers").get(sampler));
+        let sampler_value = |key| sampler.and_then(|s| s.get(key)).and_then(Json::as_usize);
+
+        texture_data.address_mode = match sampler_value("wrapS") {
+            Some(CLAMP_TO_EDGE) => AddressMode::Clamp,
+            Some(MIRRORED_REPEAT) => AddressMode::Mirror,
+            _ => AddressMode::Wrap,
+        };
+        // Mipmaps unless the minification filter doesn't use them, trilinear like the
+        // OBJ textures when it doesn't say otherwise
+        texture_data.filter = match sampler_value("minFilter") {
+            Some(NEAREST) => Filter::Nearest,
+            Some(LINEAR) => Filter::Bilinear,
+            Some(NEAREST_MIPMAP_NEAREST) => {
+                texture_data.generate_mipmaps();
+                Filter::Nearest
+            }
+            Some(LINEAR_MIPMAP_NEAREST) => {
+                texture_data.generate_mipmaps();
+                Filter::Bilinear
+            }
+            _ => {
+                texture_data.generate_mipmaps();
+                Filter::Trilinear
+            }
+        };
+
+        Some(texture_data)
+    }
+
+    fn load_mesh(&self, index: usize, json: &Json, materials: &[Material]) -> Result<Mesh, Error> {
+        let name = json
+            .get("name")
+            .and_then(Json::as_str)
+            .map_or_else(|| format!("mesh {}", index), String::from);
+
+        let mut parts = vec![];
+        for primitive in array(json, "primitives") {
+            let mode = primitive
+                .get("mode")
+                .and_then(Json::as_usize)
+                .unwrap_or(TRIANGLES);
+            if ![TRIANGLES, TRIANGLE_STRIP, TRIANGLE_FAN].contains(&mode) {
+                continue;
+            }
+            parts.push(self.load_primitive(primitive, mode)?);
+        }
+
+        let has_normals = parts.iter().all(|part| !part.normals.is_empty());
+        let has_tangents = parts.iter().all(|part| !part.tangents.is_empty());
+        let has_uvs = parts.iter().any(|part| !part.uvs.is_empty());
+        let has_colors = parts.iter().any(|part| !part.colors.is_empty());
+
+        let mut mesh = Mesh {
+            name: name.clone(),
+            ..Default::default()
+        };
+        // Where each material of the file (and the default one, at the end) went
+        // in the mesh's materials
+        let mut used_materials: Vec<Option<usize>> = vec![None; materials.len() + 1];
+
+        for part in parts {
+            let offset = mesh.vertices.len();
+            mesh.vertices.extend(&part.positions);
+            let vertex_count = mesh.vertices.len();
+
+            if has_normals {
+                mesh.normals.extend(part.normals);
+            }
+            if has_tangents {
+                mesh.tangents.extend(part.tangents);
+            }
+            if has_uvs {
+                if part.uvs.is_empty() {
+                    mesh.uvs.resize(vertex_count, Vec2::zero());
+                } else {
+                    mesh.uvs.extend(part.uvs);
+                }
+            }
+            if has_colors {
+                if part.colors.is_empty() {
+                    mesh.colors
+                        .resize(vertex_count, Vec4::new(1.0, 1.0, 1.0, 1.0));
+                } else {
+                    mesh.colors.extend(part.colors);
+                }
+            }
+
+            mesh.indices
+                .extend(part.indices.iter().map(|&index| offset + index));
+
+            let file_id = part
+                .material
+                .filter(|&id| id < materials.len())
+                .unwrap_or(materials.len());
+            let material_id = *used_materials[file_id].get_or_insert_with(|| {
+                mesh.materials
+                    .push(materials.get(file_id).cloned().unwrap_or_default());
+                mesh.materials.len() - 1
+            });
+            mesh.material_ids
+                .extend(std::iter::repeat_n(material_id, part.indices.len() / 3));
+        }
+
+        mesh.groups.push(MeshGroup {
+            name,
+            triangles: 0..mesh.material_ids.len(),
+        });
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    fn load_primitive(&self, primitive: &Json, mode: usize) -> Result<Primitive, Error> {
+        let attributes = primitive.get("attributes");
+        let attribute = |name| {
+            attributes
+                .and_then(|attributes| attributes.get(name))
+                .and_then(Json::as_usize)
+        };
+
+        let position = attribute("POSITION")
+            .ok_or_else(|| parse_error(String::from("primitive without positions")))?;
+        let (_, positions) = self.floats(position, &[3], None)?;
+        let positions: Vec<Vec3> = positions
+            .chunks_exact(3)
+            .map(|p| Vec3::new(p[0], p[1], p[2]))
+            .collect();
+        let vertex_count = positions.len();
+
+        // Every attribute has one value per vertex
+        let read = |name, components: &[usize]| -> Result<(usize, Vec<f32>), Error> {
+            let Some(index) = attribute(name) else {
+                return Ok((0, vec![]));
+            };
+            self.floats(index, components, Some(vertex_count))
+        };
+
+        let (_, normals) = read("NORMAL", &[3])?;
+        let (_, tangents) = read("TANGENT", &[4])?;
+        let (_, uvs) = read("TEXCOORD_0", &[2])?;
+        let (color_components, colors) = read("COLOR_0", &[3, 4])?;
+
+        let vertex_indices = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(indices) => self.indices(indices)?,
+            None => (0..vertex_count).collect(),
+        };
+        let indices = match mode {
+            TRIANGLE_STRIP => (2..vertex_indices.len())
+                .flat_map(|i| {
+                    // Every other triangle is flipped, to keep them all counter-clockwise
+                    if i % 2 == 0 {
+                        [
+                            vertex_indices[i - 2],
+                            vertex_indices[i - 1],
+                            vertex_indices[i],
+                        ]
+                    } else {
+                        [
+                            vertex_indices[i - 1],
+                            vertex_indices[i - 2],
+                            vertex_indices[i],
+                        ]
+                    }
+                })
+                .collect(),
+            TRIANGLE_FAN => (2..vertex_indices.len())
+                .flat_map(|i| [vertex_indices[0], vertex_indices[i - 1], vertex_indices[i]])
+                .collect(),
+            _ => {
+                let mut indices = vertex_indices;
+                indices.truncate(indices.len() / 3 * 3);
+                indices
+            }
+        };
+
+        Ok(Primitive {
+            positions,
+            normals: normals
+                .chunks_exact(3)
+                .map(|n| Vec3::new(n[0], n[1], n[2]))
+                .collect(),
+            tangents: tangents
+                .chunks_exact(4)
+                .map(|t| Vec4::new(t[0], t[1], t[2], t[3]))
+                .collect(),
+            // glTF's v goes down from the top of the image, the textures' v goes up
+            uvs: uvs
+                .chunks_exact(2)
+                .map(|t| Vec2::new(t[0], 1.0 - t[1]))
+                .collect(),
+            colors: colors
+                .chunks_exact(color_components.max(1))
+                .map(|c| Vec4::new(c[0], c[1], c[2], c.get(3).copied().unwrap_or(1.0)))
+                .collect(),
+            indices,
+            material: primitive.get("material").and_then(Json::as_usize),
+        })
+    }
+}
+
+// One primitive of a glTF mesh, as triangles
+struct Primitive {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    tangents: Vec<Vec4>,
+    uvs: Vec<Vec2>,
+    colors: Vec<Vec4>,
+    indices: Vec<usize>,
+    material: Option<usize>,
+}
+
+// The node's matrix, or its translation, rotation and scale
+fn node_matrix(node: &Json) -> Matrix4x4 {
+    if let Some(matrix) = node.get("matrix") {
+        // Stored column by column
+        let identity: [f32; 16] = std::array::from_fn(|i| if i % 5 == 0 { 1.0 } else { 0.0 });
+        let values = numbers(Some(matrix), identity);
+        let mut result = Matrix4x4::identity();
+        for (i, value) in values.into_iter().enumerate() {
+            result.data[i % 4][i / 4] = value;
+        }
+        return result;
+    }
+
+    let [x, y, z] = numbers(node.get("translation"), [0.0; 3]);
+    let [qx, qy, qz, qw] = numbers(node.get("rotation"), [0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = numbers(node.get("scale"), [1.0; 3]);
+
+    ModelMatrix::from_rotation(
+        Vec3::new(x, y, z),
+        Quat::new(qx, qy, qz, qw).normalize(),
+        Vec3::new(sx, sy, sz),
+    )
+    .get_model_matrix()
+}
+
+// The metallic-roughness material, and the Blinn-Phong colors the default shader
+// uses: the base color as diffuse, a specular going from a dull white for dielectrics
+// to the base color for metals, and a shininess that goes down with the roughness
+fn load_material(index: usize, json: &Json, textures: &[Option<Texture>]) -> Material {
+    let default = Material::default();
+    let pbr = json.get("pbrMetallicRoughness");
+    let texture = |info: Option<&Json>| {
+        info.and_then(|info| info.get("index"))
+            .and_then(Json::as_usize)
+            .and_then(|index| textures.get(index).cloned().flatten())
+    };
+
+    let [r, g, b, a] = numbers(pbr.and_then(|pbr| pbr.get("baseColorFactor")), [1.0; 4]);
+    let base = Vec4::new(r, g, b, 1.0);
+    let pbr_number = |key, default: f32| {
+        pbr.and_then(|pbr| pbr.get(key))
+            .and_then(Json::as_f32)
+            .unwrap_or(default)
+    };
+    let metallic = pbr_number("metallicFactor", 1.0).clamp(0.0, 1.0);
+    let roughness = pbr_number("roughnessFactor", 1.0).clamp(0.0, 1.0);
+    let [er, eg, eb] = numbers(json.get("emissiveFactor"), [0.0; 3]);
+
+    // Blinn-Phong exponent matching the width of the highlight (alpha = roughness²)
+    let alpha = (roughness * roughness).max(0.01);
+    let shininess = 2.0 / (alpha * alpha) - 2.0;
+
+    // Blending isn't supported, so blended materials are cut out like masked ones
+    // (with the shader's cutoff, alphaCutoff is only for MASK)
+    let (opacity, alpha_mode, alpha_cutoff) = match json.get("alphaMode").and_then(Json::as_str) {
+        Some("MASK") => {
+            let cutoff = json
+                .get("alphaCutoff")
+                .and_then(Json::as_f32)
+                .unwrap_or(0.5);
+            (a, AlphaMode::Mask, Some(cutoff))
+        }
+        Some("BLEND") => (a, AlphaMode::Mask, None),
+        _ => (1.0, AlphaMode::Opaque, None),
+    };
+
+    Material {
+        name: json
+            .get("name")
+            .and_then(Json::as_str)
+            .map_or_else(|| format!("material {}", index), String::from),
+        ambient: default.ambient,
+        diffuse: base,
+        specular: Vec4::new(0.04, 0.04, 0.04, 1.0).lerp(base, metallic),
+        shininess,
+        opacity,
+        alpha_mode,
+        alpha_cutoff,
+        emissive: Vec4::new(er, eg, eb, 1.0),
+        metallic,
+        roughness,
+        ambient_texture: None,
+        diffuse_texture: texture(pbr.and_then(|pbr| pbr.get("baseColorTexture"))),
+        specular_texture: None,
+        normal_texture: texture(json.get("normalTexture")),
+        metallic_roughness_texture: texture(
+            pbr.and_then(|pbr| pbr.get("metallicRoughnessTexture")),
+        ),
+        emissive_texture: texture(json.get("emissiveTexture")),
+    }
+}
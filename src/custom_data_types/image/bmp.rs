@@ -0,0 +1,177 @@
+use super::{Image, ImageError, Reader, checked_size};
+use crate::custom_data_types::color::Color;
+
+// BI_RGB and BI_BITFIELDS, the RLE and embedded JPEG/PNG compressions aren't handled
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+// Decodes uncompressed 1, 4, 8 (palette), 16, 24 and 32 bits per pixel BMPs,
+// with any of the usual info headers (OS/2 core, v1 to v5)
+pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+    let mut reader = Reader::new(bytes);
+
+    // File header
+    reader.take(2)?; // "BM"
+    let _file_size = reader.u32_le()?;
+    reader.take(4)?; // Reserved
+    let data_offset = reader.u32_le()? as usize;
+
+    // Info header
+    let header_start = 14;
+    let header_size = reader.u32_le()? as usize;
+
+    let (width, height, bit_count, compression) = if header_size == 12 {
+        // OS/2 BITMAPCOREHEADER
+        let width = reader.u16_le()? as i32;
+        let height = reader.u16_le()? as i32;
+        let _planes = reader.u16_le()?;
+        let bit_count = reader.u16_le()?;
+        (width, height, bit_count, BI_RGB)
+    } else if header_size >= 40 {
+        let width = reader.i32_le()?;
+        let height = reader.i32_le()?;
+        let _planes = reader.u16_le()?;
+        let bit_count = reader.u16_le()?;
+        let compression = reader.u32_le()?;
+        (width, height, bit_count, compression)
+    } else {
+        return Err(ImageError::Unsupported(format!(
+            "BMP info header of {} bytes",
+            header_size
+        )));
+    };
+
+    if width <= 0 || height == 0 {
+        return Err(ImageError::Malformed(format!(
+            "invalid BMP size {}x{}",
+            width, height
+        )));
+    }
+
+    // A negative height means the rows are stored top to bottom
+    let top_to_bottom = height < 0;
+    let width = width as usize;
+    let height = height.unsigned_abs() as usize;
+    let size = checked_size(width, height)?;
+
+    if compression != BI_RGB && compression != BI_BITFIELDS && compression != BI_ALPHABITFIELDS {
+        return Err(ImageError::Unsupported(format!(
+            "BMP compression {}",
+            compression
+        )));
+    }
+    if !matches!(bit_count, 1 | 4 | 8 | 16 | 24 | 32) {
+        return Err(ImageError::Unsupported(format!(
+            "BMP with {} bits per pixel",
+            bit_count
+        )));
+    }
+
+    let mut colors_used = 0;
+    if header_size >= 40 {
+        reader.seek(header_start + 32)?;
+        colors_used = reader.u32_le()? as usize;
+    }
+
+    // Channel masks (r, g, b, a): either right after a v1 header, or inside the v2+ headers
+    let mut masks = match bit_count {
+        16 => [0x7c00, 0x03e0, 0x001f, 0],
+        _ => [0x00ff0000, 0x0000ff00, 0x000000ff, 0],
+    };
+    if compression == BI_BITFIELDS || compression == BI_ALPHABITFIELDS {
+        reader.seek(header_start + 40)?;
+        let mask_count = if compression == BI_ALPHABITFIELDS || header_size >= 56 {
+            4
+        } else {
+            3
+        };
+        for mask in masks.iter_mut().take(mask_count) {
+            *mask = reader.u32_le()?;
+        }
+    } else if header_size >= 56 && bit_count == 32 {
+        // Some writers put a valid alpha mask in the v3+ headers even for BI_RGB
+        reader.seek(header_start + 52)?;
+        masks[3] = reader.u32_le()?;
+    }
+
+    // Palette (BGR0, or BGR for the core header), right after the info header
+    let mut palette = vec![];
+    if bit_count <= 8 {
+        let entry_size = if header_size == 12 { 3 } else { 4 };
+        let count = if colors_used == 0 {
+            1 << bit_count
+        } else {
+            colors_used.min(1 << bit_count)
+        };
+
+        let mut palette_start = header_start + header_size;
+        if compression == BI_BITFIELDS && header_size == 40 {
+            palette_start += 12;
+        }
+        reader.seek(palette_start)?;
+
+        for _ in 0..count {
+            let entry = reader.take(entry_size)?;
+            palette.push(Color::new(entry[2], entry[1], entry[0], 255));
+        }
+    }
+
+    // Rows are padded to 4 bytes
+    let row_bytes = (width * bit_count as usize).div_ceil(32) * 4;
+    reader.seek(data_offset)?;
+    let data = reader.take(row_bytes * height)?;
+
+    let mut pixels = Vec::with_capacity(size);
+    for y in 0..height {
+        let stored_row = if top_to_bottom { y } else { height - 1 - y };
+        let row = &data[stored_row * row_bytes..(stored_row + 1) * row_bytes];
+
+        for x in 0..width {
+            let color = match bit_count {
+                1 | 4 | 8 => {
+                    let bits = bit_count as usize;
+                    let bit = x * bits;
+                    let shift = 8 - bits - bit % 8;
+                    let index = ((row[bit / 8] >> shift) as usize) & ((1 << bits) - 1);
+                    *palette.get(index).ok_or(ImageError::Malformed(
+                        "BMP palette index out of range".to_string(),
+                    ))?
+                }
+                16 => {
+                    let value = u16::from_le_bytes([row[2 * x], row[2 * x + 1]]) as u32;
+                    masked_color(value, &masks)
+                }
+                24 => Color::new(row[3 * x + 2], row[3 * x + 1], row[3 * x], 255),
+                _ => {
+                    let b = &row[4 * x..4 * x + 4];
+                    masked_color(u32::from_le_bytes([b[0], b[1], b[2], b[3]]), &masks)
+                }
+            };
+            pixels.push(color);
+        }
+    }
+
+    Ok(Image::new(pixels, width, height))
+}
+
+// Extracts the r, g, b and a channels with their masks, scaling them to 8 bits
+// (an alpha mask of 0 means the image is opaque)
+fn masked_color(value: u32, masks: &[u32; 4]) -> Color {
+    let channel = |mask: u32, default: u8| -> u8 {
+        if mask == 0 {
+            return default;
+        }
+        let shift = mask.trailing_zeros();
+        let max = mask >> shift;
+        let c = (value & mask) >> shift;
+        ((c as u64 * 255 + max as u64 / 2) / max as u64) as u8
+    };
+
+    Color::new(
+        channel(masks[0], 0),
+        channel(masks[1], 0),
+        channel(masks[2], 0),
+        channel(masks[3], 255),
+    )
+}
@@ -0,0 +1,213 @@
+mod bmp;
+mod png;
+mod ppm;
+mod tga;
+mod zlib;
+
+use std::fmt;
+use std::path::Path;
+
+use super::color::Color;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    // Neither the magic bytes nor the extension match a format we can decode
+    UnknownFormat,
+    // The format is known, but this variant of it isn't handled (e.g. RLE BMPs)
+    Unsupported(String),
+    // The file ends before all the data announced by the header was read
+    UnexpectedEof,
+    // The header or the data doesn't make sense
+    Malformed(String),
+    // A checksum (PNG CRC / zlib Adler-32) didn't match
+    Checksum(&'static str),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "I/O error: {}", err),
+            ImageError::UnknownFormat => write!(f, "Unknown image format"),
+            ImageError::Unsupported(what) => write!(f, "Unsupported image: {}", what),
+            ImageError::UnexpectedEof => write!(f, "Unexpected end of the image data"),
+            ImageError::Malformed(what) => write!(f, "Malformed image: {}", what),
+            ImageError::Checksum(what) => write!(f, "{} checksum mismatch", what),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::Io(err)
+    }
+}
+
+// An RGBA8 image, stored row by row starting with the top row
+// (the same layout as the Texture texels)
+pub struct Image {
+    pixels: Vec<Color>,
+    width: usize,
+    height: usize,
+}
+
+impl Image {
+    pub fn new(pixels: Vec<Color>, width: usize, height: usize) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "Image size doesn't match the number of pixels"
+        );
+
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn pixels(&self) -> &Vec<Color> {
+        &self.pixels
+    }
+    pub fn into_pixels(self) -> Vec<Color> {
+        self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    // Loads a PNG, TGA, BMP or PPM (PBM/PGM too) file. The format is found from
+    // the magic bytes, TGA files don't have any so they are found from the extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Image, ImageError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+
+        let is_tga = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("tga"));
+
+        if is_tga {
+            tga::decode(&bytes)
+        } else {
+            Self::decode(&bytes)
+        }
+    }
+
+    // Saves as PNG or PPM, depending on the extension
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        let bytes = match extension.as_deref() {
+            Some("png") => self.encode_png(),
+            Some("ppm") => self.encode_ppm(),
+            _ => return Err(ImageError::UnknownFormat),
+        };
+
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    pub fn encode_png(&self) -> Vec<u8> {
+        png::encode(self)
+    }
+
+    pub fn encode_ppm(&self) -> Vec<u8> {
+        ppm::encode(self)
+    }
+
+    // Decodes an image from memory, the format being found from the magic bytes
+    pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+        if bytes.starts_with(&png::SIGNATURE) {
+            png::decode(bytes)
+        } else if bytes.starts_with(b"BM") {
+            bmp::decode(bytes)
+        } else if bytes.len() >= 2 && bytes[0] == b'P' && (b'1'..=b'6').contains(&bytes[1]) {
+            ppm::decode(bytes)
+        } else {
+            Err(ImageError::UnknownFormat)
+        }
+    }
+}
+
+// Little helper used by the decoders to read the headers without
+// having to check the length everywhere
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn seek(&mut self, position: usize) -> Result<(), ImageError> {
+        if position > self.bytes.len() {
+            return Err(ImageError::UnexpectedEof);
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ImageError> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(ImageError::UnexpectedEof)?;
+
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, ImageError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16_le(&mut self) -> Result<u16, ImageError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32_le(&mut self) -> Result<u32, ImageError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32_le(&mut self) -> Result<i32, ImageError> {
+        Ok(self.u32_le()? as i32)
+    }
+
+    fn u32_be(&mut self) -> Result<u32, ImageError> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+// Width * height, refusing sizes that are empty or too large to allocate
+fn checked_size(width: usize, height: usize) -> Result<usize, ImageError> {
+    // 2^28 pixels is already a 1 GB RGBA8 image
+    const MAX_PIXELS: usize = 1 << 28;
+
+    match width.checked_mul(height) {
+        Some(size) if size > 0 && size <= MAX_PIXELS => Ok(size),
+        _ => Err(ImageError::Malformed(format!(
+            "invalid image size {}x{}",
+            width, height
+        ))),
+    }
+}
@@ -0,0 +1,426 @@
+use super::zlib::{zlib_compress, zlib_decompress};
+use super::{Image, ImageError, Reader, checked_size};
+use crate::custom_data_types::color::Color;
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1, // Grayscale, palette
+            2 => 3,     // RGB
+            4 => 2,     // Grayscale + alpha
+            _ => 4,     // RGBA
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    // Bytes in one (not filtered) row of a pass of the given width
+    fn row_bytes(&self, width: usize) -> usize {
+        (width * self.bits_per_pixel()).div_ceil(8)
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+    let mut reader = Reader::new(bytes);
+    reader.seek(SIGNATURE.len())?;
+
+    let mut header = None;
+    let mut palette: Vec<Color> = vec![];
+    // tRNS: alpha values for the palette, or the one transparent gray / RGB value
+    let mut transparency: Vec<u8> = vec![];
+    let mut data = vec![];
+
+    loop {
+        let length = reader.u32_be()? as usize;
+        let chunk_type = reader.take(4)?;
+        let chunk_data = reader.take(length)?;
+        let crc = reader.u32_be()?;
+
+        if crc32(&[chunk_type, chunk_data]) != crc {
+            return Err(ImageError::Checksum("PNG chunk CRC"));
+        }
+
+        match chunk_type {
+            b"IHDR" => header = Some(parse_header(chunk_data)?),
+            b"PLTE" => {
+                if chunk_data.len() % 3 != 0 || chunk_data.len() > 256 * 3 {
+                    return Err(ImageError::Malformed("invalid PNG palette".to_string()));
+                }
+                palette = chunk_data
+                    .chunks(3)
+                    .map(|c| Color::new(c[0], c[1], c[2], 255))
+                    .collect();
+            }
+            b"tRNS" => transparency = chunk_data.to_vec(),
+            b"IDAT" => data.extend_from_slice(chunk_data),
+            b"IEND" => break,
+            _ => {
+                // Bit 5 of the first letter is 0 for chunks that can't be ignored
+                if chunk_type[0] & 0x20 == 0 {
+                    return Err(ImageError::Unsupported(format!(
+                        "critical PNG chunk {}",
+                        String::from_utf8_lossy(chunk_type)
+                    )));
+                }
+            }
+        }
+    }
+
+    let header = header.ok_or(ImageError::Malformed("PNG without IHDR".to_string()))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(ImageError::Malformed(
+            "palette PNG without PLTE".to_string(),
+        ));
+    }
+
+    for (alpha, color) in transparency.iter().zip(palette.iter_mut()) {
+        *color = Color::new(color.r(), color.g(), color.b(), *alpha);
+    }
+
+    // (start x, start y, step x, step y) of the 7 Adam7 passes
+    let passes: &[(usize, usize, usize, usize)] = if header.interlaced {
+        &[
+            (0, 0, 8, 8),
+            (4, 0, 8, 8),
+            (0, 4, 4, 8),
+            (2, 0, 4, 4),
+            (0, 2, 2, 4),
+            (1, 0, 2, 2),
+            (0, 1, 1, 2),
+        ]
+    } else {
+        &[(0, 0, 1, 1)]
+    };
+
+    // Every pass row starts with its filter byte. The decompressed data can't be larger
+    // than that, so a small file can't expand into gigabytes
+    let pass_size = |&(start_x, start_y, step_x, step_y): &(usize, usize, usize, usize)| {
+        let pass_width = (header.width + step_x - 1 - start_x) / step_x;
+        let pass_height = (header.height + step_y - 1 - start_y) / step_y;
+        if pass_width == 0 || pass_height == 0 {
+            0
+        } else {
+            (header.row_bytes(pass_width) + 1) * pass_height
+        }
+    };
+    let raw = zlib_decompress(&data, passes.iter().map(pass_size).sum())?;
+
+    let mut pixels = vec![Color::new(0, 0, 0, 255); header.width * header.height];
+
+    let mut offset = 0;
+    for &(start_x, start_y, step_x, step_y) in passes {
+        let pass_width = (header.width + step_x - 1 - start_x) / step_x;
+        let pass_height = (header.height + step_y - 1 - start_y) / step_y;
+        if pass_width == 0 || pass_height == 0 {
+            continue;
+        }
+
+        let row_bytes = header.row_bytes(pass_width);
+        let pass_size = (row_bytes + 1) * pass_height;
+        if offset + pass_size > raw.len() {
+            return Err(ImageError::UnexpectedEof);
+        }
+
+        let rows = unfilter(&header, &raw[offset..offset + pass_size], row_bytes)?;
+        offset += pass_size;
+
+        for (y, row) in rows.chunks(row_bytes).enumerate() {
+            for x in 0..pass_width {
+                let color = pixel(&header, row, x, &palette, &transparency)?;
+                let (image_x, image_y) = (start_x + x * step_x, start_y + y * step_y);
+                pixels[image_y * header.width + image_x] = color;
+            }
+        }
+    }
+
+    Ok(Image::new(pixels, header.width, header.height))
+}
+
+// Encodes an 8 bit RGB PNG, or RGBA if some pixels aren't opaque.
+// Every row gets the filter that makes it the "smallest", as libpng does
+pub fn encode(image: &Image) -> Vec<u8> {
+    let opaque = image.pixels().iter().all(|color| color.a() == 255);
+    let bpp = if opaque { 3 } else { 4 };
+    let row_bytes = image.width() * bpp;
+
+    let mut rows = Vec::with_capacity(row_bytes * image.height());
+    for color in image.pixels() {
+        rows.extend_from_slice(&[color.r(), color.g(), color.b()]);
+        if !opaque {
+            rows.push(color.a());
+        }
+    }
+
+    let mut data = Vec::with_capacity((row_bytes + 1) * image.height());
+    let mut candidate = vec![0u8; row_bytes];
+    let mut best = vec![0u8; row_bytes];
+    for y in 0..image.height() {
+        let current = &rows[y * row_bytes..(y + 1) * row_bytes];
+        let previous = (y > 0).then(|| &rows[(y - 1) * row_bytes..y * row_bytes]);
+
+        let mut best_filter = 0;
+        let mut best_cost = u64::MAX;
+        for filter in 0..5u8 {
+            for x in 0..row_bytes {
+                let left = if x >= bpp { current[x - bpp] } else { 0 };
+                let up = previous.map_or(0, |p| p[x]);
+                let up_left = if x >= bpp {
+                    previous.map_or(0, |p| p[x - bpp])
+                } else {
+                    0
+                };
+
+                candidate[x] = match filter {
+                    0 => current[x],
+                    1 => current[x].wrapping_sub(left),
+                    2 => current[x].wrapping_sub(up),
+                    3 => current[x].wrapping_sub(((left as u16 + up as u16) / 2) as u8),
+                    _ => current[x].wrapping_sub(paeth(left, up, up_left)),
+                };
+            }
+
+            // Sum of the bytes as signed values, small differences compress well
+            let cost = candidate
+                .iter()
+                .map(|&byte| (byte as i8).unsigned_abs() as u64)
+                .sum();
+            if cost < best_cost {
+                best_cost = cost;
+                best_filter = filter;
+                best.copy_from_slice(&candidate);
+            }
+        }
+
+        data.push(best_filter);
+        data.extend_from_slice(&best);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width() as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height() as u32).to_be_bytes());
+    // Bit depth, color type, compression, filter method, no interlacing
+    header.extend_from_slice(&[8, if opaque { 2 } else { 6 }, 0, 0, 0]);
+
+    let mut bytes = SIGNATURE.to_vec();
+    write_chunk(&mut bytes, b"IHDR", &header);
+    write_chunk(&mut bytes, b"IDAT", &zlib_compress(&data));
+    write_chunk(&mut bytes, b"IEND", &[]);
+    bytes
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(chunk_type);
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(&crc32(&[chunk_type, data]).to_be_bytes());
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, ImageError> {
+    let mut reader = Reader::new(data);
+    let width = reader.u32_be()? as usize;
+    let height = reader.u32_be()? as usize;
+    let bit_depth = reader.u8()?;
+    let color_type = reader.u8()?;
+    let compression = reader.u8()?;
+    let filter = reader.u8()?;
+    let interlace = reader.u8()?;
+
+    checked_size(width, height)?;
+
+    let valid_depth = match color_type {
+        0 => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+        3 => matches!(bit_depth, 1 | 2 | 4 | 8),
+        2 | 4 | 6 => matches!(bit_depth, 8 | 16),
+        _ => false,
+    };
+    if !valid_depth {
+        return Err(ImageError::Malformed(format!(
+            "invalid PNG color type {} with bit depth {}",
+            color_type, bit_depth
+        )));
+    }
+    if compression != 0 || filter != 0 || interlace > 1 {
+        return Err(ImageError::Malformed(
+            "invalid PNG compression, filter or interlace method".to_string(),
+        ));
+    }
+
+    Ok(Header {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        interlaced: interlace == 1,
+    })
+}
+
+// Undoes the per row filters, returning the rows without their filter type byte
+fn unfilter(header: &Header, data: &[u8], row_bytes: usize) -> Result<Vec<u8>, ImageError> {
+    // Filters work on bytes, with the "left" byte being the same byte of the previous pixel
+    let bpp = header.bits_per_pixel().div_ceil(8);
+    let mut output = vec![0u8; row_bytes * (data.len() / (row_bytes + 1))];
+
+    for (y, row) in data.chunks(row_bytes + 1).enumerate() {
+        let filter = row[0];
+        let row = &row[1..];
+        let (previous, current) = output.split_at_mut(y * row_bytes);
+        let previous = if y == 0 {
+            None
+        } else {
+            Some(&previous[(y - 1) * row_bytes..])
+        };
+        let current = &mut current[..row_bytes];
+
+        for x in 0..row_bytes {
+            let left = if x >= bpp { current[x - bpp] } else { 0 };
+            let up = previous.map_or(0, |p| p[x]);
+            let up_left = if x >= bpp {
+                previous.map_or(0, |p| p[x - bpp])
+            } else {
+                0
+            };
+
+            current[x] = match filter {
+                0 => row[x],
+                1 => row[x].wrapping_add(left),
+                2 => row[x].wrapping_add(up),
+                3 => row[x].wrapping_add(((left as u16 + up as u16) / 2) as u8),
+                4 => row[x].wrapping_add(paeth(left, up, up_left)),
+                _ => {
+                    return Err(ImageError::Malformed(format!(
+                        "invalid PNG filter type {}",
+                        filter
+                    )));
+                }
+            };
+        }
+    }
+
+    Ok(output)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// Reads the sample (channel value) at index from an unfiltered row,
+// as stored (not scaled to 8 bits)
+fn sample(row: &[u8], index: usize, bit_depth: u8) -> u16 {
+    match bit_depth {
+        16 => u16::from_be_bytes([row[2 * index], row[2 * index + 1]]),
+        8 => row[index] as u16,
+        _ => {
+            // Packed from the most significant bit
+            let bits = bit_depth as usize;
+            let bit = index * bits;
+            let byte = row[bit / 8];
+            let shift = 8 - bits - bit % 8;
+            ((byte >> shift) & ((1 << bits) - 1) as u8) as u16
+        }
+    }
+}
+
+fn pixel(
+    header: &Header,
+    row: &[u8],
+    x: usize,
+    palette: &[Color],
+    transparency: &[u8],
+) -> Result<Color, ImageError> {
+    let depth = header.bit_depth;
+    let channels = header.channels();
+    let max = ((1u32 << depth) - 1) as u16;
+    let to_u8 = |value: u16| ((value as u32 * 255 + max as u32 / 2) / max as u32) as u8;
+    let s = |channel: usize| sample(row, x * channels + channel, depth);
+
+    // The tRNS value is stored as 16 bits no matter the bit depth
+    let key = |index: usize| {
+        transparency
+            .get(2 * index..2 * index + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+
+    let color = match header.color_type {
+        0 => {
+            let gray = s(0);
+            let alpha = if key(0) == Some(gray) { 0 } else { 255 };
+            let gray = to_u8(gray);
+            Color::new(gray, gray, gray, alpha)
+        }
+        2 => {
+            let (r, g, b) = (s(0), s(1), s(2));
+            let is_key = transparency.len() >= 6
+                && key(0) == Some(r)
+                && key(1) == Some(g)
+                && key(2) == Some(b);
+            let alpha = if is_key { 0 } else { 255 };
+            Color::new(to_u8(r), to_u8(g), to_u8(b), alpha)
+        }
+        3 => *palette.get(s(0) as usize).ok_or(ImageError::Malformed(
+            "PNG palette index out of range".to_string(),
+        ))?,
+        4 => {
+            let gray = to_u8(s(0));
+            Color::new(gray, gray, gray, to_u8(s(1)))
+        }
+        _ => Color::new(to_u8(s(0)), to_u8(s(1)), to_u8(s(2)), to_u8(s(3))),
+    };
+
+    Ok(color)
+}
+
+// CRC-32 lookup table, built at compile time
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+// CRC-32 (the one used by PNG and zlib's crc32) over several slices
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for part in parts {
+        for &byte in part.iter() {
+            crc = CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+    }
+
+    crc ^ 0xffffffff
+}
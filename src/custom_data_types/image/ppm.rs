@@ -0,0 +1,171 @@
+use super::{Image, ImageError, checked_size};
+use crate::custom_data_types::color::Color;
+
+// Encodes a binary PPM (P6), the alpha channel is dropped
+pub fn encode(image: &Image) -> Vec<u8> {
+    let mut bytes = format!("P6\n{} {}\n255\n", image.width(), image.height()).into_bytes();
+    bytes.reserve(image.width() * image.height() * 3);
+    for color in image.pixels() {
+        bytes.extend_from_slice(&[color.r(), color.g(), color.b()]);
+    }
+    bytes
+}
+
+// Decodes the netpbm formats: PBM (P1/P4), PGM (P2/P5) and PPM (P3/P6),
+// both ASCII and binary, with 8 or 16 bit samples
+pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+    let kind = bytes[1];
+    let mut position = 2;
+
+    let width = next_number(bytes, &mut position)?;
+    let height = next_number(bytes, &mut position)?;
+    let max_value = if kind == b'1' || kind == b'4' {
+        1
+    } else {
+        next_number(bytes, &mut position)?
+    };
+
+    let size = checked_size(width, height)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(ImageError::Malformed(format!(
+            "invalid netpbm max value {}",
+            max_value
+        )));
+    }
+
+    let channels = match kind {
+        b'3' | b'6' => 3,
+        _ => 1,
+    };
+    let binary = kind >= b'4';
+
+    // Scales a sample to 8 bits (PBM is the other way around: 1 is black)
+    let scale = |value: usize| -> u8 {
+        if kind == b'1' || kind == b'4' {
+            if value == 0 { 255 } else { 0 }
+        } else {
+            ((value.min(max_value) * 255 + max_value / 2) / max_value) as u8
+        }
+    };
+
+    let mut samples = Vec::with_capacity(size * channels);
+
+    if !binary {
+        for _ in 0..size * channels {
+            let value = if kind == b'1' {
+                // PBM samples don't need to be separated by whitespace
+                next_bit(bytes, &mut position)?
+            } else {
+                next_number(bytes, &mut position)?
+            };
+            samples.push(scale(value));
+        }
+    } else {
+        // Exactly one whitespace byte between the header and the data
+        position += 1;
+        let data = bytes.get(position..).unwrap_or(&[]);
+
+        if kind == b'4' {
+            // One bit per pixel, each row padded to a byte
+            let row_bytes = width.div_ceil(8);
+            if data.len() < row_bytes * height {
+                return Err(ImageError::UnexpectedEof);
+            }
+            for y in 0..height {
+                for x in 0..width {
+                    let byte = data[y * row_bytes + x / 8];
+                    samples.push(scale(((byte >> (7 - x % 8)) & 1) as usize));
+                }
+            }
+        } else {
+            let sample_bytes = if max_value > 255 { 2 } else { 1 };
+            let count = size * channels;
+            if data.len() < count * sample_bytes {
+                return Err(ImageError::UnexpectedEof);
+            }
+            for i in 0..count {
+                let value = if sample_bytes == 2 {
+                    u16::from_be_bytes([data[2 * i], data[2 * i + 1]]) as usize
+                } else {
+                    data[i] as usize
+                };
+                samples.push(scale(value));
+            }
+        }
+    }
+
+    let pixels = samples
+        .chunks(channels)
+        .map(|s| {
+            if channels == 3 {
+                Color::new(s[0], s[1], s[2], 255)
+            } else {
+                Color::new(s[0], s[0], s[0], 255)
+            }
+        })
+        .collect();
+
+    Ok(Image::new(pixels, width, height))
+}
+
+// Skips whitespace and # comments (which go to the end of the line)
+fn skip_whitespace(bytes: &[u8], position: &mut usize) {
+    while *position < bytes.len() {
+        match bytes[*position] {
+            b'#' => {
+                while *position < bytes.len() && bytes[*position] != b'\n' {
+                    *position += 1;
+                }
+            }
+            byte if byte.is_ascii_whitespace() => *position += 1,
+            _ => return,
+        }
+    }
+}
+
+fn next_number(bytes: &[u8], position: &mut usize) -> Result<usize, ImageError> {
+    skip_whitespace(bytes, position);
+
+    let start = *position;
+    while *position < bytes.len() && bytes[*position].is_ascii_digit() {
+        *position += 1;
+    }
+
+    if start == *position {
+        return if *position >= bytes.len() {
+            Err(ImageError::UnexpectedEof)
+        } else {
+            Err(ImageError::Malformed(format!(
+                "expected a number in the netpbm data, found {:?}",
+                bytes[*position] as char
+            )))
+        };
+    }
+
+    std::str::from_utf8(&bytes[start..*position])
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or(ImageError::Malformed(
+            "number too large in the netpbm data".to_string(),
+        ))
+}
+
+fn next_bit(bytes: &[u8], position: &mut usize) -> Result<usize, ImageError> {
+    skip_whitespace(bytes, position);
+
+    match bytes.get(*position) {
+        Some(b'0') => {
+            *position += 1;
+            Ok(0)
+        }
+        Some(b'1') => {
+            *position += 1;
+            Ok(1)
+        }
+        Some(&byte) => Err(ImageError::Malformed(format!(
+            "expected 0 or 1 in the PBM data, found {:?}",
+            byte as char
+        ))),
+        None => Err(ImageError::UnexpectedEof),
+    }
+}
@@ -0,0 +1,156 @@
+use super::{Image, ImageError, Reader, checked_size};
+use crate::custom_data_types::color::Color;
+
+// Decodes color-mapped, true-color and grayscale TGA files,
+// both uncompressed (types 1, 2, 3) and RLE compressed (types 9, 10, 11)
+pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+    let mut reader = Reader::new(bytes);
+
+    let id_length = reader.u8()? as usize;
+    let color_map_type = reader.u8()?;
+    let image_type = reader.u8()?;
+    let color_map_start = reader.u16_le()? as usize;
+    let color_map_length = reader.u16_le()? as usize;
+    let color_map_depth = reader.u8()?;
+    let _x_origin = reader.u16_le()?;
+    let _y_origin = reader.u16_le()?;
+    let width = reader.u16_le()? as usize;
+    let height = reader.u16_le()? as usize;
+    let pixel_depth = reader.u8()?;
+    let descriptor = reader.u8()?;
+
+    let size = checked_size(width, height)?;
+    if color_map_type > 1 {
+        return Err(ImageError::Malformed(format!(
+            "invalid TGA color map type {}",
+            color_map_type
+        )));
+    }
+
+    let (is_rle, kind) = match image_type {
+        1..=3 => (false, image_type),
+        9..=11 => (true, image_type - 8),
+        0 => return Err(ImageError::Malformed("TGA without image data".to_string())),
+        _ => {
+            return Err(ImageError::Unsupported(format!(
+                "TGA image type {}",
+                image_type
+            )));
+        }
+    };
+
+    reader.take(id_length)?;
+
+    let mut color_map = vec![];
+    if color_map_type == 1 {
+        let entry_bytes = (color_map_depth as usize).div_ceil(8);
+        for _ in 0..color_map_length {
+            let entry = reader.take(entry_bytes)?;
+            color_map.push(true_color(entry, color_map_depth)?);
+        }
+    }
+
+    if kind == 1 && color_map.is_empty() {
+        return Err(ImageError::Malformed(
+            "color-mapped TGA without a color map".to_string(),
+        ));
+    }
+
+    let valid_depth = match kind {
+        1 => matches!(pixel_depth, 8 | 16),
+        2 => matches!(pixel_depth, 15 | 16 | 24 | 32),
+        _ => matches!(pixel_depth, 8 | 16),
+    };
+    if !valid_depth {
+        return Err(ImageError::Unsupported(format!(
+            "TGA image type {} with {} bits per pixel",
+            image_type, pixel_depth
+        )));
+    }
+
+    let pixel_bytes = (pixel_depth as usize).div_ceil(8);
+    let to_color = |data: &[u8]| -> Result<Color, ImageError> {
+        match kind {
+            1 => {
+                let index = if pixel_bytes == 1 {
+                    data[0] as usize
+                } else {
+                    u16::from_le_bytes([data[0], data[1]]) as usize
+                };
+                index
+                    .checked_sub(color_map_start)
+                    .and_then(|index| color_map.get(index).copied())
+                    .ok_or(ImageError::Malformed(
+                        "TGA color map index out of range".to_string(),
+                    ))
+            }
+            2 => true_color(data, pixel_depth),
+            _ => {
+                let alpha = if pixel_bytes == 2 { data[1] } else { 255 };
+                Ok(Color::new(data[0], data[0], data[0], alpha))
+            }
+        }
+    };
+
+    // Pixels in the order they are stored in the file
+    let mut stored = Vec::with_capacity(size);
+    if is_rle {
+        while stored.len() < size {
+            let packet = reader.u8()?;
+            let count = (packet & 0x7f) as usize + 1;
+
+            if packet & 0x80 != 0 {
+                let color = to_color(reader.take(pixel_bytes)?)?;
+                stored.extend(std::iter::repeat_n(color, count));
+            } else {
+                for _ in 0..count {
+                    stored.push(to_color(reader.take(pixel_bytes)?)?);
+                }
+            }
+        }
+        // A packet is allowed to go over the end of a row, but not over the image
+        stored.truncate(size);
+    } else {
+        for _ in 0..size {
+            stored.push(to_color(reader.take(pixel_bytes)?)?);
+        }
+    }
+
+    // Bit 4: stored right to left, bit 5: stored top to bottom
+    let right_to_left = descriptor & 0x10 != 0;
+    let top_to_bottom = descriptor & 0x20 != 0;
+
+    let mut pixels = Vec::with_capacity(size);
+    for y in 0..height {
+        let row = if top_to_bottom { y } else { height - 1 - y };
+        for x in 0..width {
+            let column = if right_to_left { width - 1 - x } else { x };
+            pixels.push(stored[row * width + column]);
+        }
+    }
+
+    Ok(Image::new(pixels, width, height))
+}
+
+// TGA stores true-color pixels as BGR(A) or as 16 bit A1R5G5B5
+fn true_color(data: &[u8], depth: u8) -> Result<Color, ImageError> {
+    match depth {
+        15 | 16 => {
+            let value = u16::from_le_bytes([data[0], data[1]]);
+            let expand = |c: u16| ((c & 0x1f) as u32 * 255 / 31) as u8;
+            // The attribute bit isn't reliable as alpha, most files leave it at 0
+            Ok(Color::new(
+                expand(value >> 10),
+                expand(value >> 5),
+                expand(value),
+                255,
+            ))
+        }
+        24 => Ok(Color::new(data[2], data[1], data[0], 255)),
+        32 => Ok(Color::new(data[2], data[1], data[0], data[3])),
+        _ => Err(ImageError::Unsupported(format!(
+            "TGA {} bit color map entries",
+            depth
+        ))),
+    }
+}
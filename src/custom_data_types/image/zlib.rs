@@ -0,0 +1,519 @@
+// Zlib / DEFLATE (RFC 1950 / RFC 1951), only what the PNG decoder and encoder need
+use super::ImageError;
+
+// Reads the bits LSB first, as DEFLATE stores them
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn refill(&mut self) {
+        while self.bit_count <= 56 && self.position < self.bytes.len() {
+            self.bit_buffer |= (self.bytes[self.position] as u64) << self.bit_count;
+            self.position += 1;
+            self.bit_count += 8;
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, ImageError> {
+        if count == 0 {
+            return Ok(0);
+        }
+        if self.bit_count < count {
+            self.refill();
+            if self.bit_count < count {
+                return Err(ImageError::UnexpectedEof);
+            }
+        }
+
+        let value = (self.bit_buffer & ((1u64 << count) - 1)) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    // Drops the bits up to the next byte boundary (for the stored blocks)
+    fn align_to_byte(&mut self) {
+        let extra = self.bit_count % 8;
+        self.bit_buffer >>= extra;
+        self.bit_count -= extra;
+    }
+
+    fn byte(&mut self) -> Result<u8, ImageError> {
+        Ok(self.bits(8)? as u8)
+    }
+}
+
+// Canonical Huffman code, decoded one bit at a time with the
+// count / symbol tables (like zlib's "puff")
+struct Huffman {
+    // Number of codes of each length (0..=15)
+    counts: [u16; 16],
+    // Symbols sorted by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Over-subscribed codes can't be decoded
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(ImageError::Malformed(
+                    "over-subscribed Huffman code".to_string(),
+                ));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ImageError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err(ImageError::Malformed("invalid Huffman code".to_string()))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Order in which the code length code lengths are stored in a dynamic block
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// Decompresses a zlib stream (2 bytes header, DEFLATE data, Adler-32 checksum).
+// Decompressing more than max_size bytes is an error
+pub fn zlib_decompress(bytes: &[u8], max_size: usize) -> Result<Vec<u8>, ImageError> {
+    if bytes.len() < 6 {
+        return Err(ImageError::UnexpectedEof);
+    }
+
+    let (cmf, flg) = (bytes[0], bytes[1]);
+    if cmf & 0x0f != 8 || !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        return Err(ImageError::Malformed("invalid zlib header".to_string()));
+    }
+    if flg & 0x20 != 0 {
+        return Err(ImageError::Unsupported(
+            "zlib stream with a preset dictionary".to_string(),
+        ));
+    }
+
+    let mut reader = BitReader::new(&bytes[2..]);
+    let output = inflate(&mut reader, max_size)?;
+
+    // The checksum is right after the last (byte aligned) DEFLATE block
+    reader.align_to_byte();
+    let mut expected = 0u32;
+    for _ in 0..4 {
+        expected = (expected << 8) | reader.byte()? as u32;
+    }
+
+    if adler32(&output) != expected {
+        return Err(ImageError::Checksum("Adler-32"));
+    }
+
+    Ok(output)
+}
+
+fn inflate(reader: &mut BitReader, max_size: usize) -> Result<Vec<u8>, ImageError> {
+    let mut output = Vec::with_capacity(max_size);
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        let block_type = reader.bits(2)?;
+
+        match block_type {
+            0 => stored_block(reader, &mut output, max_size)?,
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                compressed_block(reader, &mut output, max_size, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(reader)?;
+                compressed_block(reader, &mut output, max_size, &literals, &distances)?;
+            }
+            _ => {
+                return Err(ImageError::Malformed(
+                    "invalid DEFLATE block type".to_string(),
+                ));
+            }
+        }
+
+        if is_final {
+            return Ok(output);
+        }
+    }
+}
+
+fn stored_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    max_size: usize,
+) -> Result<(), ImageError> {
+    reader.align_to_byte();
+    let length = reader.bits(16)?;
+    let complement = reader.bits(16)?;
+    if length != !complement & 0xffff {
+        return Err(ImageError::Malformed(
+            "stored DEFLATE block length mismatch".to_string(),
+        ));
+    }
+
+    if output.len() + length as usize > max_size {
+        return Err(too_large());
+    }
+    for _ in 0..length {
+        output.push(reader.byte()?);
+    }
+
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), ImageError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    if literal_count > 286 || distance_count > 30 {
+        return Err(ImageError::Malformed("too many DEFLATE codes".to_string()));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    // The literal/length and distance code lengths are stored together
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                if index == 0 {
+                    return Err(ImageError::Malformed(
+                        "DEFLATE length repeat with no previous length".to_string(),
+                    ));
+                }
+                (lengths[index - 1], 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+
+        if index + repeat > lengths.len() {
+            return Err(ImageError::Malformed(
+                "too many DEFLATE code lengths".to_string(),
+            ));
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+
+    if lengths[256] == 0 {
+        return Err(ImageError::Malformed(
+            "DEFLATE block without an end code".to_string(),
+        ));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    max_size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+
+        if symbol < 256 {
+            if output.len() == max_size {
+                return Err(too_large());
+            }
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(ImageError::Malformed(
+                "invalid DEFLATE length code".to_string(),
+            ));
+        }
+        let length =
+            LENGTH_BASE[symbol] as usize + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+        let symbol = distances.decode(reader)? as usize;
+        if symbol >= DISTANCE_BASE.len() {
+            return Err(ImageError::Malformed(
+                "invalid DEFLATE distance code".to_string(),
+            ));
+        }
+        let distance =
+            DISTANCE_BASE[symbol] as usize + reader.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+
+        if distance > output.len() {
+            return Err(ImageError::Malformed(
+                "DEFLATE distance goes before the start of the data".to_string(),
+            ));
+        }
+
+        if output.len() + length > max_size {
+            return Err(too_large());
+        }
+
+        // Byte by byte, the copy is allowed to overlap what it is writing
+        let start = output.len() - distance;
+        for i in 0..length {
+            output.push(output[start + i]);
+        }
+    }
+}
+
+fn too_large() -> ImageError {
+    ImageError::Malformed("DEFLATE data larger than expected".to_string())
+}
+
+// Writes the bits LSB first, the mirror of BitReader
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    // Huffman codes are stored starting with their most significant bit
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bit_buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+// Symbol of the fixed literal / length code (RFC 1951, 3.2.6)
+fn write_fixed_literal(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.code(0x30 + symbol, 8),
+        144..=255 => writer.code(0x190 + symbol - 144, 9),
+        256..=279 => writer.code(symbol - 256, 7),
+        _ => writer.code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    // The last base that isn't above the value
+    let length_code = LENGTH_BASE.partition_point(|&base| base as usize <= length) - 1;
+    write_fixed_literal(writer, 257 + length_code as u32);
+    writer.bits(
+        (length - LENGTH_BASE[length_code] as usize) as u32,
+        LENGTH_EXTRA[length_code] as u32,
+    );
+
+    let distance_code = DISTANCE_BASE.partition_point(|&base| base as usize <= distance) - 1;
+    writer.code(distance_code as u32, 5);
+    writer.bits(
+        (distance - DISTANCE_BASE[distance_code] as usize) as u32,
+        DISTANCE_EXTRA[distance_code] as u32,
+    );
+}
+
+// Compresses into a zlib stream made of a single fixed Huffman block. The matches
+// are found greedily with hash chains, which is plenty for the rendered images
+pub fn zlib_compress(bytes: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 32768;
+    const MIN_MATCH: usize = 3;
+    const MAX_MATCH: usize = 258;
+    const HASH_BITS: u32 = 15;
+    // How many earlier positions with the same hash are tried
+    const MAX_CHAIN: usize = 32;
+    const NONE: usize = usize::MAX;
+
+    // CMF: deflate with a 32K window, FLG: default level, no dictionary
+    let mut writer = BitWriter::new(vec![0x78, 0x9c]);
+    writer.bits(1, 1); // Final block
+    writer.bits(1, 2); // Fixed Huffman codes
+
+    let hash = |i: usize| -> usize {
+        let value = (bytes[i] as u32) << 16 | (bytes[i + 1] as u32) << 8 | bytes[i + 2] as u32;
+        (value.wrapping_mul(0x9e3779b1) >> (32 - HASH_BITS)) as usize
+    };
+    let mut head = vec![NONE; 1 << HASH_BITS];
+    let mut previous = vec![NONE; bytes.len()];
+    let insert = |i: usize, head: &mut [usize], previous: &mut [usize]| {
+        if i + MIN_MATCH <= bytes.len() {
+            let h = hash(i);
+            previous[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+
+        if i + MIN_MATCH <= bytes.len() {
+            let max_length = MAX_MATCH.min(bytes.len() - i);
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+
+            while candidate != NONE && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let length = bytes[candidate..]
+                    .iter()
+                    .zip(&bytes[i..i + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = i - candidate;
+                    if length == max_length {
+                        break;
+                    }
+                }
+                candidate = previous[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_length >= MIN_MATCH {
+            write_match(&mut writer, best_length, best_distance);
+            for j in i..i + best_length {
+                insert(j, &mut head, &mut previous);
+            }
+            i += best_length;
+        } else {
+            write_fixed_literal(&mut writer, bytes[i] as u32);
+            insert(i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+
+    write_fixed_literal(&mut writer, 256); // End of block
+
+    let mut output = writer.finish();
+    output.extend_from_slice(&adler32(bytes).to_be_bytes());
+    output
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // 5552 is the most bytes that can be summed before b could overflow
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+
+    (b << 16) | a
+}
@@ -0,0 +1,136 @@
+use super::matrices::Matrix4x4;
+use super::vec3::Vec3;
+use std::ops::Mul;
+
+// A 3x3 matrix (row major like Matrix4x4) for linear transforms of Vec3s that never
+// translate: rotations, scales and the normal matrix
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat3 {
+    pub data: [[f32; 3]; 3],
+}
+
+impl Mat3 {
+    pub fn identity() -> Self {
+        Self {
+            data: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self {
+            data: [[0.0; 3]; 3],
+        }
+    }
+
+    pub fn from_rows(x: Vec3, y: Vec3, z: Vec3) -> Self {
+        Self {
+            data: [x.to_array(), y.to_array(), z.to_array()],
+        }
+    }
+
+    pub fn from_columns(x: Vec3, y: Vec3, z: Vec3) -> Self {
+        Self::from_rows(x, y, z).transpose()
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self {
+            data: [
+                [scale.x, 0.0, 0.0],
+                [0.0, scale.y, 0.0],
+                [0.0, 0.0, scale.z],
+            ],
+        }
+    }
+
+    pub fn row(&self, index: usize) -> Vec3 {
+        Vec3::from(self.data[index])
+    }
+
+    pub fn column(&self, index: usize) -> Vec3 {
+        Vec3::new(
+            self.data[0][index],
+            self.data[1][index],
+            self.data[2][index],
+        )
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self::from_rows(self.column(0), self.column(1), self.column(2))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.row(0).dot(self.row(1).cross(self.row(2)))
+    }
+
+    // None if the matrix is singular. Only an exactly zero (or non-finite) determinant
+    // counts: a threshold would also reject small matrices that are fine, like a
+    // scale of 1e-5 whose determinant is 1e-15
+    pub fn inverse(&self) -> Option<Self> {
+        let (r0, r1, r2) = (self.row(0), self.row(1), self.row(2));
+
+        // The columns of the inverse are the cross products of the rows
+        let (c0, c1, c2) = (r1.cross(r2), r2.cross(r0), r0.cross(r1));
+        let determinant = r0.dot(c0);
+
+        let inv = 1.0 / determinant;
+        if !determinant.is_finite() || !inv.is_finite() {
+            return None;
+        }
+
+        Some(Self::from_rows(c0 * inv, c1 * inv, c2 * inv).transpose())
+    }
+
+    // Back to a Matrix4x4, with no translation
+    pub fn to_matrix4(&self) -> Matrix4x4 {
+        let mut matrix = Matrix4x4::identity();
+        for (row, values) in matrix.data.iter_mut().zip(self.data) {
+            row[..3].copy_from_slice(&values);
+        }
+        matrix
+    }
+}
+
+// The top left 3x3 of the matrix: its rotation and scale, without the translation
+impl From<Matrix4x4> for Mat3 {
+    fn from(matrix: Matrix4x4) -> Self {
+        let mut result = Mat3::zero();
+        for (row, values) in result.data.iter_mut().zip(matrix.data) {
+            row.copy_from_slice(&values[..3]);
+        }
+        result
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut result = Mat3::zero();
+
+        for row in 0..3 {
+            for col in 0..3 {
+                result.data[row][col] = self.row(row).dot(rhs.column(col));
+            }
+        }
+
+        result
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(
+            self.row(0).dot(rhs),
+            self.row(1).dot(rhs),
+            self.row(2).dot(rhs),
+        )
+    }
+}
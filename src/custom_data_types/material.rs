@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use super::image::Image;
+use super::texture::{Filter, Texture};
+use super::vec4::Vec4;
+
+// What the alpha of a material (its opacity, times the alpha of the diffuse texture
+// and of the vertex color) does, glTF's alphaMode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    // The alpha is ignored and the surface is drawn fully opaque
+    Opaque,
+    // Fragments whose alpha is below the material's alpha_cutoff are discarded
+    Mask,
+}
+
+// Surface properties of (a part of) a mesh, as described by an MTL file.
+// The colors are stored as (r, g, b, 1.0), with every channel in [0.0, 1.0]
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Vec4,
+    pub diffuse: Vec4,
+    pub specular: Vec4,
+    pub shininess: f32,
+    // 1.0 is fully opaque (MTL's "d")
+    pub opacity: f32,
+    pub alpha_mode: AlphaMode,
+    // The cutoff of masked materials, None uses the one of the shader
+    pub alpha_cutoff: Option<f32>,
+    // Light given off whatever the lighting, black for most materials
+    pub emissive: Vec4,
+    // The metallic-roughness parameters of glTF materials, kept for shaders that use
+    // them (the default one only uses the colors and shininess they are turned into)
+    pub metallic: f32,
+    pub roughness: f32,
+    pub ambient_texture: Option<Texture>,
+    pub diffuse_texture: Option<Texture>,
+    pub specular_texture: Option<Texture>,
+    pub normal_texture: Option<Texture>,
+    // Metalness in the blue channel and roughness in the green one, like in glTF
+    pub metallic_roughness_texture: Option<Texture>,
+    pub emissive_texture: Option<Texture>,
+}
+
+impl Default for Material {
+    // Light gray and matte, used for the faces that don't have a material
+    fn default() -> Self {
+        Self {
+            name: String::from("default"),
+            ambient: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            diffuse: Vec4::new(0.8, 0.8, 0.8, 1.0),
+            specular: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            shininess: 0.0,
+            opacity: 1.0,
+            alpha_mode: AlphaMode::Mask,
+            alpha_cutoff: None,
+            emissive: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            metallic: 0.0,
+            roughness: 1.0,
+            ambient_texture: None,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+            metallic_roughness_texture: None,
+            emissive_texture: None,
+        }
+    }
+}
+
+impl Material {
+    // Loads every material of an MTL file. The texture paths are relative to the file
+    pub fn load_mtl(path: &str) -> Vec<Material> {
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        match tobj::load_mtl(path) {
+            Ok((materials, _)) => materials
+                .iter()
+                .map(|material| Self::from_tobj(material, directory))
+                .collect(),
+            Err(err) => {
+                eprintln!("Failed to load the materials from {}: {}", path, err);
+                vec![]
+            }
+        }
+    }
+
+    // Converts a material parsed by tobj, loading its textures from the directory
+    pub fn from_tobj(material: &tobj::Material, directory: &Path) -> Self {
+        let default = Self::default();
+        let color = |c: Option<[f32; 3]>, default: Vec4| {
+            c.map_or(default, |c| Vec4::new(c[0], c[1], c[2], 1.0))
+        };
+        let texture = |name: &Option<String>| {
+            name.as_ref()
+                .and_then(|name| Self::load_texture(directory, name))
+        };
+
+        Self {
+            name: material.name.clone(),
+            ambient: color(material.ambient, default.ambient),
+            diffuse: color(material.diffuse, default.diffuse),
+            specular: color(material.specular, default.specular),
+            shininess: material.shininess.unwrap_or(default.shininess),
+            opacity: material.dissolve.unwrap_or(default.opacity),
+            ambient_texture: texture(&material.ambient_texture),
+            diffuse_texture: texture(&material.diffuse_texture),
+            specular_texture: texture(&material.specular_texture),
+            normal_texture: texture(&material.normal_texture),
+            ..default
+        }
+    }
+
+    // A texture that can't be loaded is skipped, so the material falls back to its colors
+    fn load_texture(directory: &Path, name: &str) -> Option<Texture> {
+        // MTL files made on Windows often use backslashes
+        let path = directory.join(name.replace('\\', "/"));
+
+        match Image::load(&path) {
+            Ok(image) => {
+                let mut texture = Texture::from_image(image);
+                texture.generate_mipmaps();
+                texture.filter = Filter::Trilinear;
+                Some(texture)
+            }
+            Err(err) => {
+                eprintln!("Failed to load the texture {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use super::mat3::Mat3;
+use super::quat::{EulerOrder, Quat};
+use super::vec3::Vec3;
 use super::vec4::Vec4;
 use std::ops::Mul;
 
@@ -6,78 +9,150 @@ pub struct Matrix4x4 {
     pub data: [[f32; 4]; 4],
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct ModelMatrix {
-    translation: Vec4,
-    angle: Vec4,
-    scale: Vec4,
+    translation: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+    // Applied over the translation, rotation and scale, e.g. the transform of the node
+    // an object hangs from in a scene file. Unlike them, it may shear
+    parent: Option<Matrix4x4>,
+    // Recomputed whenever the angle, the scale or the parent changes (see normal_matrix)
+    normal_matrix: Mat3,
 }
 
 impl ModelMatrix {
-    pub fn new(translation: Vec4, angle: Vec4, scale: Vec4) -> Self {
-        Self {
+    // angle holds Euler angles in radians, applied in XYZ order (see EulerOrder)
+    pub fn new(translation: Vec3, angle: Vec3, scale: Vec3) -> Self {
+        Self::from_rotation(translation, Quat::from_euler(angle, EulerOrder::XYZ), scale)
+    }
+
+    pub fn from_rotation(translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        let mut model = Self {
             translation,
-            angle,
+            rotation,
             scale,
+            parent: None,
+            normal_matrix: Mat3::identity(),
+        };
+        model.update_normal_matrix();
+        model
+    }
+
+    // Splits a matrix made of a translation, a rotation and a scale back into them.
+    // A shear (from a non-uniform scale under a rotation) can't be kept, it has to go
+    // in the parent (see update_parent). A mirroring is kept as a negative x scale
+    pub fn from_matrix(matrix: &Matrix4x4) -> Self {
+        let translation = Vec3::new(matrix.data[0][3], matrix.data[1][3], matrix.data[2][3]);
+
+        let linear = Mat3::from(*matrix);
+        let mut columns = [linear.column(0), linear.column(1), linear.column(2)];
+        let mut scale = Vec3::new(
+            columns[0].magnitude(),
+            columns[1].magnitude(),
+            columns[2].magnitude(),
+        );
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
         }
+
+        // A zero scale leaves nothing to take the rotation from
+        let rotation = if scale.x != 0.0 && scale.y != 0.0 && scale.z != 0.0 {
+            for (column, scale) in columns.iter_mut().zip(scale.to_array()) {
+                *column = *column / scale;
+            }
+            Quat::from_rotation_matrix(&Mat3::from_columns(columns[0], columns[1], columns[2]))
+        } else {
+            Quat::identity()
+        };
+
+        Self::from_rotation(translation, rotation, scale)
     }
 
-    // If there was a non-uniform scaling applied, then the inverse
-    // must be calculated for the normals.
-    pub fn inverse_needed_for_normals(&self) -> bool {
-        return !(self.scale.x == self.scale.y && self.scale.y == self.scale.z);
+    // The rotation as XYZ Euler angles
+    pub fn angle(&self) -> Vec3 {
+        self.rotation.to_euler(EulerOrder::XYZ)
     }
 
-    pub fn angle(&self) -> Vec4 {
-        self.angle
+    pub fn rotation(&self) -> Quat {
+        self.rotation
     }
 
-    pub fn translation(&self) -> Vec4 {
+    pub fn scale(&self) -> Vec3 {
+        self.scale
+    }
+
+    pub fn translation(&self) -> Vec3 {
         self.translation
     }
 
-    pub fn update_translate(&mut self, translation: Vec4) {
+    pub fn update_translate(&mut self, translation: Vec3) {
         self.translation = translation;
     }
 
-    pub fn update_angle(&mut self, angle: Vec4) {
-        self.angle = angle;
+    // Sets the rotation from XYZ Euler angles
+    pub fn update_angle(&mut self, angle: Vec3) {
+        self.update_rotation(Quat::from_euler(angle, EulerOrder::XYZ));
+    }
+
+    pub fn update_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+        self.update_normal_matrix();
     }
 
-    pub fn update_scale(&mut self, scale: Vec4) {
+    pub fn update_scale(&mut self, scale: Vec3) {
         self.scale = scale;
+        self.update_normal_matrix();
     }
 
-    // Assumes only T, R and S operations were performed,
-    // then computed the M matrix based on them
-    pub fn get_model_matrix(&self) -> Matrix4x4 {
-        let mut model_matrix = Matrix4x4::identity();
+    pub fn parent(&self) -> Option<Matrix4x4> {
+        self.parent
+    }
+
+    // The translation, rotation and scale stay relative to the parent
+    pub fn update_parent(&mut self, parent: Option<Matrix4x4>) {
+        self.parent = parent;
+        self.update_normal_matrix();
+    }
+
+    // Brings the normals from local to world space: the inverse-transpose of the
+    // rotation and scale part of the model matrix, so they stay perpendicular to the
+    // surface with a non-uniform scale
+    pub fn normal_matrix(&self) -> Mat3 {
+        self.normal_matrix
+    }
 
-        let (sx, cx) = self.angle.x.sin_cos();
-        let (sy, cy) = self.angle.y.sin_cos();
-        let (sz, cz) = self.angle.z.sin_cos();
+    fn update_normal_matrix(&mut self) {
+        let linear = Mat3::from(self.get_model_matrix());
 
-        // The Rotation * Scale Matrix (RS)
-        // Row 1
-        model_matrix.data[0][0] = (cz * cy) * self.scale.x;
-        model_matrix.data[0][1] = (-sz * cx + cz * sy * sx) * self.scale.y;
-        model_matrix.data[0][2] = (sz * sx + cz * sy * cx) * self.scale.z;
+        // A zero scale flattens the object, there is no inverse to take
+        self.normal_matrix = linear
+            .inverse()
+            .map_or(Mat3::identity(), |inverse| inverse.transpose());
+    }
 
-        // Row 2
-        model_matrix.data[1][0] = (sz * cy) * self.scale.x;
-        model_matrix.data[1][1] = (cz * cx + sz * sy * sx) * self.scale.y;
-        model_matrix.data[1][2] = (-cz * sx + sz * sy * cx) * self.scale.z;
+    // Assumes only T, R and S operations were performed,
+    // then computed the M matrix based on them (with the parent applied over it)
+    pub fn get_model_matrix(&self) -> Matrix4x4 {
+        let mut model_matrix = self.rotation.to_matrix();
 
-        // Row 3
-        model_matrix.data[2][0] = (-sy) * self.scale.x;
-        model_matrix.data[2][1] = (cy * sx) * self.scale.y;
-        model_matrix.data[2][2] = (cy * cx) * self.scale.z;
+        // The Rotation * Scale Matrix (RS): the scale applies to the columns
+        let scale = self.scale.to_array();
+        for row in &mut model_matrix.data[..3] {
+            for (value, scale) in row.iter_mut().zip(scale) {
+                *value *= scale;
+            }
+        }
 
         // Appending the translation
         model_matrix.data[0][3] = self.translation.x;
         model_matrix.data[1][3] = self.translation.y;
         model_matrix.data[2][3] = self.translation.z;
 
-        model_matrix
+        match self.parent {
+            Some(parent) => parent * model_matrix,
+            None => model_matrix,
+        }
     }
 }
 
@@ -103,6 +178,92 @@ impl Matrix4x4 {
             ],
         }
     }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Matrix4x4::zero();
+        for row in 0..4 {
+            for col in 0..4 {
+                result.data[row][col] = self.data[col][row];
+            }
+        }
+        result
+    }
+
+    // The 2x2 determinants of the top two rows (s) and of the bottom two rows (c),
+    // for the column pairs 01, 02, 03, 12, 13, 23. The determinant and the inverse
+    // are both built from them (Laplace expansion along the two halves)
+    fn sub_determinants(&self) -> ([f32; 6], [f32; 6]) {
+        let m = &self.data;
+        let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+
+        let s = pairs.map(|(i, j)| m[0][i] * m[1][j] - m[0][j] * m[1][i]);
+        let c = pairs.map(|(i, j)| m[2][i] * m[3][j] - m[2][j] * m[3][i]);
+        (s, c)
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.sub_determinants();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    // None if the matrix is singular, meaning an exactly zero (or non-finite)
+    // determinant, like Mat3::inverse
+    pub fn inverse(&self) -> Option<Self> {
+        let m = &self.data;
+        let (s, c) = self.sub_determinants();
+
+        let determinant =
+            s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        let inv = 1.0 / determinant;
+        if !determinant.is_finite() || !inv.is_finite() {
+            return None;
+        }
+
+        // The adjugate (transposed cofactors) divided by the determinant
+        let adjugate = [
+            [
+                m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3],
+                -m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3],
+                m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3],
+                -m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3],
+            ],
+            [
+                -m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1],
+                m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1],
+                -m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1],
+                m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1],
+            ],
+            [
+                m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0],
+                -m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0],
+                m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0],
+                -m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0],
+            ],
+            [
+                -m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0],
+                m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0],
+                -m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0],
+                m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0],
+            ],
+        ];
+
+        Some(Self {
+            data: adjugate.map(|row| row.map(|value| value * inv)),
+        })
+    }
+}
+
+impl Matrix4x4 {
+    // Transforms a position (w = 1), so the translation applies. There is no
+    // perspective divide, use Vec4::project on self * Vec4::point for that
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        (*self * Vec4::point(point)).xyz()
+    }
+
+    // Transforms a direction (w = 0), the translation is ignored
+    pub fn transform_direction(&self, direction: Vec3) -> Vec3 {
+        (*self * Vec4::direction(direction)).xyz()
+    }
 }
 
 impl Default for Matrix4x4 {
@@ -0,0 +1,489 @@
+mod obj;
+mod ply;
+mod stl;
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use super::error::Error;
+use super::material::Material;
+use super::vec2::Vec2;
+use super::vec3::Vec3;
+use super::vec4::Vec4;
+
+// An object (or group) of the file a mesh was loaded from, with the triangles it
+// covers in the mesh (in triangles, not in indices)
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshGroup {
+    pub name: String,
+    pub triangles: Range<usize>,
+}
+
+// Per vertex values the mesh's fields don't cover, e.g. the confidence or the
+// intensity of a scan, kept under the name the file gives them
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexAttribute {
+    pub name: String,
+    // One for every vertex
+    pub values: Vec<f32>,
+}
+
+// The default mesh has nothing to draw, e.g. in place of one that failed to load
+#[derive(Default)]
+pub struct Mesh {
+    // The object name for one object of a file, the file name for a whole file
+    pub name: String,
+    pub vertices: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    // Texture coordinates (u, v)
+    pub uvs: Vec<Vec2>,
+    // RGBA vertex colors, every channel in [0.0, 1.0]. They tint the material
+    pub colors: Vec<Vec4>,
+    // Tangents along u for normal mapping, w being the handedness of the bitangent
+    // (bitangent = normal.cross(tangent.xyz()) * w)
+    pub tangents: Vec<Vec4>,
+    pub attributes: Vec<VertexAttribute>,
+    pub indices: Vec<usize>,
+    pub materials: Vec<Material>,
+    // Index in materials for every triangle (indices.len() / 3 of them). The triangles
+    // without one (e.g. in a mesh built by hand) use the default material
+    pub material_ids: Vec<usize>,
+    // The objects the mesh was made of, in the order of the file (empty for meshes
+    // built by hand)
+    pub groups: Vec<MeshGroup>,
+}
+
+// The files meshes can be loaded from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshFormat {
+    Obj,
+    Stl,
+    Ply,
+}
+
+impl MeshFormat {
+    // From the extension, or from the contents for the files without a known one
+    pub fn detect(path: &Path, bytes: &[u8]) -> Option<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("obj") => Some(Self::Obj),
+            Some("stl") => Some(Self::Stl),
+            Some("ply") => Some(Self::Ply),
+            _ => Self::from_contents(bytes),
+        }
+    }
+
+    // PLYs start with "ply". The other formats don't have magic bytes: binary STLs are
+    // found from their size, ASCII ones from their "solid" line, and OBJs from the
+    // keywords of their first line
+    pub fn from_contents(bytes: &[u8]) -> Option<Self> {
+        if ply::looks_like(bytes) {
+            Some(Self::Ply)
+        } else if stl::looks_like(bytes) {
+            Some(Self::Stl)
+        } else if obj::looks_like(bytes) {
+            Some(Self::Obj)
+        } else {
+            None
+        }
+    }
+}
+
+impl Mesh {
+    // Loads every object of an OBJ, STL or PLY file into one mesh, the objects being
+    // listed in groups. Missing materials and textures are only reported, the mesh
+    // loads without them
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let meshes = Self::load_objects(path)?;
+        Ok(Self::merge(file_name(path), meshes))
+    }
+
+    // Loads every object of an OBJ file (or solid of an STL file) as its own mesh, named
+    // after the object, with only the materials it uses. PLY files only have one object.
+    // The objects without any triangle are left out
+    pub fn load_objects(path: impl AsRef<Path>) -> Result<Vec<Self>, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+
+        let meshes = match MeshFormat::detect(path, &bytes) {
+            Some(MeshFormat::Obj) => obj::load(path, &bytes)?,
+            Some(MeshFormat::Stl) => stl::decode(&bytes)?,
+            Some(MeshFormat::Ply) => vec![ply::decode(&bytes)?],
+            None => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "{} isn't an OBJ, STL or PLY file",
+                    path.display()
+                )));
+            }
+        };
+
+        let mut meshes: Vec<Self> = meshes
+            .into_iter()
+            .filter(|mesh| !mesh.indices.is_empty())
+            .collect();
+
+        if meshes.is_empty() {
+            return Err(Error::DegenerateGeometry(format!(
+                "{} doesn't have any triangle",
+                path.display()
+            )));
+        }
+        for mesh in &mut meshes {
+            // Binary STLs and PLYs don't have a name for their object
+            if mesh.name.is_empty() {
+                mesh.name = file_name(path);
+                mesh.groups[0].name = mesh.name.clone();
+            }
+            mesh.validate()?;
+        }
+        Ok(meshes)
+    }
+
+    // Saves as STL (binary), depending on the extension
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let bytes = match MeshFormat::detect(path, &[]) {
+            Some(MeshFormat::Stl) => self.encode_stl_binary(),
+            _ => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "meshes can't be saved as {}",
+                    path.display()
+                )));
+            }
+        };
+
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    // Every triangle with its face normal, the smooth normals and the UVs are lost
+    pub fn encode_stl_ascii(&self) -> Vec<u8> {
+        stl::encode_ascii(self)
+    }
+
+    pub fn encode_stl_binary(&self) -> Vec<u8> {
+        stl::encode_binary(self)
+    }
+
+    // One mesh out of several (the objects of a file), with a group for each. The
+    // materials with the same name are taken to be the same, as they are in a file
+    fn merge(name: String, meshes: Vec<Mesh>) -> Self {
+        let has_normals = meshes.iter().all(|mesh| !mesh.normals.is_empty());
+        let has_uvs = meshes.iter().any(|mesh| !mesh.uvs.is_empty());
+        let has_colors = meshes.iter().any(|mesh| !mesh.colors.is_empty());
+        let has_tangents = meshes.iter().all(|mesh| !mesh.tangents.is_empty());
+
+        let mut merged = Self {
+            name,
+            ..Default::default()
+        };
+
+        for mesh in meshes {
+            let offset = merged.vertices.len();
+            let first_triangle = merged.indices.len() / 3;
+
+            merged.vertices.extend(mesh.vertices);
+            if has_normals {
+                merged.normals.extend(mesh.normals);
+            }
+            if has_tangents {
+                merged.tangents.extend(mesh.tangents);
+            }
+            if has_uvs {
+                if mesh.uvs.is_empty() {
+                    merged.uvs.resize(merged.vertices.len(), Vec2::zero());
+                } else {
+                    merged.uvs.extend(mesh.uvs);
+                }
+            }
+            if has_colors {
+                if mesh.colors.is_empty() {
+                    merged
+                        .colors
+                        .resize(merged.vertices.len(), Vec4::new(1.0, 1.0, 1.0, 1.0));
+                } else {
+                    merged.colors.extend(mesh.colors);
+                }
+            }
+            // The attributes some meshes don't have are 0.0 for their vertices
+            for attribute in mesh.attributes {
+                match merged
+                    .attributes
+                    .iter_mut()
+                    .find(|merged| merged.name == attribute.name)
+                {
+                    Some(merged) => merged.values.extend(attribute.values),
+                    None => {
+                        let mut values = vec![0.0; offset];
+                        values.extend(attribute.values);
+                        merged.attributes.push(VertexAttribute {
+                            name: attribute.name,
+                            values,
+                        });
+                    }
+                }
+            }
+            for attribute in &mut merged.attributes {
+                attribute.values.resize(merged.vertices.len(), 0.0);
+            }
+            merged
+                .indices
+                .extend(mesh.indices.iter().map(|&i| offset + i));
+
+            let material_ids: Vec<usize> = mesh
+                .materials
+                .into_iter()
+                .map(|material| {
+                    match merged
+                        .materials
+                        .iter()
+                        .position(|m| m.name == material.name)
+                    {
+                        Some(id) => id,
+                        None => {
+                            merged.materials.push(material);
+                            merged.materials.len() - 1
+                        }
+                    }
+                })
+                .collect();
+            merged
+                .material_ids
+                .extend(mesh.material_ids.iter().map(|&id| material_ids[id]));
+
+            merged.groups.push(MeshGroup {
+                name: mesh.name,
+                triangles: first_triangle..merged.indices.len() / 3,
+            });
+        }
+
+        merged
+    }
+
+    // Merges the vertices closer than tolerance to each other, so the triangles around
+    // them share them. Vertices with another normal, UV, color, tangent or attribute
+    // value are kept apart. Formats that repeat the vertices for every triangle (STL)
+    // need it for smooth normals. A tolerance of 0 or less only merges the vertices
+    // at exactly the same position
+    pub fn weld(&mut self, tolerance: f32) {
+        let has_normals = !self.normals.is_empty();
+        let has_uvs = !self.uvs.is_empty();
+        let has_colors = !self.colors.is_empty();
+        let has_tangents = !self.tangents.is_empty();
+
+        // Vertices within tolerance of each other are at most one cell apart. Without a
+        // tolerance the cell is the position itself (adding 0.0 turns -0.0 into 0.0)
+        let tolerance = tolerance.max(0.0);
+        let exact = tolerance == 0.0;
+        let reach = if exact { 0 } else { 1 };
+        let cell = |p: Vec3| {
+            if exact {
+                (
+                    (p.x + 0.0).to_bits() as i64,
+                    (p.y + 0.0).to_bits() as i64,
+                    (p.z + 0.0).to_bits() as i64,
+                )
+            } else {
+                // Far away (or infinite) coordinates saturate, so the cells at the
+                // limits hold more vertices but the distance test still separates them
+                (
+                    (p.x / tolerance).floor() as i64,
+                    (p.y / tolerance).floor() as i64,
+                    (p.z / tolerance).floor() as i64,
+                )
+            }
+        };
+
+        let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+        let mut vertices: Vec<Vec3> = vec![];
+        let mut normals: Vec<Vec3> = vec![];
+        let mut uvs: Vec<Vec2> = vec![];
+        let mut colors: Vec<Vec4> = vec![];
+        let mut tangents: Vec<Vec4> = vec![];
+        let mut attributes: Vec<Vec<f32>> = vec![vec![]; self.attributes.len()];
+        // The new index of every old vertex
+        let mut remap = Vec::with_capacity(self.vertices.len());
+
+        for old in 0..self.vertices.len() {
+            let position = self.vertices[old];
+            let (x, y, z) = cell(position);
+
+            let mut found = None;
+            'search: for dx in -reach..=reach {
+                for dy in -reach..=reach {
+                    for dz in -reach..=reach {
+                        let neighbour = (x.checked_add(dx), y.checked_add(dy), z.checked_add(dz));
+                        let (Some(nx), Some(ny), Some(nz)) = neighbour else {
+                            continue;
+                        };
+                        let Some(candidates) = grid.get(&(nx, ny, nz)) else {
+                            continue;
+                        };
+                        for &new in candidates {
+                            let close = (vertices[new] - position).magnitude_squared()
+                                <= tolerance * tolerance;
+                            if close
+                                && (!has_normals || normals[new] == self.normals[old])
+                                && (!has_uvs || uvs[new] == self.uvs[old])
+                                && (!has_colors || colors[new] == self.colors[old])
+                                && (!has_tangents || tangents[new] == self.tangents[old])
+                                && self
+                                    .attributes
+                                    .iter()
+                                    .zip(&attributes)
+                                    .all(|(attribute, values)| values[new] == attribute.values[old])
+                            {
+                                found = Some(new);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let new = found.unwrap_or_else(|| {
+                vertices.push(position);
+                if has_normals {
+                    normals.push(self.normals[old]);
+                }
+                if has_uvs {
+                    uvs.push(self.uvs[old]);
+                }
+                if has_colors {
+                    colors.push(self.colors[old]);
+                }
+                if has_tangents {
+                    tangents.push(self.tangents[old]);
+                }
+                for (values, attribute) in attributes.iter_mut().zip(&self.attributes) {
+                    values.push(attribute.values[old]);
+                }
+                grid.entry((x, y, z)).or_default().push(vertices.len() - 1);
+                vertices.len() - 1
+            });
+            remap.push(new);
+        }
+
+        for index in &mut self.indices {
+            *index = remap[*index];
+        }
+        self.vertices = vertices;
+        self.normals = normals;
+        self.uvs = uvs;
+        self.colors = colors;
+        self.tangents = tangents;
+        for (attribute, values) in self.attributes.iter_mut().zip(attributes) {
+            attribute.values = values;
+        }
+    }
+
+    // Smooth normals: every vertex gets the average of the normals of the triangles
+    // around it, weighted by their area. Clearing normals gives flat shading back
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::zero(); self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+            // Its length is twice the area of the triangle
+            let normal =
+                (self.vertices[b] - self.vertices[a]).cross(self.vertices[c] - self.vertices[a]);
+            for index in [a, b, c] {
+                normals[index] = normals[index] + normal;
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|normal| {
+                if normal.magnitude_squared() > 0.0 {
+                    normal.normalize()
+                } else {
+                    // Only in degenerate triangles, any direction will do
+                    Vec3::new(0.0, 0.0, 1.0)
+                }
+            })
+            .collect();
+    }
+
+    // Checks that every index points to something: the loaders call it, and meshes
+    // built by hand should be checked with it before they are drawn
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.indices.len().is_multiple_of(3) {
+            return Err(Error::DegenerateGeometry(format!(
+                "{} indices, which isn't a whole number of triangles",
+                self.indices.len()
+            )));
+        }
+
+        let check = |what, index: usize, len: usize| {
+            if index < len {
+                Ok(())
+            } else {
+                Err(Error::InvalidIndex { what, index, len })
+            }
+        };
+
+        if let Some(&highest) = self.indices.iter().max() {
+            check("vertex", highest, self.vertices.len())?;
+            if !self.normals.is_empty() {
+                check("normal", highest, self.normals.len())?;
+            }
+            if !self.uvs.is_empty() {
+                check("UV", highest, self.uvs.len())?;
+            }
+            if !self.colors.is_empty() {
+                check("color", highest, self.colors.len())?;
+            }
+            if !self.tangents.is_empty() {
+                check("tangent", highest, self.tangents.len())?;
+            }
+            for attribute in &self.attributes {
+                check("attribute", highest, attribute.values.len())?;
+            }
+        }
+
+        if let Some(&highest) = self.material_ids.iter().max() {
+            check("material", highest, self.materials.len())?;
+        }
+
+        Ok(())
+    }
+
+    // The material of the triangle at index (in triangles, not in indices), or the
+    // default one if the triangle has none
+    pub fn material(&self, triangle: usize) -> &Material {
+        static DEFAULT_MATERIAL: LazyLock<Material> = LazyLock::new(Material::default);
+
+        self.material_ids
+            .get(triangle)
+            .and_then(|&id| self.materials.get(id))
+            .unwrap_or(&DEFAULT_MATERIAL)
+    }
+
+    // The values of the vertex attribute called name
+    pub fn attribute(&self, name: &str) -> Option<&[f32]> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.values.as_slice())
+    }
+
+    // The group (object of the file) called name
+    pub fn group(&self, name: &str) -> Option<&MeshGroup> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+}
+
+// The name of the file, without the directory and the extension
+fn file_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use super::{Mesh, MeshGroup};
+use crate::custom_data_types::error::Error;
+use crate::custom_data_types::material::Material;
+use crate::custom_data_types::vec2::Vec2;
+use crate::custom_data_types::vec3::Vec3;
+
+// Loads every object of an OBJ file as its own mesh, with only the materials it uses.
+// The MTL files and textures are looked for next to path. Missing ones are only
+// reported, the meshes load without them
+pub(super) fn load(path: &Path, bytes: &[u8]) -> Result<Vec<Mesh>, Error> {
+    let load_options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let (models, materials) = tobj::load_obj_buf(&mut &bytes[..], &load_options, |mtl| {
+        tobj::load_mtl(directory.join(mtl))
+    })
+    .map_err(|err| Error::Parse(format!("{}: {}", path.display(), err)))?;
+
+    let materials: Vec<Material> = match materials {
+        Ok(materials) => materials
+            .iter()
+            .map(|material| Material::from_tobj(material, directory))
+            .collect(),
+        Err(err) => {
+            eprintln!(
+                "Failed to load the materials of {}: {}",
+                path.display(),
+                err
+            );
+            vec![]
+        }
+    };
+
+    // tobj starts a new model (with the same name) every time the material
+    // changes, so an object might be split over several models. They are put
+    // back together, in the order the objects first appear in
+    let mut objects: Vec<(String, Vec<tobj::Model>)> = vec![];
+    for model in models {
+        match objects.iter_mut().find(|(name, _)| *name == model.name) {
+            Some((_, parts)) => parts.push(model),
+            None => objects.push((model.name.clone(), vec![model])),
+        }
+    }
+
+    Ok(objects
+        .into_iter()
+        .map(|(name, parts)| object_mesh(name, &parts, &materials))
+        .collect())
+}
+
+// Builds the mesh of one object out of its models. Only the materials used are copied
+// from materials (plus the default one if some faces don't have any)
+fn object_mesh(name: String, parts: &[tobj::Model], materials: &[Material]) -> Mesh {
+    let has_normals = parts.iter().all(|part| !part.mesh.normals.is_empty());
+    let has_uvs = parts.iter().any(|part| !part.mesh.texcoords.is_empty());
+
+    let mut mesh = Mesh {
+        name,
+        ..Default::default()
+    };
+    // Where each material of the file (and the default one, at the end) went
+    // in the mesh's materials
+    let mut used_materials: Vec<Option<usize>> = vec![None; materials.len() + 1];
+
+    for part in parts {
+        let mesh_data = &part.mesh;
+        let offset = mesh.vertices.len();
+
+        mesh.vertices.extend(
+            mesh_data
+                .positions
+                .chunks(3)
+                .map(|v| Vec3::new(v[0], v[1], v[2])),
+        );
+
+        if has_normals {
+            mesh.normals.extend(
+                mesh_data
+                    .normals
+                    .chunks(3)
+                    .map(|n| Vec3::new(n[0], n[1], n[2])),
+            );
+        }
+
+        if has_uvs {
+            if !mesh_data.texcoords.is_empty() {
+                mesh.uvs
+                    .extend(mesh_data.texcoords.chunks(2).map(|t| Vec2::new(t[0], t[1])));
+            } else {
+                mesh.uvs.resize(mesh.vertices.len(), Vec2::zero());
+            }
+        }
+
+        mesh.indices
+            .extend(mesh_data.indices.iter().map(|&i| offset + i as usize));
+
+        let file_id = mesh_data
+            .material_id
+            .filter(|&id| id < materials.len())
+            .unwrap_or(materials.len());
+        let material_id = *used_materials[file_id].get_or_insert_with(|| {
+            mesh.materials
+                .push(materials.get(file_id).cloned().unwrap_or_default());
+            mesh.materials.len() - 1
+        });
+        mesh.material_ids.extend(std::iter::repeat_n(
+            material_id,
+            mesh_data.indices.len() / 3,
+        ));
+    }
+
+    mesh.groups.push(MeshGroup {
+        name: mesh.name.clone(),
+        triangles: 0..mesh.material_ids.len(),
+    });
+    mesh
+}
+
+// Whether the first line that isn't empty or a comment starts with an OBJ keyword
+pub(super) fn looks_like(bytes: &[u8]) -> bool {
+    const KEYWORDS: [&str; 12] = [
+        "v", "vt", "vn", "vp", "f", "l", "p", "o", "g", "s", "mtllib", "usemtl",
+    ];
+
+    let start = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+    start
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .and_then(|line| line.split_whitespace().next())
+        .is_some_and(|keyword| KEYWORDS.contains(&keyword))
+}
@@ -0,0 +1,411 @@
+use super::{Mesh, MeshGroup, VertexAttribute};
+use crate::custom_data_types::error::Error;
+use crate::custom_data_types::material::Material;
+use crate::custom_data_types::vec2::Vec2;
+use crate::custom_data_types::vec3::Vec3;
+use crate::custom_data_types::vec4::Vec4;
+
+pub(super) fn looks_like(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"ply\n") || bytes.starts_with(b"ply\r\n")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    // Both the old names and the sized ones (int8, float32...) are used
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    // What the colors stored with this type are divided by, to bring them to [0.0, 1.0].
+    // Floating point colors already are
+    fn color_scale(self) -> f64 {
+        match self {
+            Self::I8 => i8::MAX as f64,
+            Self::U8 => u8::MAX as f64,
+            Self::I16 => i16::MAX as f64,
+            Self::U16 => u16::MAX as f64,
+            Self::I32 => i32::MAX as f64,
+            Self::U32 => u32::MAX as f64,
+            Self::F32 | Self::F64 => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, ScalarType),
+    // A count of the type count, followed by that many values of the type item
+    List {
+        name: String,
+        count: ScalarType,
+        item: ScalarType,
+    },
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Where the values of the body come from, one at a time whatever the encoding
+trait Values {
+    fn next(&mut self, scalar: ScalarType) -> Result<f64, Error>;
+}
+
+struct AsciiValues<'a> {
+    words: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl Values for AsciiValues<'_> {
+    fn next(&mut self, _scalar: ScalarType) -> Result<f64, Error> {
+        let word = self
+            .words
+            .next()
+            .ok_or_else(|| Error::Parse(String::from("PLY ends before all its elements")))?;
+        word.parse()
+            .map_err(|_| Error::Parse(format!("invalid PLY value \"{}\"", word)))
+    }
+}
+
+struct BinaryValues<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    big_endian: bool,
+}
+
+impl Values for BinaryValues<'_> {
+    fn next(&mut self, scalar: ScalarType) -> Result<f64, Error> {
+        let size = scalar.size();
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + size)
+            .ok_or_else(|| Error::Parse(String::from("PLY ends before all its elements")))?;
+        self.position += size;
+
+        // Always read as little endian
+        let mut value = [0; 8];
+        value[..size].copy_from_slice(bytes);
+        if self.big_endian {
+            value[..size].reverse();
+        }
+
+        Ok(match scalar {
+            ScalarType::I8 => value[0] as i8 as f64,
+            ScalarType::U8 => value[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([value[0], value[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([value[0], value[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+            ScalarType::F64 => f64::from_le_bytes(value),
+        })
+    }
+}
+
+// The encoding, the elements and where the body starts
+fn parse_header(bytes: &[u8]) -> Result<(Encoding, Vec<Element>, usize), Error> {
+    let error = |what: &str| Error::Parse(format!("PLY header: {}", what));
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut position = 0;
+
+    loop {
+        let end = bytes[position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| error("no end_header"))?;
+        let line = String::from_utf8_lossy(&bytes[position..position + end]);
+        position += end + 1;
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["ply"] | [] => {}
+            ["comment", ..] | ["obj_info", ..] => {}
+            ["format", format, _version] => {
+                encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::LittleEndian,
+                    "binary_big_endian" => Encoding::BigEndian,
+                    _ => return Err(error(&format!("unknown format {}", format))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| error(&format!("invalid count {}", count)))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before any element"))?;
+                let (Some(count), Some(item)) = (ScalarType::parse(count), ScalarType::parse(item))
+                else {
+                    return Err(error(&format!("unknown type in {}", line)));
+                };
+                element.properties.push(Property::List {
+                    name: name.to_string(),
+                    count,
+                    item,
+                });
+            }
+            ["property", scalar, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before any element"))?;
+                let scalar = ScalarType::parse(scalar)
+                    .ok_or_else(|| error(&format!("unknown type {}", scalar)))?;
+                element
+                    .properties
+                    .push(Property::Scalar(name.to_string(), scalar));
+            }
+            ["end_header"] => break,
+            _ => return Err(error(&format!("unexpected line \"{}\"", line.trim()))),
+        }
+    }
+
+    let encoding = encoding.ok_or_else(|| error("no format"))?;
+    Ok((encoding, elements, position))
+}
+
+// What the mesh is built from: a column of values for every scalar vertex property,
+// and the vertex indices of every face
+struct Body {
+    vertex_columns: Vec<(String, ScalarType, Vec<f64>)>,
+    faces: Vec<Vec<usize>>,
+}
+
+fn read_body(elements: &[Element], values: &mut impl Values) -> Result<Body, Error> {
+    let mut body = Body {
+        vertex_columns: vec![],
+        faces: vec![],
+    };
+
+    for element in elements {
+        // Nothing to read, and the count alone could keep the loop going for a long time
+        if element.properties.is_empty() {
+            continue;
+        }
+
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+        let columns_start = body.vertex_columns.len();
+        if is_vertex {
+            for property in &element.properties {
+                if let Property::Scalar(name, scalar) = property {
+                    body.vertex_columns.push((name.clone(), *scalar, vec![]));
+                }
+            }
+        }
+
+        for _ in 0..element.count {
+            let mut column = columns_start;
+            for property in &element.properties {
+                match property {
+                    Property::Scalar(_, scalar) => {
+                        let value = values.next(*scalar)?;
+                        if is_vertex {
+                            body.vertex_columns[column].2.push(value);
+                            column += 1;
+                        }
+                    }
+                    Property::List { name, count, item } => {
+                        let count = values.next(*count)?;
+                        if !(0.0..=u32::MAX as f64).contains(&count) {
+                            return Err(Error::Parse(format!("invalid PLY list length {}", count)));
+                        }
+
+                        // Not allocated up front: the count comes from the file, and the
+                        // values run out long before a wrong one is reached
+                        let mut list = vec![];
+                        for _ in 0..count as usize {
+                            list.push(values.next(*item)?);
+                        }
+
+                        let is_indices = name == "vertex_indices" || name == "vertex_index";
+                        if is_face && is_indices {
+                            if list.iter().any(|&index| index < 0.0) {
+                                return Err(Error::Parse(String::from(
+                                    "negative PLY vertex index",
+                                )));
+                            }
+                            body.faces
+                                .push(list.into_iter().map(|index| index as usize).collect());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(body)
+}
+
+// The vertices, with their normals, colors, UVs and other properties, and the faces.
+// Binary files are read as they are, so the values don't lose any precision
+pub(super) fn decode(bytes: &[u8]) -> Result<Mesh, Error> {
+    let (encoding, elements, body_start) = parse_header(bytes)?;
+
+    let body = match encoding {
+        Encoding::Ascii => {
+            let text = std::str::from_utf8(&bytes[body_start..])
+                .map_err(|_| Error::Parse(String::from("ASCII PLY that isn't valid text")))?;
+            read_body(
+                &elements,
+                &mut AsciiValues {
+                    words: text.split_ascii_whitespace(),
+                },
+            )?
+        }
+        Encoding::LittleEndian | Encoding::BigEndian => read_body(
+            &elements,
+            &mut BinaryValues {
+                bytes,
+                position: body_start,
+                big_endian: encoding == Encoding::BigEndian,
+            },
+        )?,
+    };
+
+    build_mesh(body)
+}
+
+fn build_mesh(body: Body) -> Result<Mesh, Error> {
+    let mut used = vec![false; body.vertex_columns.len()];
+    let mut column = |names: &[&str]| {
+        let index = body
+            .vertex_columns
+            .iter()
+            .position(|(name, _, _)| names.contains(&name.as_str()))?;
+        used[index] = true;
+        let (_, scalar, values) = &body.vertex_columns[index];
+        Some((*scalar, values))
+    };
+
+    let (Some((_, x)), Some((_, y)), Some((_, z))) =
+        (column(&["x"]), column(&["y"]), column(&["z"]))
+    else {
+        return Err(Error::Parse(String::from(
+            "PLY vertices without x, y and z",
+        )));
+    };
+    let vertices: Vec<Vec3> = (0..x.len())
+        .map(|i| Vec3::new(x[i] as f32, y[i] as f32, z[i] as f32))
+        .collect();
+
+    let normals = match (column(&["nx"]), column(&["ny"]), column(&["nz"])) {
+        (Some((_, x)), Some((_, y)), Some((_, z))) => (0..x.len())
+            .map(|i| Vec3::new(x[i] as f32, y[i] as f32, z[i] as f32))
+            .collect(),
+        _ => vec![],
+    };
+
+    let uvs = match (column(&["s", "u"]), column(&["t", "v"])) {
+        (Some((_, s)), Some((_, t))) => (0..s.len())
+            .map(|i| Vec2::new(s[i] as f32, t[i] as f32))
+            .collect(),
+        _ => vec![],
+    };
+
+    let colors = match (
+        column(&["red"]),
+        column(&["green"]),
+        column(&["blue"]),
+        column(&["alpha"]),
+    ) {
+        (Some(red), Some(green), Some(blue), alpha) => {
+            let channel = |(scalar, values): (ScalarType, &Vec<f64>), i: usize| {
+                (values[i] / scalar.color_scale()) as f32
+            };
+            (0..vertices.len())
+                .map(|i| {
+                    Vec4::new(
+                        channel(red, i),
+                        channel(green, i),
+                        channel(blue, i),
+                        alpha.map_or(1.0, |alpha| channel(alpha, i)),
+                    )
+                })
+                .collect()
+        }
+        _ => vec![],
+    };
+
+    // Everything else is kept as it is
+    let attributes = body
+        .vertex_columns
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|((name, _, values), _)| VertexAttribute {
+            name: name.clone(),
+            values: values.iter().map(|&value| value as f32).collect(),
+        })
+        .collect();
+
+    // Polygons are fanned out, and what has less than 3 vertices is left out
+    let mut indices = vec![];
+    for face in &body.faces {
+        for i in 1..face.len().saturating_sub(1) {
+            indices.extend([face[0], face[i], face[i + 1]]);
+        }
+    }
+
+    let triangles = indices.len() / 3;
+    Ok(Mesh {
+        vertices,
+        normals,
+        uvs,
+        colors,
+        attributes,
+        indices,
+        materials: vec![Material::default()],
+        material_ids: vec![0; triangles],
+        groups: vec![MeshGroup {
+            name: String::new(),
+            triangles: 0..triangles,
+        }],
+        ..Default::default()
+    })
+}
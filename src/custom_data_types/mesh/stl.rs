@@ -0,0 +1,231 @@
+use std::fmt::Write;
+
+use super::{Mesh, MeshGroup};
+use crate::custom_data_types::error::Error;
+use crate::custom_data_types::material::Material;
+use crate::custom_data_types::vec3::Vec3;
+
+// STL files repeat the vertices for every triangle. The copies are exactly the same
+// in practice, the tolerance only covers exporters rounding them differently
+const WELD_TOLERANCE: f32 = 1e-5;
+
+const HEADER_SIZE: usize = 80;
+// The normal, the 3 vertices and a 2 byte attribute
+const TRIANGLE_SIZE: usize = 50;
+
+// Binary files are exactly as long as their triangle count says. They may start with
+// "solid" too (some exporters put it in the header), so the size is checked first
+fn is_binary(bytes: &[u8]) -> bool {
+    binary_triangle_count(bytes)
+        .is_some_and(|count| HEADER_SIZE + 4 + count * TRIANGLE_SIZE == bytes.len())
+}
+
+fn binary_triangle_count(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(HEADER_SIZE..HEADER_SIZE + 4)?;
+    Some(u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize)
+}
+
+fn is_ascii(bytes: &[u8]) -> bool {
+    bytes.trim_ascii_start().starts_with(b"solid")
+}
+
+pub(super) fn looks_like(bytes: &[u8]) -> bool {
+    is_binary(bytes) || is_ascii(bytes)
+}
+
+// Every solid of the file as a mesh, welded and with smooth normals. Binary files
+// have a single solid, without a name
+pub(super) fn decode(bytes: &[u8]) -> Result<Vec<Mesh>, Error> {
+    let solids = if !is_binary(bytes) && is_ascii(bytes) {
+        decode_ascii(bytes)?
+    } else {
+        vec![(String::new(), decode_binary(bytes)?)]
+    };
+
+    Ok(solids
+        .into_iter()
+        .map(|(name, positions)| solid_mesh(name, positions))
+        .collect())
+}
+
+fn decode_binary(bytes: &[u8]) -> Result<Vec<Vec3>, Error> {
+    let count = binary_triangle_count(bytes)
+        .ok_or_else(|| Error::Parse(String::from("binary STL shorter than its header")))?;
+
+    let triangles = &bytes[HEADER_SIZE + 4..];
+    if triangles.len() / TRIANGLE_SIZE < count {
+        return Err(Error::Parse(format!(
+            "binary STL with {} triangles, but only room for {}",
+            count,
+            triangles.len() / TRIANGLE_SIZE
+        )));
+    }
+
+    let float = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let positions: Vec<Vec3> = triangles
+        .chunks_exact(TRIANGLE_SIZE)
+        .take(count)
+        .flat_map(|triangle| {
+            // The normal (the first 12 bytes) is recomputed from the welded vertices
+            triangle[12..48].chunks_exact(12).map(move |vertex| {
+                Vec3::new(
+                    float(&vertex[0..]),
+                    float(&vertex[4..]),
+                    float(&vertex[8..]),
+                )
+            })
+        })
+        .collect();
+
+    let finite = |p: &Vec3| p.x.is_finite() && p.y.is_finite() && p.z.is_finite();
+    if let Some(index) = positions.iter().position(|p| !finite(p)) {
+        return Err(Error::Parse(format!(
+            "binary STL triangle {} has a non-finite vertex",
+            index / 3
+        )));
+    }
+    Ok(positions)
+}
+
+// The name and the triangle vertices of every solid
+fn decode_ascii(bytes: &[u8]) -> Result<Vec<(String, Vec<Vec3>)>, Error> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| Error::Parse(String::from("ASCII STL that isn't valid text")))?;
+
+    let mut solids = vec![];
+    let mut solid: Option<(String, Vec<Vec3>)> = None;
+    // The vertices of the current facet, usually 3 but polygons are fanned out
+    let mut polygon = vec![];
+
+    for (number, line) in text.lines().enumerate() {
+        let error = |what: &str| Error::Parse(format!("STL line {}: {}", number + 1, what));
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("solid") => {
+                if let Some(unfinished) = solid.take() {
+                    solids.push(unfinished);
+                }
+                solid = Some((line["solid".len()..].trim().to_string(), vec![]));
+            }
+            Some("outer") => polygon.clear(),
+            Some("vertex") => {
+                let mut coordinate = || {
+                    words
+                        .next()
+                        .and_then(|word| word.parse::<f32>().ok())
+                        .filter(|value| value.is_finite())
+                        .ok_or_else(|| error("invalid vertex"))
+                };
+                polygon.push(Vec3::new(coordinate()?, coordinate()?, coordinate()?));
+            }
+            Some("endloop") => {
+                let (_, positions) = solid
+                    .as_mut()
+                    .ok_or_else(|| error("facet outside a solid"))?;
+                if polygon.len() < 3 {
+                    return Err(error("facet with less than 3 vertices"));
+                }
+                for i in 1..polygon.len() - 1 {
+                    positions.extend([polygon[0], polygon[i], polygon[i + 1]]);
+                }
+            }
+            Some("endsolid") => {
+                if let Some(finished) = solid.take() {
+                    solids.push(finished);
+                }
+            }
+            Some("facet") | Some("endfacet") | None => {}
+            Some(other) => return Err(error(&format!("unexpected \"{}\"", other))),
+        }
+    }
+
+    // Some exporters leave out the last endsolid
+    if let Some(unfinished) = solid {
+        solids.push(unfinished);
+    }
+    Ok(solids)
+}
+
+fn solid_mesh(name: String, positions: Vec<Vec3>) -> Mesh {
+    let triangles = positions.len() / 3;
+    let mut mesh = Mesh {
+        name: name.clone(),
+        indices: (0..positions.len()).collect(),
+        vertices: positions,
+        materials: vec![Material::default()],
+        material_ids: vec![0; triangles],
+        groups: vec![MeshGroup {
+            name,
+            triangles: 0..triangles,
+        }],
+        ..Default::default()
+    };
+
+    mesh.weld(WELD_TOLERANCE);
+    mesh.compute_normals();
+    mesh
+}
+
+// The triangles of the mesh, as their 3 vertices and their face normal
+fn triangles(mesh: &Mesh) -> impl Iterator<Item = (Vec3, [Vec3; 3])> + '_ {
+    mesh.indices.chunks_exact(3).map(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i]]);
+        let normal = (b - a).cross(c - a);
+        let normal = if normal.magnitude_squared() > 0.0 {
+            normal.normalize()
+        } else {
+            Vec3::zero()
+        };
+        (normal, [a, b, c])
+    })
+}
+
+pub(super) fn encode_ascii(mesh: &Mesh) -> Vec<u8> {
+    // The name ends at the end of the line
+    let name = mesh.name.lines().next().unwrap_or("");
+    let mut text = String::new();
+
+    // Writing to a String can't fail
+    let _ = writeln!(text, "solid {}", name);
+    for (normal, vertices) in triangles(mesh) {
+        let _ = writeln!(
+            text,
+            "  facet normal {} {} {}",
+            normal.x, normal.y, normal.z
+        );
+        let _ = writeln!(text, "    outer loop");
+        for vertex in vertices {
+            let _ = writeln!(text, "      vertex {} {} {}", vertex.x, vertex.y, vertex.z);
+        }
+        let _ = writeln!(text, "    endloop");
+        let _ = writeln!(text, "  endfacet");
+    }
+    let _ = writeln!(text, "endsolid {}", name);
+
+    text.into_bytes()
+}
+
+pub(super) fn encode_binary(mesh: &Mesh) -> Vec<u8> {
+    let triangle_count = mesh.indices.len() / 3;
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + 4 + triangle_count * TRIANGLE_SIZE);
+
+    // The header must not start with "solid", or readers might take it for an ASCII file
+    let mut header = format!("binary STL {}", mesh.name).into_bytes();
+    header.resize(HEADER_SIZE, 0);
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&(triangle_count as u32).to_le_bytes());
+
+    for (normal, vertices) in triangles(mesh) {
+        for vector in [normal, vertices[0], vertices[1], vertices[2]] {
+            for value in vector.to_array() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        // The attribute byte count, unused
+        bytes.extend_from_slice(&[0, 0]);
+    }
+
+    bytes
+}
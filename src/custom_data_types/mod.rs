@@ -1,10 +1,29 @@
 pub mod camera;
+pub mod camera_controller;
+pub mod clipper;
 pub mod color;
 pub mod depth_buffer;
 pub mod draw_buffer;
+pub mod edge_equation;
+pub mod error;
+pub mod frame_timer;
 pub mod game_object;
+pub mod gltf;
+pub mod image;
+pub mod mat3;
+pub mod material;
 pub mod matrices;
 pub mod mesh;
+pub mod multisample;
+pub mod quat;
 pub mod rasterizer;
+pub mod render_target;
 pub mod scene;
+pub mod shader;
+pub mod texture;
+pub mod varying;
+pub mod vec2;
+pub mod vec3;
 pub mod vec4;
+#[cfg(feature = "window")]
+pub mod window;
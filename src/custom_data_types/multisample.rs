@@ -0,0 +1,94 @@
+use std::num::NonZeroUsize;
+
+use super::rasterizer::SUBPIXEL_ONE;
+
+// The most samples per pixel MSAA supports
+pub const MAX_SAMPLES: usize = 8;
+
+// The numbers of samples per pixel there are sample patterns for. X1 is the pixel
+// center, what the rasterizer uses without MSAA
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleCount {
+    X1,
+    X2,
+    X4,
+    X8,
+}
+
+impl SampleCount {
+    pub fn count(self) -> usize {
+        match self {
+            SampleCount::X1 => 1,
+            SampleCount::X2 => 2,
+            SampleCount::X4 => 4,
+            SampleCount::X8 => 8,
+        }
+    }
+}
+
+// How the rasterizer smooths the edges of the triangles (see Rasterizer::anti_aliasing)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AntiAliasing {
+    Off,
+    // Multisampling: coverage and depth are tested per sample, but the fragment
+    // shader only runs once per pixel (X1 draws like Off)
+    Msaa(SampleCount),
+    // Supersampling: the scene is drawn at factor x factor the resolution and averaged
+    // down, so the shading is anti-aliased too. Slow, meant for reference stills
+    Ssaa(NonZeroUsize),
+}
+
+// A sixteenth of a pixel in fixed point, the unit of the sample patterns
+const SIXTEENTH: i32 = SUBPIXEL_ONE / 16;
+
+// The standard rotated-grid patterns (the ones D3D and most GPUs use), as offsets from
+// the pixel center in fixed point. They are given with y going down, so y is flipped here
+const PATTERN_1X: [(i32, i32); 1] = [(0, 0)];
+const PATTERN_2X: [(i32, i32); 2] = [
+    (4 * SIXTEENTH, -4 * SIXTEENTH),
+    (-4 * SIXTEENTH, 4 * SIXTEENTH),
+];
+const PATTERN_4X: [(i32, i32); 4] = [
+    (-2 * SIXTEENTH, 6 * SIXTEENTH),
+    (6 * SIXTEENTH, 2 * SIXTEENTH),
+    (-6 * SIXTEENTH, -2 * SIXTEENTH),
+    (2 * SIXTEENTH, -6 * SIXTEENTH),
+];
+const PATTERN_8X: [(i32, i32); 8] = [
+    (SIXTEENTH, 3 * SIXTEENTH),
+    (-SIXTEENTH, -3 * SIXTEENTH),
+    (5 * SIXTEENTH, -SIXTEENTH),
+    (-3 * SIXTEENTH, 5 * SIXTEENTH),
+    (-5 * SIXTEENTH, -5 * SIXTEENTH),
+    (-7 * SIXTEENTH, SIXTEENTH),
+    (3 * SIXTEENTH, -7 * SIXTEENTH),
+    (7 * SIXTEENTH, 7 * SIXTEENTH),
+];
+
+// The sample positions of a pixel for this many samples
+pub fn sample_offsets(samples: SampleCount) -> &'static [(i32, i32)] {
+    match samples {
+        SampleCount::X1 => &PATTERN_1X,
+        SampleCount::X2 => &PATTERN_2X,
+        SampleCount::X4 => &PATTERN_4X,
+        SampleCount::X8 => &PATTERN_8X,
+    }
+}
+
+// Averages 0x00RRGGBB colors per channel, rounding to the closest value
+pub fn average_colors(colors: impl IntoIterator<Item = u32>) -> u32 {
+    let mut sums = [0u32; 3];
+    let mut count = 0;
+    for color in colors {
+        sums[0] += (color >> 16) & 0xff;
+        sums[1] += (color >> 8) & 0xff;
+        sums[2] += color & 0xff;
+        count += 1;
+    }
+    if count == 0 {
+        return 0;
+    }
+
+    let [r, g, b] = sums.map(|sum| (sum + count / 2) / count);
+    (r << 16) | (g << 8) | b
+}
@@ -0,0 +1,258 @@
+use std::ops::{Mul, Neg};
+
+use super::mat3::Mat3;
+use super::matrices::Matrix4x4;
+use super::vec3::Vec3;
+
+// The order Euler angles are applied in: XYZ rotates around x first, then y, then z
+// (all around the fixed world axes), which is the order ModelMatrix uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EulerOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+}
+
+impl EulerOrder {
+    // The axes (0 = x, 1 = y, 2 = z) in the order they are applied
+    fn axes(self) -> [usize; 3] {
+        match self {
+            EulerOrder::XYZ => [0, 1, 2],
+            EulerOrder::XZY => [0, 2, 1],
+            EulerOrder::YXZ => [1, 0, 2],
+            EulerOrder::YZX => [1, 2, 0],
+            EulerOrder::ZXY => [2, 0, 1],
+            EulerOrder::ZYX => [2, 1, 0],
+        }
+    }
+}
+
+// A rotation as a unit quaternion w + xi + yj + zk. Unlike Euler angles it has no
+// gimbal lock, and two orientations can be interpolated smoothly (see slerp)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(0.0, 0.0, 0.0, 1.0)
+    }
+
+    // A rotation of angle radians around axis (counterclockwise looking down the axis),
+    // the axis doesn't have to be normalized
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle * 0.5).sin_cos();
+
+        Self::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+    }
+
+    // The rotation of the Euler angles (x, y and z of angles, in radians) applied in order
+    pub fn from_euler(angles: Vec3, order: EulerOrder) -> Self {
+        let angles = angles.to_array();
+
+        order
+            .axes()
+            .iter()
+            .fold(Self::identity(), |rotation, &axis| {
+                let mut direction = [0.0; 3];
+                direction[axis] = 1.0;
+                let axis_rotation = Self::from_axis_angle(Vec3::from(direction), angles[axis]);
+                // Applied after the previous ones, so it goes on the left
+                axis_rotation * rotation
+            })
+    }
+
+    // Back to Euler angles (x, y and z of the result) for the given order.
+    // When the middle rotation is +-90 degrees (gimbal lock) the first and last
+    // axes line up, and the whole rotation around them is put in the first angle
+    pub fn to_euler(&self, order: EulerOrder) -> Vec3 {
+        let m = self.to_matrix().data;
+        let [i, j, k] = order.axes();
+        // +1.0 if the axes are in cyclic order (xyz, yzx, zxy), -1.0 otherwise
+        let sign = if (j + 3 - i) % 3 == 1 { 1.0 } else { -1.0 };
+
+        // atan2 instead of asin, which loses precision close to +-90 degrees
+        let mut angles = [0.0; 3];
+        let cos_middle = m[k][j].hypot(m[k][k]);
+        angles[j] = (-sign * m[k][i]).atan2(cos_middle);
+
+        if cos_middle > 1e-4 {
+            angles[i] = (sign * m[k][j]).atan2(m[k][k]);
+            angles[k] = (sign * m[j][i]).atan2(m[i][i]);
+        } else {
+            angles[i] = (-sign * m[j][k]).atan2(m[j][j]);
+            angles[k] = 0.0;
+        }
+
+        Vec3::from(angles)
+    }
+
+    // The rotation of an orthonormal matrix (without scale). Starts from the largest of
+    // w, x, y and z so the square root and the division stay precise
+    pub fn from_rotation_matrix(matrix: &Mat3) -> Self {
+        let m = &matrix.data;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+
+        let rotation = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+                0.25 * s,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            Self::new(
+                0.25 * s,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[2][1] - m[1][2]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            Self::new(
+                (m[0][1] + m[1][0]) / s,
+                0.25 * s,
+                (m[1][2] + m[2][1]) / s,
+                (m[0][2] - m[2][0]) / s,
+            )
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            Self::new(
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                0.25 * s,
+                (m[1][0] - m[0][1]) / s,
+            )
+        };
+
+        rotation.normalize()
+    }
+
+    pub fn dot(self, rhs: Quat) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    }
+
+    pub fn magnitude(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Self {
+        let inv = 1.0 / self.magnitude();
+        Self::new(self.x * inv, self.y * inv, self.z * inv, self.w * inv)
+    }
+
+    // The opposite rotation (the inverse, for a unit quaternion)
+    pub fn conjugate(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        // v + 2w (q x v) + 2 q x (q x v), with q the vector part
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = q.cross(v) * 2.0;
+        v + t * self.w + q.cross(t)
+    }
+
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        let Quat { x, y, z, w } = *self;
+        let mut matrix = Matrix4x4::identity();
+
+        matrix.data[0][0] = 1.0 - 2.0 * (y * y + z * z);
+        matrix.data[0][1] = 2.0 * (x * y - w * z);
+        matrix.data[0][2] = 2.0 * (x * z + w * y);
+
+        matrix.data[1][0] = 2.0 * (x * y + w * z);
+        matrix.data[1][1] = 1.0 - 2.0 * (x * x + z * z);
+        matrix.data[1][2] = 2.0 * (y * z - w * x);
+
+        matrix.data[2][0] = 2.0 * (x * z - w * y);
+        matrix.data[2][1] = 2.0 * (y * z + w * x);
+        matrix.data[2][2] = 1.0 - 2.0 * (x * x + y * y);
+
+        matrix
+    }
+
+    // Linear interpolation followed by a normalization: cheaper than slerp, but the
+    // speed isn't constant over t. Both take the shortest way around
+    pub fn nlerp(self, to: Quat, t: f32) -> Self {
+        let to = if self.dot(to) < 0.0 { -to } else { to };
+
+        Self::new(
+            self.x + (to.x - self.x) * t,
+            self.y + (to.y - self.y) * t,
+            self.z + (to.z - self.z) * t,
+            self.w + (to.w - self.w) * t,
+        )
+        .normalize()
+    }
+
+    // Spherical linear interpolation: rotates from self to to at a constant speed
+    pub fn slerp(self, to: Quat, t: f32) -> Self {
+        let mut cos = self.dot(to);
+        let to = if cos < 0.0 {
+            cos = -cos;
+            -to
+        } else {
+            to
+        };
+
+        // Almost the same rotation, sin(angle) would be too close to 0
+        if cos > 0.9995 {
+            return self.nlerp(to, t);
+        }
+
+        let angle = cos.acos();
+        let inv_sin = 1.0 / angle.sin();
+        let from_weight = ((1.0 - t) * angle).sin() * inv_sin;
+        let to_weight = (t * angle).sin() * inv_sin;
+
+        Self::new(
+            self.x * from_weight + to.x * to_weight,
+            self.y * from_weight + to.y * to_weight,
+            self.z * from_weight + to.z * to_weight,
+            self.w * from_weight + to.w * to_weight,
+        )
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+// q * r rotates by r first, then by q
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+        )
+    }
+}
+
+impl Neg for Quat {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
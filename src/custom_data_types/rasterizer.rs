@@ -1,318 +1,750 @@
-use super::color::Color;
-use super::depth_buffer::DepthBuffer;
-use super::draw_buffer::DrawBuffer;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::clipper::{ClipVertex, Clipper};
+use super::edge_equation::TriangleEdges;
+use super::error::Error;
+use super::material::Material;
+use super::mesh::Mesh;
+use super::multisample::{AntiAliasing, MAX_SAMPLES, SampleCount, average_colors, sample_offsets};
+use super::render_target::RenderTarget;
+use super::shader::{FragmentInput, FragmentShader, Uniforms, VertexInput, VertexShader};
+use super::varying::Varying;
+use super::vec2::Vec2;
+use super::vec3::Vec3;
 use super::vec4::Vec4;
-use crate::custom_data_types::camera::{self, Camera};
-use crate::custom_data_types::matrices::Matrix4x4;
 use crate::custom_data_types::scene::Scene;
 
+// Screen positions are stored in fixed point with this many fractional bits,
+// so vertices keep their sub-pixel position instead of snapping to a pixel corner
+pub const SUBPIXEL_BITS: i32 = 8;
+pub const SUBPIXEL_ONE: i32 = 1 << SUBPIXEL_BITS;
+pub const SUBPIXEL_HALF: i32 = SUBPIXEL_ONE / 2;
+
+// Size in pixels of the square tiles of the multithreaded path (must be even,
+// so the 2x2 quads never cross a tile)
+const TILE_SIZE: usize = 64;
+// Triangles set up together by a worker of the multithreaded path
+const SETUP_CHUNK: usize = 1024;
+
+// A vertex after the perspective divide and the viewport transform
+// (x and y are in fixed point, see SUBPIXEL_BITS)
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenVertex {
+    pub x: i32,
+    pub y: i32,
+    pub z: f32,
+    pub inv_w: f32,
+}
+
+// The triangle being drawn, as the fragment shader sees it
+#[derive(Clone, Copy)]
+struct Primitive<'a> {
+    // Index of the triangle in the mesh
+    id: usize,
+    material: &'a Material,
+    uniforms: &'a Uniforms,
+}
+
+// A triangle that survived clipping and culling, ready to be filled
+struct ScreenTriangle<'a, V> {
+    vertices: [(ScreenVertex, V); 3],
+    primitive: Primitive<'a>,
+}
+
+// The rows of the render target fill_triangle draws into, as slices of the buffers.
+// The single-threaded path uses the whole target, the tiled one a row of tiles per worker.
+// With MSAA these are the sample buffers, samples values per pixel
+struct Band<'a> {
+    color: &'a mut [u32],
+    depth: &'a mut [f32],
+    width: usize,
+    sample_count: SampleCount,
+    samples: usize,
+    // Screen y (going up) of the first and one past the last row
+    y_start: i32,
+    y_end: i32,
+}
+
+impl<'a> Band<'a> {
+    fn new(
+        color: &'a mut [u32],
+        depth: &'a mut [f32],
+        width: usize,
+        sample_count: SampleCount,
+        y_start: usize,
+    ) -> Self {
+        let samples = sample_count.count();
+        let rows = color.len() / (width * samples);
+        Self {
+            color,
+            depth,
+            width,
+            sample_count,
+            samples,
+            y_start: y_start as i32,
+            y_end: (y_start + rows) as i32,
+        }
+    }
+
+    // Like DrawBuffer::set, the rows are stored top row first.
+    // The index of the first sample of the pixel
+    fn index(&self, x: i32, y: i32) -> usize {
+        debug_assert!(y >= self.y_start && y < self.y_end);
+        ((self.y_end - 1 - y) as usize * self.width + x as usize) * self.samples
+    }
+}
+
 pub struct Rasterizer {
-    pub draw_buffer: DrawBuffer,
-    pub depth_buffer: DepthBuffer,
+    pub target: RenderTarget,
+    pub clipper: Clipper,
+    // Threads used by draw_scene, 1 draws everything on the calling thread
+    pub threads: usize,
+    pub anti_aliasing: AntiAliasing,
+    // The high resolution target of AntiAliasing::Ssaa, kept between frames
+    supersampled: RenderTarget,
 }
 
 impl Rasterizer {
-    pub fn new(draw_buffer: DrawBuffer, depth_buffer: DepthBuffer) -> Self {
+    pub fn new(target: RenderTarget) -> Self {
         Self {
-            draw_buffer,
-            depth_buffer,
+            target,
+            clipper: Clipper::new(1.0),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            anti_aliasing: AntiAliasing::Off,
+            supersampled: RenderTarget::new(0, 0),
         }
     }
 
     // From NDC [-1.0, 1.0] to screen coordinates [0-1920; 0-1080]
-    fn viewport_transform(&self, point: Vec4) -> (i32, i32, f32) {
-        let x = (point.x + 1.0) * 0.5 * self.draw_buffer.buffer_width() as f32;
-        let y = (point.y + 1.0) * 0.5 * self.draw_buffer.buffer_height() as f32;
+    // inv_w (1 / clip w) is carried along for perspective-correct interpolation
+    fn viewport_transform(&self, point: Vec3, inv_w: f32) -> ScreenVertex {
+        let x = (point.x + 1.0) * 0.5 * self.target.draw_buffer.buffer_width() as f32;
+        let y = (point.y + 1.0) * 0.5 * self.target.draw_buffer.buffer_height() as f32;
         let z = (point.z + 1.0) * 0.5;
 
-        (x as i32, y as i32, z)
+        ScreenVertex {
+            x: (x * SUBPIXEL_ONE as f32).round() as i32,
+            y: (y * SUBPIXEL_ONE as f32).round() as i32,
+            z,
+            inv_w,
+        }
     }
 
-    fn edge_function(a: (i32, i32, f32), b: (i32, i32, f32), c: (i32, i32, f32)) -> i32 {
-        let result = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
-        result
+    // Done in i64: the fixed point coordinates would overflow an i32 product
+    fn edge_function(a: ScreenVertex, b: ScreenVertex, c: (i32, i32)) -> i64 {
+        (b.x - a.x) as i64 * (c.1 - a.y) as i64 - (b.y - a.y) as i64 * (c.0 - a.x) as i64
     }
 
-    // Checks if point is in the triangle and also returns the z coordinate of the point
-    fn in_triangle(
-        &self,
-        a: (i32, i32, f32),
-        b: (i32, i32, f32),
-        c: (i32, i32, f32),
-        n_a: Vec4,
-        n_b: Vec4,
-        n_c: Vec4,
-        point: (i32, i32, f32),
-    ) -> (bool, f32, Vec4) {
-        let abp = Self::edge_function(a, b, point) as f32;
-        let bcp = Self::edge_function(b, c, point) as f32;
-        let cap = Self::edge_function(c, a, point) as f32;
-        let total_area = abp + bcp + cap;
-        let weight_c = abp / total_area;
-        let weight_a = bcp / total_area;
-        let weight_b = cap / total_area;
-
-        let z_coord = c.2 * weight_c + b.2 * weight_b + a.2 * weight_a;
-        let pixel_normal = (n_a * weight_a + n_b * weight_b + n_c * weight_c).normalize();
-        return (
-            abp >= 0.0 && bcp >= 0.0 && cap >= 0.0,
-            z_coord,
-            pixel_normal,
-        );
+    // Returns the z coordinate and the attributes of the point with these weights
+    // (inv_area is 1 / their sum, see TriangleEdges::area).
+    // The attributes must already be divided by w (see fill_triangle): the screen-space
+    // weights are used for z, and the perspective-correct ones for the attributes
+    fn interpolate<V: Varying>(
+        a: ScreenVertex,
+        b: ScreenVertex,
+        c: ScreenVertex,
+        attributes: &[V; 3],
+        weights: [i64; 3],
+        inv_area: f32,
+    ) -> (f32, V) {
+        let [weight_a, weight_b, weight_c] = weights.map(|weight| weight as f32 * inv_area);
+
+        let z_coord = Self::interpolate_depth(a, b, c, weights, inv_area);
+
+        // 1/w is linear in screen space, so the interpolated (attribute / w) divided
+        // by the interpolated 1/w gives the attribute as if it was interpolated in 3D
+        let inv_w = a.inv_w * weight_a + b.inv_w * weight_b + c.inv_w * weight_c;
+        let attribute = V::barycentric(
+            attributes[0],
+            attributes[1],
+            attributes[2],
+            weight_a,
+            weight_b,
+            weight_c,
+        )
+        .scale(1.0 / inv_w);
+
+        (z_coord, attribute)
     }
 
-    // Creates the bounding box and then checks which pixel is inside the triangle,
-    // also updates the depth buffer/uses it to NOT draw triangles in the background
-    // over the ones in the foreground
-    fn fill_triangle(
-        &mut self,
-        a: (i32, i32, f32),
-        b: (i32, i32, f32),
-        c: (i32, i32, f32),
-        n_a: Vec4,
-        n_b: Vec4,
-        n_c: Vec4,
-        color: Color,
+    // Only the z coordinate of interpolate, for the MSAA samples
+    fn interpolate_depth(
+        a: ScreenVertex,
+        b: ScreenVertex,
+        c: ScreenVertex,
+        weights: [i64; 3],
+        inv_area: f32,
+    ) -> f32 {
+        let [weight_a, weight_b, weight_c] = weights.map(|weight| weight as f32 * inv_area);
+        c.z * weight_c + b.z * weight_b + a.z * weight_a
+    }
+
+    // The range of pixels whose centers can be covered by the triangle, clamped to the
+    // rectangle [x0, x1) x [y0, y1). Returns (min_x, min_y, max_x, max_y), max excluded.
+    // With multisampling the samples can be up to half a pixel away from the centers
+    fn pixel_bounds(
+        a: ScreenVertex,
+        b: ScreenVertex,
+        c: ScreenVertex,
+        (x0, y0, x1, y1): (i32, i32, i32, i32),
+        multisampled: bool,
+    ) -> (i32, i32, i32, i32) {
+        let margin = if multisampled { SUBPIXEL_HALF } else { 0 };
+        let min_x = a.x.min(b.x.min(c.x)) - margin;
+        let min_y = a.y.min(b.y.min(c.y)) - margin;
+        let max_x = a.x.max(b.x.max(c.x)) + margin;
+        let max_y = a.y.max(b.y.max(c.y)) + margin;
+
+        // From fixed point to pixels
+        let min_x = ((min_x - SUBPIXEL_HALF) >> SUBPIXEL_BITS).max(x0);
+        let min_y = ((min_y - SUBPIXEL_HALF) >> SUBPIXEL_BITS).max(y0);
+        let max_x = (((max_x - SUBPIXEL_HALF) >> SUBPIXEL_BITS) + 1).min(x1);
+        let max_y = (((max_y - SUBPIXEL_HALF) >> SUBPIXEL_BITS) + 1).min(y1);
+
+        (min_x, min_y, max_x, max_y)
+    }
+
+    // Creates the bounding box (clamped to rect, whose corner must be on even coordinates)
+    // and then checks which pixel is inside the triangle, also updates the depth buffer/uses
+    // it to NOT draw triangles in the background over the ones in the foreground.
+    // Pixels are sampled at their centers, or at the MSAA sample positions
+    fn fill_triangle<V: Varying, FS: FragmentShader<V>>(
+        band: &mut Band,
+        rect: (i32, i32, i32, i32),
+        triangle: &ScreenTriangle<V>,
+        fragment_shader: &FS,
     ) {
-        let min_x = a.0.min(b.0.min(c.0));
-        let min_y = a.1.min(b.1.min(c.1));
-        let max_x = a.0.max(b.0.max(c.0));
-        let max_y = a.1.max(b.1.max(c.1));
-
-        let min_x = min_x.max(0i32);
-        let min_y = min_y.max(0i32);
-        let max_x = max_x.min(self.draw_buffer.buffer_width() as i32);
-        let max_y = max_y.min(self.draw_buffer.buffer_height() as i32);
-
-        // Default light, going in from the camera: To be properly implemented
-        let light = Vec4::new(5.0, 1.0, -0.25, 0.0).normalize();
-
-        for i in min_y..max_y {
-            for j in min_x..max_x {
-                let (in_trig, z_coord, pixel_normal) =
-                    Self::in_triangle(&self, a, b, c, n_a, n_b, n_c, (j, i, 0.0));
-                let buffer_value = self.depth_buffer.get(i as usize, j as usize);
-
-                if in_trig && z_coord < buffer_value {
-                    self.depth_buffer.set(i as usize, j as usize, z_coord);
-                    let intensity = pixel_normal.dot(light).max(0.15);
-
-                    self.draw_buffer.set(
-                        i as usize,
-                        j as usize,
-                        Color::mix(color, Color::new(0, 0, 0, 255), intensity),
-                        //Color::new(depth_color, depth_color, depth_color, depth_color),
-                    );
-                }
-            }
+        let [(a, v_a), (b, v_b), (c, v_c)] = triangle.vertices;
+        let primitive = &triangle.primitive;
+
+        let edges = TriangleEdges::new(a, b, c);
+        let inv_area = 1.0 / edges.area() as f32;
+
+        // Divided by w once per vertex instead of once per pixel
+        let varyings = [v_a.scale(a.inv_w), v_b.scale(b.inv_w), v_c.scale(c.inv_w)];
+
+        let samples = sample_offsets(band.sample_count);
+        let mut deltas = [[0; 3]; MAX_SAMPLES];
+        for (delta, &offset) in deltas.iter_mut().zip(samples) {
+            *delta = edges.sample_delta(offset);
         }
+
+        // Pixels are shaded in 2x2 quads (aligned to even coordinates), so the
+        // fragment shader gets the screen space derivatives of the varyings
+        let bounds = Self::pixel_bounds(a, b, c, rect, samples.len() > 1);
+        edges.for_each_quad(bounds, band.sample_count, |quad_x, quad_y, coverage| {
+            Self::shade_quad(
+                band,
+                (quad_x, quad_y),
+                coverage,
+                edges.quad_weights(quad_x, quad_y),
+                &deltas[..samples.len()],
+                (a, b, c),
+                &varyings,
+                inv_area,
+                fragment_shader,
+                primitive,
+            );
+        });
     }
 
-    // Used to find the intserection between a line (triangle edge) and the
-    // z_near plane
-    fn find_t(a: Vec4, b: Vec4, camera: &Camera) -> f32 {
-        (-a.z - camera.z_near) / (b.z - a.z)
+    // Runs the fragment shader once on every pixel of a quad with a covered sample that
+    // passes the depth test (bit s of coverage[k] for the sample s of the pixel k of weights,
+    // deltas[s] being the offset of the sample's weights). The color goes to these samples
+    #[allow(clippy::too_many_arguments)]
+    fn shade_quad<V: Varying, FS: FragmentShader<V>>(
+        band: &mut Band,
+        (quad_x, quad_y): (i32, i32),
+        coverage: [u8; 4],
+        weights: [[i64; 3]; 4],
+        deltas: &[[i64; 3]],
+        (a, b, c): (ScreenVertex, ScreenVertex, ScreenVertex),
+        varyings: &[V; 3],
+        inv_area: f32,
+        fragment_shader: &FS,
+        primitive: &Primitive,
+    ) {
+        let pixels = [
+            (quad_x, quad_y),
+            (quad_x + 1, quad_y),
+            (quad_x, quad_y + 1),
+            (quad_x + 1, quad_y + 1),
+        ];
+
+        // Every pixel of the quad is interpolated, even the ones outside of the
+        // triangle, otherwise there would be nothing to take the differences from
+        let interpolated = weights.map(|w| Self::interpolate(a, b, c, varyings, w, inv_area));
+        let ddx = interpolated[1].1.add(interpolated[0].1.scale(-1.0));
+        let ddy = interpolated[2].1.add(interpolated[0].1.scale(-1.0));
+
+        for k in 0..4 {
+            if coverage[k] == 0 {
+                continue;
+            }
+
+            let (j, i) = pixels[k];
+            let (z_coord, pixel_varyings) = interpolated[k];
+            let index = band.index(j, i);
+
+            // The depth is tested per sample, with z interpolated at the sample
+            let mut depths = [0.0; MAX_SAMPLES];
+            let mut passed = 0u8;
+            for (sample, delta) in deltas.iter().enumerate() {
+                if coverage[k] & (1 << sample) == 0 {
+                    continue;
+                }
+                depths[sample] = if *delta == [0; 3] {
+                    z_coord
+                } else {
+                    let sample_weights = [0, 1, 2].map(|e| weights[k][e] + delta[e]);
+                    Self::interpolate_depth(a, b, c, sample_weights, inv_area)
+                };
+                if depths[sample] < band.depth[index + sample] {
+                    passed |= 1 << sample;
+                }
+            }
+
+            if passed != 0 {
+                let fragment = FragmentInput {
+                    varyings: pixel_varyings,
+                    ddx,
+                    ddy,
+                    x: j as usize,
+                    y: i as usize,
+                    depth: z_coord,
+                    primitive_id: primitive.id,
+                    material: primitive.material,
+                    uniforms: primitive.uniforms,
+                };
+
+                if let Some(color) = fragment_shader.shade(&fragment) {
+                    let color = color.format_as_u32();
+                    for (sample, &depth) in depths[..deltas.len()].iter().enumerate() {
+                        if passed & (1 << sample) != 0 {
+                            band.depth[index + sample] = depth;
+                            band.color[index + sample] = color;
+                        }
+                    }
+                }
+            }
+        }
     }
 
-    fn lerp(a: Vec4, b: Vec4, t: f32) -> Vec4 {
-        a + (b - a) * t
+    // Clips the triangle against the view frustum, then transforms the resulting
+    // polygon from clip to screen space (triangles fully outside get culled).
+    // The polygon keeps the CCW order of the triangle, so it can be drawn as a fan
+    pub fn world_to_screen<V: Varying>(
+        &self,
+        v0: ClipVertex<V>,
+        v1: ClipVertex<V>,
+        v2: ClipVertex<V>,
+    ) -> Vec<(ScreenVertex, V)> {
+        self.clipper
+            .clip_triangle(v0, v1, v2)
+            .into_iter()
+            .map(|(v, attribute)| {
+                let inv_w = 1.0 / v.w;
+                let ndc = v.xyz() * inv_w; // Perspective Divide
+                (self.viewport_transform(ndc, inv_w), attribute)
+            })
+            .collect()
     }
 
-    // V0 is never behind the camera; V1 is in case == 2; V2 is in case == 1 || case == 2
-    fn handle_behind_camera(
+    // Runs the vertex shader on the corners of the triangle, then clips and culls it.
+    // What is left (the clipped polygon, as a fan) is added to output
+    fn setup_triangle<'a, VS: VertexShader>(
         &self,
-        v0: Vec4,
-        v1: Vec4,
-        v2: Vec4,
-        behind_camera: i32,
-        camera: &Camera,
-    ) -> (
-        Option<(i32, i32, f32)>,
-        Option<(i32, i32, f32)>,
-        Option<(i32, i32, f32)>,
-        Option<(i32, i32, f32)>,
+        mesh: &'a Mesh,
+        triangle: usize,
+        uniforms: &'a Uniforms,
+        vertex_shader: &VS,
+        output: &mut Vec<ScreenTriangle<'a, VS::Varyings>>,
     ) {
-        let to_screen = |v: Vec4| -> (i32, i32, f32) {
-            let ndc = v / v.w; // Perspective Divide
-            Self::viewport_transform(self, ndc)
+        let primitive = Primitive {
+            id: triangle,
+            material: mesh.material(triangle),
+            uniforms,
         };
+        let i = triangle * 3;
+        let idx = [mesh.indices[i], mesh.indices[i + 1], mesh.indices[i + 2]];
+        let positions = idx.map(|index| mesh.vertices[index]);
 
-        if behind_camera == 2 {
-            let t1 = Self::find_t(v0, v1, camera);
-            let t2 = Self::find_t(v0, v2, camera);
-            let new_v1 = Self::lerp(v0, v1, t1);
-            let new_v2 = Self::lerp(v0, v2, t2);
-
-            return (
-                Some(to_screen(v0)),
-                Some(to_screen(new_v1)),
-                Some(to_screen(new_v2)),
-                None,
-            );
-        } else if behind_camera == 1 {
-            let t_12 = Self::find_t(v1, v2, camera);
-            let t_02 = Self::find_t(v0, v2, camera);
-
-            let new_v1 = Self::lerp(v1, v2, t_12);
-            let new_v0 = Self::lerp(v0, v2, t_02);
-
-            return (
-                Some(to_screen(v0)),
-                Some(to_screen(v1)),
-                Some(to_screen(new_v1)),
-                Some(to_screen(new_v0)),
-            );
-        }
+        let normals = if !mesh.normals.is_empty() {
+            idx.map(|index| mesh.normals[index])
+        } else {
+            // Fallback to flat shading
+            let edge0 = positions[1] - positions[0];
+            let edge1 = positions[2] - positions[0];
+            [edge0.cross(edge1).normalize(); 3]
+        };
 
-        (
-            Some(to_screen(v0)),
-            Some(to_screen(v1)),
-            Some(to_screen(v2)),
-            None,
-        )
+        let uvs = if !mesh.uvs.is_empty() {
+            idx.map(|index| mesh.uvs[index])
+        } else {
+            [Vec2::zero(); 3]
+        };
+
+        let colors = if !mesh.colors.is_empty() {
+            idx.map(|index| mesh.colors[index])
+        } else {
+            [Vec4::new(1.0, 1.0, 1.0, 1.0); 3]
+        };
+
+        let [v0, v1, v2] = [0, 1, 2].map(|k| {
+            vertex_shader.shade(&VertexInput {
+                position: positions[k],
+                normal: normals[k],
+                uv: uvs[k],
+                color: colors[k],
+                uniforms,
+            })
+        });
+
+        let polygon = self.world_to_screen(v0, v1, v2);
+
+        // The clipped polygon is convex, so it is drawn as a triangle fan
+        for k in 1..polygon.len().saturating_sub(1) {
+            let (p0, p1, p2) = (polygon[0], polygon[k], polygon[k + 1]);
+
+            let area = Self::edge_function(p0.0, p1.0, (p2.0.x, p2.0.y));
+            if area <= 0 {
+                continue;
+            }
+
+            output.push(ScreenTriangle {
+                vertices: [p0, p1, p2],
+                primitive,
+            });
+        }
     }
 
-    // Transforms the vertices from local to screen space,
-    // also handles triangles that have 1 or 2 vertices behind the camera
-    // (triangles fully behind get culled)
-    // Might return 4 points if there is one point behind the camera
-    pub fn world_to_screen(
-        &self,
-        v0: Vec4,
-        v1: Vec4,
-        v2: Vec4,
-        camera: &Camera,
-    ) -> (
-        Option<(i32, i32, f32)>,
-        Option<(i32, i32, f32)>,
-        Option<(i32, i32, f32)>,
-        Option<(i32, i32, f32)>,
-    ) {
-        let mut behind_camera = (false, false, false);
-        let mut total_behind_camera = 0;
-        if v0.w < camera.z_near {
-            behind_camera.0 = true;
-            total_behind_camera += 1;
+    // Draws every object in the scene, running the vertex shader on every
+    // triangle corner and the fragment shader on every covered pixel.
+    // The target is switched to the samples anti_aliasing needs, and with MSAA
+    // the samples are resolved into its draw buffer at the end.
+    // Nothing is drawn if an object uses a mesh the scene doesn't have
+    pub fn draw_scene<VS, FS>(
+        &mut self,
+        scene: &Scene,
+        vertex_shader: &VS,
+        fragment_shader: &FS,
+    ) -> Result<(), Error>
+    where
+        VS: VertexShader + Sync,
+        VS::Varyings: Send + Sync,
+        FS: FragmentShader<VS::Varyings> + Sync,
+    {
+        if let Some(obj) = scene
+            .objects
+            .iter()
+            .find(|obj| obj.object_id >= scene.meshes.len())
+        {
+            return Err(Error::InvalidIndex {
+                what: "mesh",
+                index: obj.object_id,
+                len: scene.meshes.len(),
+            });
         }
-        if v1.w < camera.z_near {
-            behind_camera.1 = true;
-            total_behind_camera += 1;
+
+        // The fields of meshes are public, so the ones drawn are checked again here:
+        // a bad index would otherwise panic in the middle of the frame
+        let mut used = vec![false; scene.meshes.len()];
+        for obj in &scene.objects {
+            used[obj.object_id] = true;
         }
-        if v2.w < camera.z_near {
-            behind_camera.2 = true;
-            total_behind_camera += 1;
+        for (mesh, _) in scene.meshes.iter().zip(used).filter(|(_, used)| *used) {
+            mesh.validate()?;
         }
 
-        if total_behind_camera == 3 {
-            return (None, None, None, None);
-        } else if total_behind_camera == 2 {
-            if !behind_camera.0 {
-                // Important: the relative order of the vertices must be kept, so they are CCW
-                // (any shift of v0-v1-v2 is valid)
-                return Self::handle_behind_camera(&self, v0, v1, v2, total_behind_camera, camera);
-            } else if !behind_camera.1 {
-                return Self::handle_behind_camera(&self, v1, v2, v0, total_behind_camera, camera);
-            } else {
-                return Self::handle_behind_camera(&self, v2, v0, v1, total_behind_camera, camera);
-            }
-        } else if total_behind_camera == 1 {
-            if behind_camera.0 {
-                return Self::handle_behind_camera(&self, v1, v2, v0, total_behind_camera, camera);
-            } else if behind_camera.1 {
-                return Self::handle_behind_camera(&self, v2, v0, v1, total_behind_camera, camera);
-            } else {
-                return Self::handle_behind_camera(&self, v0, v1, v2, total_behind_camera, camera);
-            }
-        } else {
-            return Self::handle_behind_camera(&self, v0, v1, v2, total_behind_camera, camera);
+        if self.target.width() == 0 || self.target.height() == 0 {
+            return Ok(());
         }
-    }
 
-    // Draws every object in the scene and applies basic lightning (WIP)
-    pub fn draw_scene(&mut self, scene: &Scene, colors: &Vec<Color>) {
         let view_matrix = scene.camera.get_view_matrix();
         let projection_matrix = scene.camera.get_projection_matrix();
         let pv = projection_matrix * view_matrix;
 
-        for obj in &scene.objects {
-            let model_matrix = obj.model_matrix.get_model_matrix();
-
-            let mesh = &scene.meshes[obj.object_id];
+        let uniforms: Vec<Uniforms> = scene
+            .objects
+            .iter()
+            .map(|obj| Uniforms {
+                model_matrix: obj.model_matrix.get_model_matrix(),
+                view_projection: pv,
+                normal_matrix: obj.model_matrix.normal_matrix(),
+                camera_position: scene.camera.model.translation(),
+            })
+            .collect();
+
+        match self.anti_aliasing {
+            AntiAliasing::Off => {
+                self.target.set_samples(SampleCount::X1);
+                self.draw_triangles(scene, &uniforms, vertex_shader, fragment_shader);
+            }
+            AntiAliasing::Msaa(samples) => {
+                self.target.set_samples(samples);
+                self.draw_triangles(scene, &uniforms, vertex_shader, fragment_shader);
+                self.target.resolve();
+            }
+            AntiAliasing::Ssaa(factor) => {
+                self.target.set_samples(SampleCount::X1);
+                self.draw_supersampled(factor, scene, &uniforms, vertex_shader, fragment_shader);
+            }
+        }
 
-            for i in (0..mesh.indices.len()).step_by(3) {
-                let idx0 = mesh.indices[i];
-                let idx1 = mesh.indices[i + 1];
-                let idx2 = mesh.indices[i + 2];
+        Ok(())
+    }
 
-                // Transform verties directly from local to world space
-                let v0_world = model_matrix * mesh.vertices[idx0];
-                let v1_world = model_matrix * mesh.vertices[idx1];
-                let v2_world = model_matrix * mesh.vertices[idx2];
+    // Draws the scene into the target, on this thread or with the tiled path
+    // when there are more threads (see draw_tiled)
+    fn draw_triangles<VS, FS>(
+        &mut self,
+        scene: &Scene,
+        uniforms: &[Uniforms],
+        vertex_shader: &VS,
+        fragment_shader: &FS,
+    ) where
+        VS: VertexShader + Sync,
+        VS::Varyings: Send + Sync,
+        FS: FragmentShader<VS::Varyings> + Sync,
+    {
+        if self.threads > 1 {
+            self.draw_tiled(scene, uniforms, vertex_shader, fragment_shader);
+            return;
+        }
 
-                // Clip space vertices
-                let v0 = pv * v0_world;
-                let v1 = pv * v1_world;
-                let v2 = pv * v2_world;
+        let screen = (
+            0,
+            0,
+            self.target.width() as i32,
+            self.target.height() as i32,
+        );
+        let mut triangles = vec![];
 
-                let mut n0;
-                let mut n1;
-                let mut n2;
+        for (obj, uniforms) in scene.objects.iter().zip(uniforms) {
+            let mesh = &scene.meshes[obj.object_id];
 
-                let (opt0, opt1, opt2, opt3) = self.world_to_screen(v0, v1, v2, &scene.camera);
+            for triangle in 0..mesh.indices.len() / 3 {
+                triangles.clear();
+                self.setup_triangle(mesh, triangle, uniforms, vertex_shader, &mut triangles);
 
-                // Draws the first triangle (maybe the only one)
-                if let (Some(p0), Some(p1), Some(p2)) = (opt0, opt1, opt2) {
-                    let area = Self::edge_function(p0, p1, p2);
-                    if area <= 0 {
-                        continue;
-                    }
+                let sample_count = self.target.sample_count();
+                let (color, depth) = self.target.sample_buffers();
+                let mut band = Band::new(color, depth, screen.2 as usize, sample_count, 0);
+                for triangle in &triangles {
+                    Self::fill_triangle(&mut band, screen, triangle, fragment_shader);
+                }
+            }
+        }
+    }
 
-                    if !mesh.normals.is_empty() {
-                        n0 = mesh.normals[idx0];
-                        n1 = mesh.normals[idx1];
-                        n2 = mesh.normals[idx2];
-
-                        if !obj.model_matrix.inverse_needed_for_normals() {
-                            n0 = (model_matrix * n0).normalize();
-                            n1 = (model_matrix * n1).normalize();
-                            n2 = (model_matrix * n2).normalize();
-                        } else {
-                            println!("Cannot handle non-uniform scaling yet!");
+    // Binning rasterizer: the triangles are set up in parallel (in chunks, put back in
+    // order afterwards), sorted into TILE_SIZE x TILE_SIZE tiles, and then every worker
+    // takes a row of tiles and draws them. The triangles of a tile are drawn in the
+    // order they were submitted and quads never cross tiles, so the result is exactly
+    // the same as with the single-threaded path
+    fn draw_tiled<VS, FS>(
+        &mut self,
+        scene: &Scene,
+        uniforms: &[Uniforms],
+        vertex_shader: &VS,
+        fragment_shader: &FS,
+    ) where
+        VS: VertexShader + Sync,
+        VS::Varyings: Send + Sync,
+        FS: FragmentShader<VS::Varyings> + Sync,
+    {
+        let width = self.target.width();
+        let height = self.target.height();
+        let sample_count = self.target.sample_count();
+        let samples = sample_count.count();
+
+        // (object, first triangle, last triangle + 1)
+        let jobs: Vec<(usize, usize, usize)> = scene
+            .objects
+            .iter()
+            .enumerate()
+            .flat_map(|(object, obj)| {
+                let triangles = scene.meshes[obj.object_id].indices.len() / 3;
+                (0..triangles)
+                    .step_by(SETUP_CHUNK)
+                    .map(move |first| (object, first, (first + SETUP_CHUNK).min(triangles)))
+            })
+            .collect();
+
+        let this = &*self;
+        let next_job = AtomicUsize::new(0);
+        let mut chunks: Vec<(usize, Vec<ScreenTriangle<VS::Varyings>>)> = thread::scope(|s| {
+            let workers: Vec<_> = (0..self.threads)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let job = next_job.fetch_add(1, Ordering::Relaxed);
+                            let Some(&(object, first, last)) = jobs.get(job) else {
+                                break;
+                            };
+
+                            let mesh = &scene.meshes[scene.objects[object].object_id];
+                            let mut triangles = vec![];
+                            for triangle in first..last {
+                                this.setup_triangle(
+                                    mesh,
+                                    triangle,
+                                    &uniforms[object],
+                                    vertex_shader,
+                                    &mut triangles,
+                                );
+                            }
+                            done.push((job, triangles));
                         }
-                    } else {
-                        // Fallback to flat shading
-                        let edge0 = v1_world - v0_world;
-                        let edge1 = v2_world - v0_world;
-                        let mut normal = edge0.cross(edge1).normalize();
-                        normal.w = 0.0001;
-                        n0 = normal;
-                        n1 = normal;
-                        n2 = normal;
-                    }
+                        done
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        chunks.sort_by_key(|&(job, _)| job);
+        let triangles: Vec<ScreenTriangle<VS::Varyings>> = chunks
+            .into_iter()
+            .flat_map(|(_, triangles)| triangles)
+            .collect();
+
+        // Every tile gets the indices of the triangles whose bounding box touches it
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        let screen = (0, 0, width as i32, height as i32);
+        let mut bins: Vec<Vec<usize>> = vec![vec![]; tiles_x * tiles_y];
+
+        for (index, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.vertices.map(|(vertex, _)| vertex);
+            let (min_x, min_y, max_x, max_y) = Self::pixel_bounds(a, b, c, screen, samples > 1);
+            if min_x >= max_x || min_y >= max_y {
+                continue;
+            }
+
+            let tile = TILE_SIZE as i32;
+            for tile_y in (min_y / tile)..=((max_y - 1) / tile) {
+                for tile_x in (min_x / tile)..=((max_x - 1) / tile) {
+                    bins[tile_y as usize * tiles_x + tile_x as usize].push(index);
+                }
+            }
+        }
 
-                    self.fill_triangle(p0, p1, p2, n0, n1, n2, colors[i % colors.len()]);
+        // Screen y goes up but the buffers are stored top row first,
+        // so the rows of tiles are cut starting from the end of the buffers
+        let band_size = TILE_SIZE * width * samples;
+        let (color, depth) = self.target.sample_buffers();
+        let bands = Mutex::new(
+            color
+                .rchunks_mut(band_size)
+                .zip(depth.rchunks_mut(band_size))
+                .enumerate(),
+        );
 
-                    // If there was a point behind the camera, draws the other triangle that
-                    // resulted
-                    if let Some(p3) = opt3 {
-                        let area = Self::edge_function(p0, p2, p3);
-                        if area <= 0 {
-                            continue;
+        thread::scope(|s| {
+            for _ in 0..self.threads {
+                s.spawn(|| {
+                    loop {
+                        let next = bands.lock().unwrap().next();
+                        let Some((tile_y, (color, depth))) = next else {
+                            break;
+                        };
+
+                        let mut band =
+                            Band::new(color, depth, width, sample_count, tile_y * TILE_SIZE);
+                        for tile_x in 0..tiles_x {
+                            let rect = (
+                                (tile_x * TILE_SIZE) as i32,
+                                (tile_y * TILE_SIZE) as i32,
+                                ((tile_x + 1) * TILE_SIZE).min(width) as i32,
+                                ((tile_y + 1) * TILE_SIZE).min(height) as i32,
+                            );
+
+                            for &index in &bins[tile_y * tiles_x + tile_x] {
+                                Self::fill_triangle(
+                                    &mut band,
+                                    rect,
+                                    &triangles[index],
+                                    fragment_shader,
+                                );
+                            }
                         }
-
-                        // TODO: Actually implement the normal for this case
-                        self.fill_triangle(
-                            p0,
-                            p2,
-                            p3,
-                            n0,
-                            n2,
-                            Vec4::new(1.0, 1.0, 1.0, 0.0001),
-                            colors[i % colors.len()],
-                        );
                     }
+                });
+            }
+        });
+    }
+
+    // SSAA: the scene is drawn into a target factor times larger in both directions,
+    // which starts as a copy of the current content so the draw composes like
+    // a normal one. Then every factor x factor block is averaged back into one pixel
+    // (the depth keeps the closest)
+    fn draw_supersampled<VS, FS>(
+        &mut self,
+        factor: NonZeroUsize,
+        scene: &Scene,
+        uniforms: &[Uniforms],
+        vertex_shader: &VS,
+        fragment_shader: &FS,
+    ) where
+        VS: VertexShader + Sync,
+        VS::Varyings: Send + Sync,
+        FS: FragmentShader<VS::Varyings> + Sync,
+    {
+        let factor = factor.get();
+        let width = self.target.width();
+        let height = self.target.height();
+        let large_width = width * factor;
+
+        self.supersampled.resize(large_width, height * factor);
+        {
+            let colors = self.target.draw_buffer.buffer();
+            let depths = self.target.depth_buffer.buffer();
+            let (large_colors, large_depths) = self.supersampled.sample_buffers();
+
+            for (row, (color_row, depth_row)) in large_colors
+                .chunks_mut(large_width)
+                .zip(large_depths.chunks_mut(large_width))
+                .enumerate()
+            {
+                let source = (row / factor) * width;
+                for x in 0..large_width {
+                    color_row[x] = colors[source + x / factor];
+                    depth_row[x] = depths[source + x / factor];
                 }
             }
         }
+
+        std::mem::swap(&mut self.target, &mut self.supersampled);
+        self.draw_triangles(scene, uniforms, vertex_shader, fragment_shader);
+        std::mem::swap(&mut self.target, &mut self.supersampled);
+
+        // The indices of the large pixels of the pixel index
+        let block = |index: usize| {
+            let (x, y) = (index % width, index / width);
+            (0..factor).flat_map(move |j| {
+                let start = (y * factor + j) * large_width + x * factor;
+                start..start + factor
+            })
+        };
+
+        let large_colors = self.supersampled.draw_buffer.buffer();
+        for (index, color) in self.target.draw_buffer.buffer_mut().iter_mut().enumerate() {
+            *color = average_colors(block(index).map(|large| large_colors[large]));
+        }
+
+        let large_depths = self.supersampled.depth_buffer.buffer();
+        for (index, depth) in self.target.depth_buffer.buffer_mut().iter_mut().enumerate() {
+            *depth = block(index).fold(f32::INFINITY, |closest, large| {
+                closest.min(large_depths[large])
+            });
+        }
     }
 }
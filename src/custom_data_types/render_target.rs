@@ -0,0 +1,106 @@
+use super::color::Color;
+use super::depth_buffer::DepthBuffer;
+use super::draw_buffer::DrawBuffer;
+use super::multisample::{SampleCount, average_colors};
+
+// The color and depth buffers the rasterizer draws into. Doesn't know anything
+// about windows, so it can be used headless (the window glue is in window.rs)
+pub struct RenderTarget {
+    pub draw_buffer: DrawBuffer,
+    pub depth_buffer: DepthBuffer,
+    // With MSAA the color of every sample (stored like the depth buffer), which
+    // resolve averages into draw_buffer. Empty with one sample per pixel
+    sample_colors: Vec<u32>,
+    sample_count: SampleCount,
+}
+
+impl RenderTarget {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            draw_buffer: DrawBuffer::new(vec![0; width * height], width, height),
+            depth_buffer: DepthBuffer::new(vec![1.0; width * height], width, height),
+            sample_colors: vec![],
+            sample_count: SampleCount::X1,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.draw_buffer.buffer_width()
+    }
+    pub fn height(&self) -> usize {
+        self.draw_buffer.buffer_height()
+    }
+    pub fn samples(&self) -> usize {
+        self.sample_count.count()
+    }
+    pub fn sample_count(&self) -> SampleCount {
+        self.sample_count
+    }
+
+    // Switches to this many samples per pixel. The samples start with the current
+    // content, so this can be done between clearing and drawing
+    pub fn set_samples(&mut self, sample_count: SampleCount) {
+        if sample_count == self.sample_count {
+            return;
+        }
+        self.sample_count = sample_count;
+        let samples = sample_count.count();
+
+        self.sample_colors = if samples > 1 {
+            self.draw_buffer
+                .buffer()
+                .iter()
+                .flat_map(|&color| std::iter::repeat_n(color, samples))
+                .collect()
+        } else {
+            vec![]
+        };
+        self.depth_buffer.set_samples(samples);
+    }
+
+    // Fills the color buffer with color and resets the depth buffer to the far plane
+    pub fn clear(&mut self, color: Color) {
+        self.draw_buffer.clear(color);
+        self.depth_buffer.clear();
+        self.sample_colors.fill(color.format_as_u32());
+    }
+
+    // Reallocates the buffers if the size changed (the content is lost),
+    // otherwise does nothing
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if self.width() != width || self.height() != height {
+            self.draw_buffer.resize(width, height);
+            self.depth_buffer.resize(width, height);
+            if self.samples() > 1 {
+                self.sample_colors = vec![0; width * height * self.samples()];
+            }
+        }
+    }
+
+    // Averages the samples of every pixel into draw_buffer (nothing to do without MSAA)
+    pub fn resolve(&mut self) {
+        let samples = self.samples();
+        if samples == 1 {
+            return;
+        }
+
+        for (pixel, colors) in self
+            .draw_buffer
+            .buffer_mut()
+            .iter_mut()
+            .zip(self.sample_colors.chunks(samples))
+        {
+            *pixel = average_colors(colors.iter().copied());
+        }
+    }
+
+    // The buffers the rasterizer writes: the sample colors with MSAA, draw_buffer otherwise
+    pub(crate) fn sample_buffers(&mut self) -> (&mut [u32], &mut [f32]) {
+        let color = if self.samples() > 1 {
+            &mut self.sample_colors[..]
+        } else {
+            self.draw_buffer.buffer_mut()
+        };
+        (color, self.depth_buffer.buffer_mut())
+    }
+}
@@ -1,4 +1,10 @@
-use crate::custom_data_types::{camera::Camera, game_object::GameObject, mesh::Mesh};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::custom_data_types::{
+    camera::Camera, error::Error, game_object::GameObject, gltf::GltfScene, matrices::ModelMatrix,
+    mesh::Mesh,
+};
 
 pub struct Scene {
     pub meshes: Vec<Mesh>,
@@ -14,4 +20,56 @@ impl Scene {
             camera,
         }
     }
+
+    // Adds the meshes, and an object for each of them, all placed with model_matrix.
+    // The objects of an exported scene (see Mesh::load_objects) are already where they
+    // belong relative to each other, so they share the transform of the whole file.
+    // Returns the indices of the new objects
+    pub fn instantiate(&mut self, meshes: Vec<Mesh>, model_matrix: ModelMatrix) -> Range<usize> {
+        let first_object = self.objects.len();
+
+        for mesh in meshes {
+            self.objects
+                .push(GameObject::new(self.meshes.len(), model_matrix));
+            self.meshes.push(mesh);
+        }
+
+        first_object..self.objects.len()
+    }
+
+    // The first object drawn with the mesh called name
+    pub fn find_object(&mut self, name: &str) -> Option<&mut GameObject> {
+        let meshes = &self.meshes;
+        self.objects.iter_mut().find(|object| {
+            meshes
+                .get(object.object_id)
+                .is_some_and(|mesh| mesh.name == name)
+        })
+    }
+
+    // Adds the meshes of a glTF file, and an object for every node drawing one, placed
+    // with model_matrix applied over the node's transform. Objects have no parent
+    // object, so the node tree is flattened: the node's own transform is the object's
+    // translation, rotation and scale, and those of the nodes above it (which may
+    // shear it) are its parent matrix. The nodes without a mesh are left out. Returns
+    // the indices of the new objects
+    pub fn load_gltf(
+        &mut self,
+        path: impl AsRef<Path>,
+        model_matrix: ModelMatrix,
+    ) -> Result<Range<usize>, Error> {
+        let gltf = GltfScene::load(path)?;
+        let first_mesh = self.meshes.len();
+        let first_object = self.objects.len();
+
+        self.meshes.extend(gltf.meshes);
+        for node in gltf.nodes {
+            let mut node_matrix = ModelMatrix::from_matrix(&node.local);
+            node_matrix.update_parent(Some(model_matrix.get_model_matrix() * node.parent));
+            self.objects
+                .push(GameObject::new(first_mesh + node.mesh, node_matrix));
+        }
+
+        Ok(first_object..self.objects.len())
+    }
 }
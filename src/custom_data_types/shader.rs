@@ -0,0 +1,164 @@
+use super::color::Color;
+use super::mat3::Mat3;
+use super::material::{AlphaMode, Material};
+use super::matrices::Matrix4x4;
+use super::texture::Texture;
+use super::varying::Varying;
+use super::vec2::Vec2;
+use super::vec3::Vec3;
+use super::vec4::Vec4;
+
+// Values that stay the same for every vertex / fragment of one object
+pub struct Uniforms {
+    pub model_matrix: Matrix4x4,
+    pub view_projection: Matrix4x4,
+    // Used to bring the normals from local to world space
+    pub normal_matrix: Mat3,
+    pub camera_position: Vec3,
+}
+
+// Everything the vertex stage gets for one vertex, in local space
+pub struct VertexInput<'a> {
+    pub position: Vec3,
+    // The face normal is used if the mesh doesn't have normals
+    pub normal: Vec3,
+    // (0.0, 0.0) if the mesh doesn't have UVs
+    pub uv: Vec2,
+    // RGBA, white if the mesh doesn't have vertex colors
+    pub color: Vec4,
+    pub uniforms: &'a Uniforms,
+}
+
+// Everything the fragment stage gets for one pixel. The varyings are already
+// interpolated (perspective-correct) across the triangle
+pub struct FragmentInput<'a, V> {
+    pub varyings: V,
+    // How much the varyings change from this pixel to the next one on the
+    // x and y axis (the same for the whole 2x2 quad the pixel is in)
+    pub ddx: V,
+    pub ddy: V,
+    pub x: usize,
+    pub y: usize,
+    pub depth: f32,
+    // Index of the triangle in the mesh
+    pub primitive_id: usize,
+    pub material: &'a Material,
+    pub uniforms: &'a Uniforms,
+}
+
+pub trait VertexShader {
+    type Varyings: Varying;
+
+    // Returns the clip space position of the vertex and its varyings
+    fn shade(&self, v: &VertexInput) -> (Vec4, Self::Varyings);
+}
+
+pub trait FragmentShader<V> {
+    // Returns the color of the pixel, or None to discard it
+    // (in which case the depth buffer isn't updated either)
+    fn shade(&self, f: &FragmentInput<V>) -> Option<Color>;
+}
+
+// Transforms the vertices to clip space and passes the world space normal,
+// the UVs, the world space position and the vertex color along
+pub struct DefaultVertexShader;
+
+impl VertexShader for DefaultVertexShader {
+    type Varyings = (Vec3, Vec2, Vec3, Vec4);
+
+    fn shade(&self, v: &VertexInput) -> (Vec4, Self::Varyings) {
+        let world = v.uniforms.model_matrix * Vec4::point(v.position);
+        let normal = v.uniforms.normal_matrix * v.normal;
+
+        (
+            v.uniforms.view_projection * world,
+            (normal.normalize(), v.uv, world.xyz(), v.color),
+        )
+    }
+}
+
+// Blinn-Phong with one directional light and an ambient floor,
+// using the colors and textures of the triangle's material, tinted by the vertex colors
+pub struct DefaultFragmentShader {
+    // Direction towards the light
+    pub light: Vec3,
+    pub ambient: f32,
+    // Fragments of masked materials whose opacity is below this are discarded,
+    // unless the material has its own cutoff
+    pub alpha_cutoff: f32,
+}
+
+impl DefaultFragmentShader {
+    pub fn new() -> Self {
+        Self {
+            // Default light, going in from the camera: To be properly implemented
+            light: Vec3::new(5.0, 1.0, -0.25).normalize(),
+            ambient: 0.15,
+            alpha_cutoff: 0.5,
+        }
+    }
+
+    // Samples an optional material texture, white if there isn't one
+    fn sample(texture: &Option<Texture>, f: &FragmentInput<(Vec3, Vec2, Vec3, Vec4)>) -> Vec4 {
+        let Some(texture) = texture else {
+            return Vec4::new(1.0, 1.0, 1.0, 1.0);
+        };
+
+        let (_, uv, _, _) = f.varyings;
+        let (_, duv_dx, _, _) = f.ddx;
+        let (_, duv_dy, _, _) = f.ddy;
+
+        texture
+            .sample_grad(uv.x, uv.y, (duv_dx.x, duv_dx.y), (duv_dy.x, duv_dy.y))
+            .to_vec4()
+    }
+}
+
+impl Default for DefaultFragmentShader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FragmentShader<(Vec3, Vec2, Vec3, Vec4)> for DefaultFragmentShader {
+    fn shade(&self, f: &FragmentInput<(Vec3, Vec2, Vec3, Vec4)>) -> Option<Color> {
+        let (normal, _, world, vertex_color) = f.varyings;
+        let material = f.material;
+
+        let diffuse = material.diffuse * Self::sample(&material.diffuse_texture, f) * vertex_color;
+        let alpha = match material.alpha_mode {
+            AlphaMode::Opaque => 1.0,
+            AlphaMode::Mask => {
+                let alpha = material.opacity * diffuse.w;
+                if alpha < material.alpha_cutoff.unwrap_or(self.alpha_cutoff) {
+                    return None;
+                }
+                alpha
+            }
+        };
+
+        let ambient = material.ambient * Self::sample(&material.ambient_texture, f) * vertex_color;
+        let specular = material.specular * Self::sample(&material.specular_texture, f);
+        let emissive = material.emissive * Self::sample(&material.emissive_texture, f);
+
+        // Interpolating shortens the normal
+        let normal = normal.normalize();
+
+        let diffuse_intensity = normal.dot(self.light).max(self.ambient);
+
+        let mut specular_intensity = 0.0;
+        if material.shininess > 0.0 && normal.dot(self.light) > 0.0 {
+            let view = (f.uniforms.camera_position - world).normalize();
+            let half = (self.light + view).normalize();
+            specular_intensity = normal.dot(half).max(0.0).powf(material.shininess);
+        }
+
+        let mut color = diffuse * diffuse_intensity
+            + ambient * self.ambient
+            + specular * specular_intensity
+            + emissive;
+        color.w = alpha;
+
+        Some(Color::from_vec4(color))
+    }
+}
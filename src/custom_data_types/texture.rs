@@ -0,0 +1,284 @@
+use super::color::Color;
+use super::image::Image;
+
+// Nearest and Bilinear read from the closest mip level (if there are mipmaps),
+// Trilinear blends the two closest ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    Trilinear,
+}
+
+// What happens to UVs outside of [0.0, 1.0]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressMode {
+    Wrap,
+    Clamp,
+    Mirror,
+}
+
+// One image of the mip chain
+#[derive(Clone)]
+struct MipLevel {
+    texels: Vec<Color>,
+    width: usize,
+    height: usize,
+}
+
+impl MipLevel {
+    fn get(&self, x: usize, y: usize) -> Color {
+        self.texels[y * self.width + x]
+    }
+
+    // Halves the size (rounding down, but never below 1), every texel being the
+    // average of the 2x2 texels it covers. For odd sizes the last row/column is
+    // reused instead of going out of the image
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (2 * x).min(self.width - 1);
+                let y0 = (2 * y).min(self.height - 1);
+                let x1 = (2 * x + 1).min(self.width - 1);
+                let y1 = (2 * y + 1).min(self.height - 1);
+
+                let sum = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+                    .map(|(x, y)| Texture::to_rgba(self.get(x, y)))
+                    .into_iter()
+                    .fold([0.0; 4], |acc, rgba| [0, 1, 2, 3].map(|i| acc[i] + rgba[i]));
+
+                texels.push(Texture::to_color(sum.map(|c| c * 0.25)));
+            }
+        }
+
+        MipLevel {
+            texels,
+            width,
+            height,
+        }
+    }
+}
+
+// RGBA texels, stored row by row starting with the top row
+// (so v = 1.0 is the first row, like in the images the UVs were made for)
+#[derive(Clone)]
+pub struct Texture {
+    // levels[0] is the full size image, the others are only there after generate_mipmaps
+    levels: Vec<MipLevel>,
+    pub filter: Filter,
+    pub address_mode: AddressMode,
+    // Maximum number of samples taken along the longest axis of the pixel's footprint.
+    // 1 turns anisotropic filtering off
+    pub max_anisotropy: u32,
+}
+
+impl Texture {
+    pub fn new(texels: Vec<Color>, width: usize, height: usize) -> Self {
+        assert_eq!(
+            texels.len(),
+            width * height,
+            "Texture size doesn't match the number of texels"
+        );
+
+        Self {
+            levels: vec![MipLevel {
+                texels,
+                width,
+                height,
+            }],
+            filter: Filter::Bilinear,
+            address_mode: AddressMode::Wrap,
+            max_anisotropy: 1,
+        }
+    }
+
+    pub fn from_image(image: Image) -> Self {
+        let (width, height) = (image.width(), image.height());
+        Self::new(image.into_pixels(), width, height)
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+    pub fn texels(&self) -> &Vec<Color> {
+        &self.levels[0].texels
+    }
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.levels[0].get(x, y)
+    }
+
+    // Builds the whole mip chain, down to 1x1 (replacing the old one, if any)
+    pub fn generate_mipmaps(&mut self) {
+        self.levels.truncate(1);
+
+        if self.texels().is_empty() {
+            return;
+        }
+
+        loop {
+            let last = &self.levels[self.levels.len() - 1];
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+
+            let next = last.downsample();
+            self.levels.push(next);
+        }
+    }
+
+    // Brings a texel coordinate (that might be outside the texture)
+    // back inside, according to the address mode
+    fn address(&self, coord: i64, size: usize) -> usize {
+        let size = size as i64;
+
+        let coord = match self.address_mode {
+            AddressMode::Wrap => coord.rem_euclid(size),
+            AddressMode::Clamp => coord.clamp(0, size - 1),
+            AddressMode::Mirror => {
+                let period = coord.rem_euclid(2 * size);
+                if period < size {
+                    period
+                } else {
+                    2 * size - 1 - period
+                }
+            }
+        };
+
+        coord as usize
+    }
+
+    fn texel(&self, level: &MipLevel, x: i64, y: i64) -> [f32; 4] {
+        Self::to_rgba(level.get(self.address(x, level.width), self.address(y, level.height)))
+    }
+
+    // Samples the full size image at (u, v), with no mipmapping
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        if self.texels().is_empty() {
+            return Color::new(255, 255, 255, 255);
+        }
+
+        Self::to_color(self.sample_level(0, u, v))
+    }
+
+    // Samples the texture at (u, v), picking the mip level from the UV derivatives
+    // along the screen x and y axes (how much the UVs change from one pixel to the next)
+    pub fn sample_grad(&self, u: f32, v: f32, duv_dx: (f32, f32), duv_dy: (f32, f32)) -> Color {
+        if self.texels().is_empty() {
+            return Color::new(255, 255, 255, 255);
+        }
+
+        // The derivatives in texels of the full size image
+        let (w, h) = (self.width() as f32, self.height() as f32);
+        let dx = (duv_dx.0 * w, duv_dx.1 * h);
+        let dy = (duv_dy.0 * w, duv_dy.1 * h);
+        let len_x = (dx.0 * dx.0 + dx.1 * dx.1).sqrt();
+        let len_y = (dy.0 * dy.0 + dy.1 * dy.1).sqrt();
+
+        let (major, minor, major_axis) = if len_x >= len_y {
+            (len_x, len_y, duv_dx)
+        } else {
+            (len_y, len_x, duv_dy)
+        };
+
+        // Anisotropic: several samples along the long axis of the footprint,
+        // each one with the LOD of the short axis
+        let probes = if self.max_anisotropy > 1 && minor > 0.0 {
+            ((major / minor).ceil() as u32).clamp(1, self.max_anisotropy)
+        } else {
+            1
+        };
+
+        let lod = (major / probes as f32).max(1e-8).log2();
+
+        let mut sum = [0.0; 4];
+        for i in 0..probes {
+            // Spread evenly along the axis, centered on (u, v)
+            let t = (i as f32 + 0.5) / probes as f32 - 0.5;
+            let rgba = self.sample_lod(u + major_axis.0 * t, v + major_axis.1 * t, lod);
+            sum = [0, 1, 2, 3].map(|c| sum[c] + rgba[c]);
+        }
+
+        Self::to_color(sum.map(|c| c / probes as f32))
+    }
+
+    fn sample_lod(&self, u: f32, v: f32, lod: f32) -> [f32; 4] {
+        let max_level = (self.levels.len() - 1) as f32;
+        let lod = lod.clamp(0.0, max_level);
+
+        match self.filter {
+            Filter::Trilinear => {
+                let level0 = lod.floor();
+                let level1 = (level0 + 1.0).min(max_level);
+                let t = lod - level0;
+
+                let a = self.sample_level(level0 as usize, u, v);
+                let b = self.sample_level(level1 as usize, u, v);
+
+                Self::lerp_rgba(a, b, t)
+            }
+            _ => self.sample_level(lod.round() as usize, u, v),
+        }
+    }
+
+    // Nearest or bilinear sampling of one mip level
+    fn sample_level(&self, level: usize, u: f32, v: f32) -> [f32; 4] {
+        let level = &self.levels[level];
+
+        // From UV space (origin in the bottom left) to texel space (origin in the top left)
+        let x = u * level.width as f32;
+        let y = (1.0 - v) * level.height as f32;
+
+        match self.filter {
+            Filter::Nearest => self.texel(level, x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear | Filter::Trilinear => {
+                // Texel centers are at .5, so the 4 closest ones are found from x - 0.5
+                let x = x - 0.5;
+                let y = y - 0.5;
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let tx = x - x0;
+                let ty = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top =
+                    Self::lerp_rgba(self.texel(level, x0, y0), self.texel(level, x0 + 1, y0), tx);
+                let bottom = Self::lerp_rgba(
+                    self.texel(level, x0, y0 + 1),
+                    self.texel(level, x0 + 1, y0 + 1),
+                    tx,
+                );
+
+                Self::lerp_rgba(top, bottom, ty)
+            }
+        }
+    }
+
+    fn lerp_rgba(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+        [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+    }
+
+    fn to_rgba(color: Color) -> [f32; 4] {
+        [
+            color.r() as f32,
+            color.g() as f32,
+            color.b() as f32,
+            color.a() as f32,
+        ]
+    }
+
+    fn to_color(rgba: [f32; 4]) -> Color {
+        let [r, g, b, a] = rgba.map(|c| c.round().clamp(0.0, 255.0) as u8);
+        Color::new(r, g, b, a)
+    }
+}
@@ -0,0 +1,99 @@
+use super::vec2::Vec2;
+use super::vec3::Vec3;
+use super::vec4::Vec4;
+
+// Anything that is stored per vertex and has to be interpolated across a
+// triangle (normals, UVs, colors...). Only scaling and adding are needed,
+// everything else (lerp, barycentric blend) is built on top of them
+pub trait Varying: Copy {
+    fn scale(self, t: f32) -> Self;
+    fn add(self, rhs: Self) -> Self;
+
+    fn lerp(self, rhs: Self, t: f32) -> Self {
+        self.scale(1.0 - t).add(rhs.scale(t))
+    }
+
+    fn barycentric(a: Self, b: Self, c: Self, weight_a: f32, weight_b: f32, weight_c: f32) -> Self {
+        a.scale(weight_a)
+            .add(b.scale(weight_b))
+            .add(c.scale(weight_c))
+    }
+}
+
+impl Varying for () {
+    fn scale(self, _t: f32) -> Self {}
+    fn add(self, _rhs: Self) -> Self {}
+}
+
+impl Varying for f32 {
+    fn scale(self, t: f32) -> Self {
+        self * t
+    }
+    fn add(self, rhs: Self) -> Self {
+        self + rhs
+    }
+}
+
+impl Varying for Vec2 {
+    fn scale(self, t: f32) -> Self {
+        self * t
+    }
+    fn add(self, rhs: Self) -> Self {
+        self + rhs
+    }
+}
+
+impl Varying for Vec3 {
+    fn scale(self, t: f32) -> Self {
+        self * t
+    }
+    fn add(self, rhs: Self) -> Self {
+        self + rhs
+    }
+}
+
+impl Varying for Vec4 {
+    fn scale(self, t: f32) -> Self {
+        self * t
+    }
+    fn add(self, rhs: Self) -> Self {
+        self + rhs
+    }
+}
+
+impl<A: Varying, B: Varying> Varying for (A, B) {
+    fn scale(self, t: f32) -> Self {
+        (self.0.scale(t), self.1.scale(t))
+    }
+    fn add(self, rhs: Self) -> Self {
+        (self.0.add(rhs.0), self.1.add(rhs.1))
+    }
+}
+
+impl<A: Varying, B: Varying, C: Varying> Varying for (A, B, C) {
+    fn scale(self, t: f32) -> Self {
+        (self.0.scale(t), self.1.scale(t), self.2.scale(t))
+    }
+    fn add(self, rhs: Self) -> Self {
+        (self.0.add(rhs.0), self.1.add(rhs.1), self.2.add(rhs.2))
+    }
+}
+
+impl<A: Varying, B: Varying, C: Varying, D: Varying> Varying for (A, B, C, D) {
+    fn scale(self, t: f32) -> Self {
+        (
+            self.0.scale(t),
+            self.1.scale(t),
+            self.2.scale(t),
+            self.3.scale(t),
+        )
+    }
+    fn add(self, rhs: Self) -> Self {
+        (
+            self.0.add(rhs.0),
+            self.1.add(rhs.1),
+            self.2.add(rhs.2),
+            self.3.add(rhs.3),
+        )
+    }
+}
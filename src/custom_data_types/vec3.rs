@@ -0,0 +1,198 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::vec2::Vec2;
+use super::vec4::Vec4;
+
+// A position, direction or normal in 3D. Unlike Vec4 there is no w to get in the way
+// of dot and cross, extend it to a Vec4 (with Vec4::point or Vec4::direction)
+// to go through a Matrix4x4
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    // The same value in every component
+    pub fn splat(value: f32) -> Self {
+        Self::new(value, value, value)
+    }
+
+    pub fn dot(self, rhs: Vec3) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn cross(self, rhs: Vec3) -> Self {
+        Self {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+
+    pub fn magnitude_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn magnitude(self) -> f32 {
+        self.magnitude_squared().sqrt()
+    }
+
+    pub fn normalize(self) -> Self {
+        self / self.magnitude()
+    }
+
+    pub fn lerp(self, rhs: Vec3, t: f32) -> Self {
+        self + (rhs - self) * t
+    }
+
+    // The direction of a ray leaving a surface with this normal (normalized) after
+    // hitting it going in self
+    pub fn reflect(self, normal: Vec3) -> Self {
+        self - normal * (2.0 * self.dot(normal))
+    }
+
+    // The direction of a ray going through a surface with this normal (normalized,
+    // against self) with eta the ratio of the refractive indices (from / to).
+    // None on total internal reflection. self must be normalized too
+    pub fn refract(self, normal: Vec3, eta: f32) -> Option<Self> {
+        let cos_in = -self.dot(normal);
+        let k = 1.0 - eta * eta * (1.0 - cos_in * cos_in);
+        if k < 0.0 {
+            return None;
+        }
+
+        Some(self * eta + normal * (eta * cos_in - k.sqrt()))
+    }
+
+    pub fn min(self, rhs: Vec3) -> Self {
+        Self::new(self.x.min(rhs.x), self.y.min(rhs.y), self.z.min(rhs.z))
+    }
+
+    pub fn max(self, rhs: Vec3) -> Self {
+        Self::new(self.x.max(rhs.x), self.y.max(rhs.y), self.z.max(rhs.z))
+    }
+
+    pub fn abs(self) -> Self {
+        Self::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    // Every component within epsilon of rhs
+    pub fn approx_eq(self, rhs: Vec3, epsilon: f32) -> bool {
+        let difference = (self - rhs).abs();
+        difference.x <= epsilon && difference.y <= epsilon && difference.z <= epsilon
+    }
+
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub fn xy(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn xz(self) -> Vec2 {
+        Vec2::new(self.x, self.z)
+    }
+
+    pub fn yz(self) -> Vec2 {
+        Vec2::new(self.y, self.z)
+    }
+
+    pub fn zyx(self) -> Self {
+        Self::new(self.z, self.y, self.x)
+    }
+
+    pub fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Mul<Vec3> for f32 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        rhs * self
+    }
+}
+
+// Component-wise
+impl Mul for Vec3 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
+    }
+}
+
+impl Div<f32> for Vec3 {
+    type Output = Self;
+
+    fn div(self, rhs: f32) -> Self {
+        let inv = 1.0 / rhs;
+        Self::new(self.x * inv, self.y * inv, self.z * inv)
+    }
+}
+
+// Component-wise
+impl Div for Vec3 {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        Self::new(self.x / rhs.x, self.y / rhs.y, self.z / rhs.z)
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl fmt::Display for Vec3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Vec3({}, {}, {})", self.x, self.y, self.z)
+    }
+}
@@ -1,7 +1,13 @@
 use std::fmt;
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
-#[derive(Clone, Copy, Debug)]
+use super::vec2::Vec2;
+use super::vec3::Vec3;
+
+// A homogeneous coordinate: a point has w = 1 and is moved by translations, a direction
+// has w = 0 and isn't. Clip space positions (any w) are Vec4s too. For plain 3D math
+// (normals, cross products...) use Vec3, where there is no w to get wrong
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec4 {
     pub x: f32,
     pub y: f32,
@@ -14,17 +20,17 @@ impl Vec4 {
         Self { x, y, z, w }
     }
 
-    pub fn dot(self, rhs: Vec4) -> f32 {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    pub fn point(position: Vec3) -> Self {
+        position.extend(1.0)
     }
 
-    pub fn cross(self, rhs: Vec4) -> Self {
-        Self {
-            x: self.y * rhs.z - self.z * rhs.y,
-            y: self.z * rhs.x - self.x * rhs.z,
-            z: self.x * rhs.y - self.y * rhs.x,
-            w: 0.0,
-        }
+    pub fn direction(direction: Vec3) -> Self {
+        direction.extend(0.0)
+    }
+
+    // All four components, w included (used for the clip planes)
+    pub fn dot(self, rhs: Vec4) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
     }
 
     pub fn magnitude_squared(self) -> f32 {
@@ -38,6 +44,64 @@ impl Vec4 {
     pub fn normalize(self) -> Self {
         self / self.magnitude()
     }
+
+    pub fn lerp(self, rhs: Vec4, t: f32) -> Self {
+        self + (rhs - self) * t
+    }
+
+    pub fn min(self, rhs: Vec4) -> Self {
+        Self::new(
+            self.x.min(rhs.x),
+            self.y.min(rhs.y),
+            self.z.min(rhs.z),
+            self.w.min(rhs.w),
+        )
+    }
+
+    pub fn max(self, rhs: Vec4) -> Self {
+        Self::new(
+            self.x.max(rhs.x),
+            self.y.max(rhs.y),
+            self.z.max(rhs.z),
+            self.w.max(rhs.w),
+        )
+    }
+
+    pub fn abs(self) -> Self {
+        Self::new(self.x.abs(), self.y.abs(), self.z.abs(), self.w.abs())
+    }
+
+    // Every component within epsilon of rhs
+    pub fn approx_eq(self, rhs: Vec4, epsilon: f32) -> bool {
+        let difference = (self - rhs).abs();
+        difference.x <= epsilon
+            && difference.y <= epsilon
+            && difference.z <= epsilon
+            && difference.w <= epsilon
+    }
+
+    // The point in 3D after the perspective divide
+    pub fn project(self) -> Vec3 {
+        self.xyz() / self.w
+    }
+
+    pub fn xyz(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn xy(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+}
+
+impl From<[f32; 4]> for Vec4 {
+    fn from([x, y, z, w]: [f32; 4]) -> Self {
+        Self::new(x, y, z, w)
+    }
 }
 
 impl Mul<f32> for Vec4 {
@@ -53,14 +117,25 @@ impl Mul<f32> for Vec4 {
     }
 }
 
+// Component-wise
+impl Mul for Vec4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            x: self.x * rhs.x,
+            y: self.y * rhs.y,
+            z: self.z * rhs.z,
+            w: self.w * rhs.w,
+        }
+    }
+}
+
 impl Div<f32> for Vec4 {
     type Output = Vec4;
 
+    // Like f32, dividing by 0.0 gives infinities (or NaN for 0.0 / 0.0) instead of panicking
     fn div(self, rhs: f32) -> Vec4 {
-        if rhs == 0.0 {
-            panic!("Division by 0 inside Vec4 Div Trait\n");
-        }
-
         let inv = 1.0 / rhs;
 
         Self {
@@ -0,0 +1,19 @@
+// Glue between a RenderTarget and a minifb window (only built with the "window" feature)
+use minifb::Window;
+
+use super::render_target::RenderTarget;
+
+// Resizes the render target to the window's current size
+pub fn sync_size(target: &mut RenderTarget, window: &Window) {
+    let (width, height) = window.get_size();
+    target.resize(width, height);
+}
+
+// Shows the color buffer in the window
+pub fn present(target: &RenderTarget, window: &mut Window) -> minifb::Result<()> {
+    window.update_with_buffer(
+        target.draw_buffer.buffer(),
+        target.draw_buffer.buffer_width(),
+        target.draw_buffer.buffer_height(),
+    )
+}
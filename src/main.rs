@@ -1,42 +1,69 @@
 // custom modules
-mod custom_data_types;
-
-use custom_data_types::draw_buffer::DrawBuffer;
-use custom_data_types::game_object::GameObject;
-use custom_data_types::matrices::ModelMatrix;
-use custom_data_types::mesh::Mesh;
-use custom_data_types::vec4::Vec4;
+use haywire_rasterizer::custom_data_types::camera::{Camera, Projection};
+use haywire_rasterizer::custom_data_types::camera_controller::{
+    CameraController, ControllerInput, FlyController, OrbitController,
+};
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::frame_timer::{FixedTimestep, FrameTimer};
+use haywire_rasterizer::custom_data_types::game_object::GameObject;
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::mesh::Mesh;
+use haywire_rasterizer::custom_data_types::multisample::{AntiAliasing, SampleCount};
+use haywire_rasterizer::custom_data_types::rasterizer::Rasterizer;
+use haywire_rasterizer::custom_data_types::render_target::RenderTarget;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::shader::{DefaultFragmentShader, DefaultVertexShader};
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::window;
 
 // external dependencies
-use minifb::{Key, MouseMode, Window, WindowOptions};
-use rand::Rng;
-
-use crate::custom_data_types::camera::Camera;
-use crate::custom_data_types::color::Color;
-use crate::custom_data_types::depth_buffer::DepthBuffer;
-use crate::custom_data_types::rasterizer::Rasterizer;
-use crate::custom_data_types::scene::Scene;
-
-fn draw(draw_buffer: &mut DrawBuffer, window: &mut Window) -> () {
-    window
-        .update_with_buffer(
-            draw_buffer.buffer(),
-            draw_buffer.buffer_width(),
-            draw_buffer.buffer_height(),
-        )
-        .unwrap();
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+
+// standard library
+use std::f32::consts::FRAC_PI_2;
+use std::num::NonZeroUsize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Roughly how far the objects are from the camera. Switching the projection
+// keeps what is at this distance the same size
+const FOCUS_DISTANCE: f32 = 10.0;
+
+// Writes the color buffer as PNG and the depth buffer both as a false color PNG
+// and as a PFM to ./screenshots, the names being the time in milliseconds
+fn save_screenshot(target: &RenderTarget) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let directory = std::path::Path::new("screenshots");
+
+    if let Err(err) = std::fs::create_dir_all(directory) {
+        eprintln!("Failed to create {}: {}", directory.display(), err);
+        return;
+    }
+
+    let color_path = directory.join(format!("{}.png", timestamp));
+    let depth_path = directory.join(format!("{}_depth.png", timestamp));
+    let pfm_path = directory.join(format!("{}_depth.pfm", timestamp));
+
+    let result = target
+        .draw_buffer
+        .save(&color_path)
+        .and_then(|_| target.depth_buffer.to_false_color_image().save(&depth_path))
+        .and_then(|_| Ok(target.depth_buffer.save_pfm(&pfm_path)?));
+
+    match result {
+        Ok(()) => println!("Saved the screenshot {}", color_path.display()),
+        Err(err) => eprintln!("Failed to save the screenshot: {}", err),
+    }
 }
 
 fn main() {
-    let mut rasterizer = Rasterizer::new(
-        DrawBuffer::new(vec![0; 1280 * 720], 1280, 720),
-        DepthBuffer::new(vec![1.0; 1280 * 720], 1280, 720),
-    );
+    let mut rasterizer = Rasterizer::new(RenderTarget::new(1280, 720));
     let camera = Camera::new(
         ModelMatrix::new(
-            Vec4::new(0.0, 0.0, 0.0, 1.0),
-            Vec4::new(0.0, 0.0, 0.0, 1.0),
-            Vec4::new(1.0, 1.0, 1.0, 1.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
         ),
         90.0f32.to_radians(),
         16.0 / 9.0,
@@ -46,8 +73,8 @@ fn main() {
 
     let mut window = Window::new(
         "Haywire Rasterizer",
-        rasterizer.draw_buffer.buffer_width(),
-        rasterizer.draw_buffer.buffer_height(),
+        rasterizer.target.width(),
+        rasterizer.target.height(),
         WindowOptions {
             resize: true,
             ..WindowOptions::default()
@@ -57,118 +84,213 @@ fn main() {
 
     window.set_target_fps(60);
 
-    let mut meshes: Vec<Mesh> = vec![];
-    meshes.push(Mesh::new("./assets/dragon.obj", ".obj"));
-    meshes.push(Mesh::new("./assets/suzzane.obj", ".obj"));
-    meshes.push(Mesh::new("./assets/cube.obj", ".obj"));
-    meshes.push(Mesh::new("./assets/bunny.obj", ".obj"));
-    let mut obj: Vec<GameObject> = vec![];
-    obj.push(GameObject::new(
-        0,
-        ModelMatrix::new(
-            Vec4::new(0.0, 0.0, -10.0, 1.0),
-            Vec4::new(0.0, 90.0f32.to_radians(), 0.0, 1.0),
-            Vec4::new(15.0, 15.0, 15.0, 1.0),
+    // A mesh that fails to load is replaced by an empty one, so the objects
+    // still point to the right meshes and the rest of the scene is drawn
+    let meshes: Vec<Mesh> = [
+        "./assets/dragon.obj",
+        "./assets/suzzane.obj",
+        "./assets/cube.obj",
+        "./assets/bunny.obj",
+    ]
+    .iter()
+    .map(|path| {
+        Mesh::new(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load {}: {}", path, err);
+            Mesh::default()
+        })
+    })
+    .collect();
+    let obj: Vec<GameObject> = vec![
+        GameObject::new(
+            0,
+            ModelMatrix::new(
+                Vec3::new(0.0, 0.0, -10.0),
+                Vec3::new(0.0, 90.0f32.to_radians(), 0.0),
+                Vec3::new(15.0, 15.0, 15.0),
+            ),
         ),
-    ));
-
-    obj.push(GameObject::new(
-        1,
-        ModelMatrix::new(
-            Vec4::new(10.0, 0.0, -10.0, 1.0),
-            Vec4::new(0.0, 0.0, 0.0, 1.0),
-            Vec4::new(1.0, 1.0, 1.0, 1.0),
+        GameObject::new(
+            1,
+            ModelMatrix::new(
+                Vec3::new(10.0, 0.0, -10.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 1.0),
+            ),
         ),
-    ));
-
-    obj.push(GameObject::new(
-        2,
-        ModelMatrix::new(
-            Vec4::new(10.0, 0.0, -5.0, 1.0),
-            Vec4::new(0.0, 0.0, 0.0, 1.0),
-            Vec4::new(1.0, 1.0, 1.0, 1.0),
+        GameObject::new(
+            2,
+            ModelMatrix::new(
+                Vec3::new(10.0, 0.0, -5.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 1.0),
+            ),
         ),
-    ));
-
-    obj.push(GameObject::new(
-        3,
-        ModelMatrix::new(
-            Vec4::new(20.0, 0.0, -10.0, 1.0),
-            Vec4::new(0.0, 0.0, 0.0, 1.0),
-            Vec4::new(30.0, 30.0, 30.0, 1.0),
+        GameObject::new(
+            3,
+            ModelMatrix::new(
+                Vec3::new(20.0, 0.0, -10.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(30.0, 30.0, 30.0),
+            ),
         ),
-    ));
+    ];
 
     let mut scene = Scene::new(meshes, obj, camera);
+    let perspective = scene.camera.projection;
+
+    // Looking at the dragon
+    let dragon = Vec3::new(0.0, 0.0, -10.0);
+    scene
+        .camera
+        .look_at(Vec3::zero(), dragon, Vec3::new(0.0, 1.0, 0.0));
 
-    let mut pos = Vec4::new(0.0, 0.0, 0.0, 1.0);
-    let mut angle = Vec4::new(0.0, 0.0, 0.0, 1.0);
+    // C switches between them: flying around (WASD, space, ctrl and dragging), and
+    // orbiting the dragon (dragging, right dragging to pan and the wheel to zoom)
+    let mut controllers: Vec<Box<dyn CameraController>> = vec![
+        Box::new(FlyController::new(6.0, 0.005)),
+        Box::new(OrbitController::new(dragon, FOCUS_DISTANCE, 0.005)),
+    ];
+    let mut active = 0;
+    controllers[active].sync(&scene.camera);
     let mut last_mouse_pos = (0.0f32, 0.0f32);
-    let mut random_colors: Vec<Color> = vec![];
 
-    for _i in 0..100 {
-        let r = rand::thread_rng().gen_range(50..255);
-        let g = rand::thread_rng().gen_range(50..255);
-        let b = rand::thread_rng().gen_range(50..255);
+    let vertex_shader = DefaultVertexShader;
+    let fragment_shader = DefaultFragmentShader::new();
 
-        let color = Color::new(r, g, b, 255);
-        random_colors.push(color);
-    }
+    let mut timer = FrameTimer::default();
+    // F switches the camera updates between once per frame and a fixed 120 per second
+    let mut fixed_timestep: Option<FixedTimestep> = None;
+    let mut input = ControllerInput::default();
+    let mut title_time = Duration::ZERO;
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        rasterizer.draw_buffer.handle_clear(&window);
-        rasterizer.depth_buffer.handle_clear(&window);
-
-        //y_angle += 0.02;
-        //x_angle += 0.00;
+        let delta_time = timer.tick();
+        window::sync_size(&mut rasterizer.target, &window);
+        rasterizer.target.clear(Color::new(0, 0, 0, 255));
 
         let current_mouse_pos = window
             .get_mouse_pos(MouseMode::Pass)
             .unwrap_or(last_mouse_pos);
 
-        if window.get_mouse_down(minifb::MouseButton::Left) {
-            let sensitivity = 0.005;
-            let dy = (current_mouse_pos.0 - last_mouse_pos.0) * sensitivity;
-            let dx = (current_mouse_pos.1 - last_mouse_pos.1) * sensitivity;
+        let axis = |positive: Key, negative: Key| {
+            if window.is_key_down(positive) {
+                1.0
+            } else if window.is_key_down(negative) {
+                -1.0
+            } else {
+                0.0
+            }
+        };
 
-            angle.x += dx;
-            angle.y -= dy;
+        // The mouse movement and the wheel add up until an update uses them
+        input.mouse_delta.0 += current_mouse_pos.0 - last_mouse_pos.0;
+        input.mouse_delta.1 += current_mouse_pos.1 - last_mouse_pos.1;
+        input.scroll += window.get_scroll_wheel().map_or(0.0, |(_, y)| y.signum());
+        input.rotating = window.get_mouse_down(MouseButton::Left);
+        input.panning = window.get_mouse_down(MouseButton::Right);
+        input.movement = Vec3::new(
+            axis(Key::D, Key::A),
+            axis(Key::Space, Key::LeftCtrl),
+            axis(Key::W, Key::S),
+        );
+
+        match &mut fixed_timestep {
+            Some(timestep) => {
+                for _ in 0..timestep.advance(timer.delta()) {
+                    let step = timestep.step_seconds();
+                    controllers[active].update(&mut scene.camera, &input, step);
+                    input.clear_events();
+                }
+            }
+            None => {
+                controllers[active].update(&mut scene.camera, &input, delta_time);
+                input.clear_events();
+            }
         }
 
-        let yaw = angle.y;
-        let forward_x = -yaw.sin();
-        let forward_z = -yaw.cos();
-        let right_x = forward_z;
-        let right_z = -forward_x;
-        let speed = 0.1;
-
-        if window.is_key_down(Key::A) {
-            pos.x += right_x * speed;
-            pos.z += right_z * speed;
-        } else if window.is_key_down(Key::D) {
-            pos.x -= right_x * speed;
-            pos.z -= right_z * speed;
+        if let Err(err) = rasterizer.draw_scene(&scene, &vertex_shader, &fragment_shader) {
+            eprintln!("Failed to draw the scene: {}", err);
+            break;
         }
+        window::present(&rasterizer.target, &mut window).unwrap();
 
-        if window.is_key_down(Key::W) {
-            pos.x += forward_x * speed;
-            pos.z += forward_z * speed;
-        } else if window.is_key_down(Key::S) {
-            pos.x -= forward_x * speed;
-            pos.z -= forward_z * speed;
+        // M cycles through the anti-aliasing modes
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            rasterizer.anti_aliasing = match rasterizer.anti_aliasing {
+                AntiAliasing::Off => AntiAliasing::Msaa(SampleCount::X2),
+                AntiAliasing::Msaa(SampleCount::X1 | SampleCount::X2) => {
+                    AntiAliasing::Msaa(SampleCount::X4)
+                }
+                AntiAliasing::Msaa(SampleCount::X4) => AntiAliasing::Msaa(SampleCount::X8),
+                AntiAliasing::Msaa(SampleCount::X8) => {
+                    AntiAliasing::Ssaa(NonZeroUsize::new(2).unwrap())
+                }
+                AntiAliasing::Ssaa(_) => AntiAliasing::Off,
+            };
+            println!("Anti-aliasing: {:?}", rasterizer.anti_aliasing);
         }
 
-        if window.is_key_down(Key::Space) {
-            pos.y += speed;
-        } else if window.is_key_down(Key::LeftCtrl) {
-            pos.y -= speed;
+        // P cycles through the perspective, orthographic and oblique (cabinet) projections
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            let height = scene.camera.projection.view_height(FOCUS_DISTANCE);
+            scene.camera.projection = match scene.camera.projection {
+                Projection::Perspective { .. } => Projection::Orthographic { height },
+                Projection::Orthographic { .. } => Projection::Oblique {
+                    height,
+                    angle: 45.0f32.to_radians(),
+                    depth_scale: 0.5,
+                    focus: FOCUS_DISTANCE,
+                },
+                Projection::Oblique { .. } => perspective,
+            };
+            println!("Projection: {:?}", scene.camera.projection);
         }
 
-        scene.camera.model.update_translate(pos);
-        scene.camera.model.update_angle(angle);
+        // 1, 3 and 7 look to the front, from the right side and down from the top
+        // (the numpad keys Blender uses), which is mostly useful with an orthographic projection
+        let view = if window.is_key_pressed(Key::Key1, KeyRepeat::No) {
+            Some(Vec3::zero())
+        } else if window.is_key_pressed(Key::Key3, KeyRepeat::No) {
+            Some(Vec3::new(0.0, FRAC_PI_2, 0.0))
+        } else if window.is_key_pressed(Key::Key7, KeyRepeat::No) {
+            Some(Vec3::new(-FRAC_PI_2, 0.0, 0.0))
+        } else {
+            None
+        };
+        if let Some(angle) = view {
+            scene.camera.model.update_angle(angle);
+            controllers[active].sync(&scene.camera);
+        }
 
-        rasterizer.draw_scene(&scene, &random_colors);
-        draw(&mut rasterizer.draw_buffer, &mut window);
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            active = (active + 1) % controllers.len();
+            controllers[active].sync(&scene.camera);
+            println!("Camera controller: {}", controllers[active].name());
+        }
+
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            fixed_timestep = match fixed_timestep {
+                Some(_) => None,
+                None => Some(FixedTimestep::new(120.0)),
+            };
+            println!("Fixed timestep: {}", fixed_timestep.is_some());
+        }
+
+        // The frame rate in the title, twice per second
+        title_time += timer.delta();
+        if title_time >= Duration::from_millis(500) {
+            title_time = Duration::ZERO;
+            window.set_title(&format!(
+                "Haywire Rasterizer - {:.0} fps, {:.1} ms (99th percentile {:.1} ms)",
+                timer.fps(),
+                timer.average_frame_time().as_secs_f64() * 1000.0,
+                timer.frame_time_percentile(99.0).as_secs_f64() * 1000.0,
+            ));
+        }
+
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            save_screenshot(&rasterizer.target);
+        }
 
         last_mouse_pos = current_mouse_pos;
     }
@@ -0,0 +1,164 @@
+// Checks of Camera::look_at and of the camera controllers
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::camera_controller::{
+    CameraController, ControllerInput, FlyController, OrbitController,
+};
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+fn camera() -> Camera {
+    Camera::new(
+        ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0)),
+        60.0f32.to_radians(),
+        16.0 / 9.0,
+        0.5,
+        100.0,
+    )
+}
+
+fn assert_vec_close(actual: Vec3, expected: Vec3) {
+    assert!(
+        actual.approx_eq(expected, 1e-4),
+        "{} instead of {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn look_at_faces_the_target() {
+    let mut camera = camera();
+    let eye = Vec3::new(3.0, 2.0, -1.0);
+    let target = Vec3::new(-1.0, 0.5, -6.0);
+    camera.look_at(eye, target, Vec3::new(0.0, 1.0, 0.0));
+
+    assert_vec_close(camera.position(), eye);
+    assert_vec_close(camera.forward(), (target - eye).normalize());
+    // The horizon stays level
+    assert!(camera.right().y.abs() < 1e-5);
+    assert!(camera.up().y > 0.0);
+
+    // In view space the target is straight ahead, down -z
+    let view = camera.get_view_matrix() * Vec4::point(target);
+    assert_vec_close(view.xyz(), Vec3::new(0.0, 0.0, -(target - eye).magnitude()));
+}
+
+#[test]
+fn look_at_straight_up_or_at_itself() {
+    let mut camera = camera();
+    camera.look_at(
+        Vec3::zero(),
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    assert_vec_close(camera.forward(), Vec3::new(0.0, 1.0, 0.0));
+    assert!(camera.right().magnitude().is_finite());
+
+    // Looking at the eye changes nothing
+    let before = camera.forward();
+    camera.look_at(Vec3::splat(1.0), Vec3::splat(1.0), Vec3::new(0.0, 1.0, 0.0));
+    assert_vec_close(camera.forward(), before);
+    assert_vec_close(camera.position(), Vec3::zero());
+}
+
+#[test]
+fn orbit_keeps_the_target_in_view() {
+    let target = Vec3::new(1.0, 0.0, -10.0);
+    let mut camera = camera();
+    let mut orbit = OrbitController::new(target, 10.0, 0.01);
+    orbit.sync(&camera);
+
+    let drag = ControllerInput {
+        mouse_delta: (40.0, -25.0),
+        rotating: true,
+        ..Default::default()
+    };
+    for _ in 0..5 {
+        orbit.update(&mut camera, &drag, 1.0 / 60.0);
+        assert!(((camera.position() - target).magnitude() - 10.0).abs() < 1e-3);
+        assert_vec_close(camera.forward(), (target - camera.position()).normalize());
+    }
+
+    // Zooming in gets closer, but never past the target
+    let zoom = ControllerInput {
+        scroll: 1.0,
+        ..Default::default()
+    };
+    orbit.update(&mut camera, &zoom, 1.0 / 60.0);
+    assert!(((camera.position() - target).magnitude() - 9.0).abs() < 1e-3);
+    for _ in 0..200 {
+        orbit.update(&mut camera, &zoom, 1.0 / 60.0);
+    }
+    assert!(orbit.distance >= orbit.min_distance);
+
+    // Panning moves the camera and the target together
+    let before = camera.position();
+    let pan = ControllerInput {
+        mouse_delta: (10.0, 0.0),
+        panning: true,
+        ..Default::default()
+    };
+    orbit.update(&mut camera, &pan, 1.0 / 60.0);
+    let moved = camera.position() - before;
+    assert_vec_close(orbit.target - target, moved);
+    assert!(moved.dot(camera.right()) < 0.0);
+}
+
+#[test]
+fn fly_moves_on_the_horizontal_plane() {
+    let mut camera = camera();
+    camera.look_at(
+        Vec3::zero(),
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    let mut fly = FlyController::new(0.5, 0.01);
+    fly.sync(&camera);
+
+    // Nothing to do, the camera stays where it is
+    let forward = camera.forward();
+    fly.update(&mut camera, &ControllerInput::default(), 1.0);
+    assert_vec_close(camera.forward(), forward);
+    assert_vec_close(camera.position(), Vec3::zero());
+
+    let walk = ControllerInput {
+        movement: Vec3::new(0.0, 0.0, 1.0),
+        ..Default::default()
+    };
+    fly.update(&mut camera, &walk, 1.0);
+    let step = Vec3::new(-1.0, 0.0, -1.0).normalize() * 0.5;
+    assert_vec_close(camera.position(), step);
+
+    let climb = ControllerInput {
+        movement: Vec3::new(0.0, 1.0, 0.0),
+        ..Default::default()
+    };
+    fly.update(&mut camera, &climb, 1.0);
+    assert_vec_close(camera.position(), step + Vec3::new(0.0, 0.5, 0.0));
+}
+
+// Switching controllers doesn't move the camera
+#[test]
+fn switching_controllers_keeps_the_view() {
+    let mut camera = camera();
+    let target = Vec3::new(0.0, 0.0, -10.0);
+    camera.look_at(Vec3::new(4.0, 3.0, 0.0), target, Vec3::new(0.0, 1.0, 0.0));
+
+    let mut controllers: Vec<Box<dyn CameraController>> = vec![
+        Box::new(FlyController::new(0.1, 0.01)),
+        Box::new(OrbitController::new(
+            target,
+            (Vec3::new(4.0, 3.0, 0.0) - target).magnitude(),
+            0.01,
+        )),
+    ];
+
+    for controller in &mut controllers {
+        let (position, forward) = (camera.position(), camera.forward());
+        controller.sync(&camera);
+        controller.update(&mut camera, &ControllerInput::default(), 1.0 / 60.0);
+        assert_vec_close(camera.position(), position);
+        assert_vec_close(camera.forward(), forward);
+    }
+}
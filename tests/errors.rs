@@ -0,0 +1,225 @@
+// Loading and drawing broken assets returns errors instead of panicking
+use std::path::{Path, PathBuf};
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::error::Error;
+use haywire_rasterizer::custom_data_types::game_object::GameObject;
+use haywire_rasterizer::custom_data_types::material::Material;
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::mesh::Mesh;
+use haywire_rasterizer::custom_data_types::rasterizer::Rasterizer;
+use haywire_rasterizer::custom_data_types::render_target::RenderTarget;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::shader::{DefaultFragmentShader, DefaultVertexShader};
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+// Writes contents to a file of the test output directory
+fn write_asset(name: &str, contents: &str) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("errors");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(path: &Path) -> Result<Mesh, Error> {
+    Mesh::new(path)
+}
+
+fn triangle() -> Mesh {
+    Mesh {
+        name: String::from("triangle"),
+        vertices: vec![
+            Vec3::new(-1.0, -1.0, -3.0),
+            Vec3::new(1.0, -1.0, -3.0),
+            Vec3::new(0.0, 1.0, -3.0),
+        ],
+        indices: vec![0, 1, 2],
+        materials: vec![Material::default()],
+        material_ids: vec![0],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn missing_file() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("does_not_exist.obj");
+    assert!(matches!(load(&path), Err(Error::Io(_))));
+}
+
+#[test]
+fn unsupported_format() {
+    let path = write_asset("model.fbx", "Kaydara FBX Binary  \0");
+    assert!(matches!(load(&path), Err(Error::UnsupportedFormat(_))));
+}
+
+#[test]
+fn malformed_obj() {
+    let path = write_asset("malformed.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 7\n");
+    assert!(matches!(load(&path), Err(Error::Parse(_))));
+
+    let path = write_asset("garbage.obj", "v 0 zero 0\nf 1 2 3\n");
+    assert!(matches!(load(&path), Err(Error::Parse(_))));
+}
+
+#[test]
+fn obj_without_triangles() {
+    let path = write_asset("points.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\n");
+    assert!(matches!(load(&path), Err(Error::DegenerateGeometry(_))));
+}
+
+// A missing MTL file is only reported, the mesh loads with the default material
+#[test]
+fn missing_materials_still_load() {
+    let path = write_asset(
+        "missing_mtl.obj",
+        "mtllib nowhere.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n",
+    );
+
+    let mesh = load(&path).unwrap();
+    assert_eq!(mesh.indices.len(), 3);
+    assert_eq!(mesh.materials.len(), 1);
+}
+
+#[test]
+fn validate_hand_built_meshes() {
+    assert!(triangle().validate().is_ok());
+    assert!(Mesh::default().validate().is_ok());
+
+    let mut mesh = triangle();
+    mesh.indices[2] = 3;
+    assert!(matches!(
+        mesh.validate(),
+        Err(Error::InvalidIndex {
+            what: "vertex",
+            index: 3,
+            len: 3
+        })
+    ));
+
+    let mut mesh = triangle();
+    mesh.normals = vec![Vec3::new(0.0, 0.0, 1.0); 2];
+    assert!(matches!(
+        mesh.validate(),
+        Err(Error::InvalidIndex { what: "normal", .. })
+    ));
+
+    let mut mesh = triangle();
+    mesh.material_ids = vec![1];
+    assert!(matches!(
+        mesh.validate(),
+        Err(Error::InvalidIndex {
+            what: "material",
+            ..
+        })
+    ));
+
+    let mut mesh = triangle();
+    mesh.indices.push(0);
+    assert!(matches!(mesh.validate(), Err(Error::DegenerateGeometry(_))));
+}
+
+#[test]
+fn draw_scene_checks_the_meshes() {
+    let camera = Camera::new(
+        ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0)),
+        1.0,
+        1.0,
+        0.5,
+        100.0,
+    );
+    let identity = || ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+    let mut scene = Scene::new(
+        vec![triangle()],
+        vec![
+            GameObject::new(0, identity()),
+            GameObject::new(1, identity()),
+        ],
+        camera,
+    );
+
+    let mut rasterizer = Rasterizer::new(RenderTarget::new(16, 16));
+    rasterizer.target.clear(Color::new(0, 0, 0, 255));
+    let result = rasterizer.draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new());
+    assert!(matches!(
+        result,
+        Err(Error::InvalidIndex {
+            what: "mesh",
+            index: 1,
+            len: 1
+        })
+    ));
+    // Nothing was drawn
+    assert!(
+        rasterizer
+            .target
+            .draw_buffer
+            .buffer()
+            .iter()
+            .all(|&c| c == 0)
+    );
+
+    // Meshes changed after they were loaded are checked again
+    scene.objects.pop();
+    scene.meshes[0].indices[2] = 9;
+    let result = rasterizer.draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new());
+    assert!(matches!(
+        result,
+        Err(Error::InvalidIndex {
+            what: "vertex",
+            index: 9,
+            len: 3
+        })
+    ));
+
+    scene.meshes[0].indices[2] = 2;
+    rasterizer
+        .draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new())
+        .unwrap();
+    assert!(
+        rasterizer
+            .target
+            .draw_buffer
+            .buffer()
+            .iter()
+            .any(|&c| c != 0)
+    );
+}
+
+// The triangles without a material id use the default material
+#[test]
+fn meshes_without_materials_draw() {
+    let mut mesh = triangle();
+    mesh.materials.clear();
+    mesh.material_ids.clear();
+    assert!(mesh.validate().is_ok());
+    assert_eq!(mesh.material(0).name, Material::default().name);
+
+    let identity = || ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+    let camera = Camera::new(identity(), 1.0, 1.0, 0.5, 100.0);
+    let scene = Scene::new(vec![mesh], vec![GameObject::new(0, identity())], camera);
+
+    let mut rasterizer = Rasterizer::new(RenderTarget::new(16, 16));
+    rasterizer.target.clear(Color::new(0, 0, 0, 255));
+    rasterizer
+        .draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new())
+        .unwrap();
+    assert!(
+        rasterizer
+            .target
+            .draw_buffer
+            .buffer()
+            .iter()
+            .any(|&c| c != 0)
+    );
+}
+
+#[test]
+fn dividing_by_zero_does_not_panic() {
+    let v = Vec4::new(1.0, -1.0, 0.0, 1.0) / 0.0;
+    assert_eq!(v.x, f32::INFINITY);
+    assert_eq!(v.y, f32::NEG_INFINITY);
+    assert!(v.z.is_nan());
+}
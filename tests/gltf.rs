@@ -0,0 +1,562 @@
+// glTF import from .gltf files (embedded or external buffers) and .glb files
+use std::path::{Path, PathBuf};
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::error::Error;
+use haywire_rasterizer::custom_data_types::gltf::GltfScene;
+use haywire_rasterizer::custom_data_types::image::Image;
+use haywire_rasterizer::custom_data_types::mat3::Mat3;
+use haywire_rasterizer::custom_data_types::material::AlphaMode;
+use haywire_rasterizer::custom_data_types::matrices::{Matrix4x4, ModelMatrix};
+use haywire_rasterizer::custom_data_types::rasterizer::Rasterizer;
+use haywire_rasterizer::custom_data_types::render_target::RenderTarget;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::shader::{DefaultFragmentShader, DefaultVertexShader};
+use haywire_rasterizer::custom_data_types::texture::{AddressMode, Filter};
+use haywire_rasterizer::custom_data_types::vec2::Vec2;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+// The binary buffer of a file, and the buffer views and accessors pointing into it
+#[derive(Default)]
+struct Buffer {
+    bytes: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+}
+
+impl Buffer {
+    fn view(&mut self, bytes: &[u8]) -> usize {
+        while !self.bytes.len().is_multiple_of(4) {
+            self.bytes.push(0);
+        }
+        self.views.push(format!(
+            "{{\"buffer\": 0, \"byteOffset\": {}, \"byteLength\": {}}}",
+            self.bytes.len(),
+            bytes.len()
+        ));
+        self.bytes.extend(bytes);
+        self.views.len() - 1
+    }
+
+    fn accessor(&mut self, bytes: &[u8], component_type: u32, count: usize, kind: &str) -> usize {
+        let view = self.view(bytes);
+        self.accessors.push(format!(
+            "{{\"bufferView\": {}, \"componentType\": {}, \"count\": {}, \"type\": \"{}\"}}",
+            view, component_type, count, kind
+        ));
+        self.accessors.len() - 1
+    }
+
+    fn floats(&mut self, values: &[f32], components: usize, kind: &str) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        self.accessor(&bytes, 5126, values.len() / components, kind)
+    }
+
+    // Indices stored with size bytes each
+    fn indices(&mut self, values: &[u32], size: usize) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes()[..size].to_vec())
+            .collect();
+        let component_type = match size {
+            1 => 5121,
+            2 => 5123,
+            _ => 5125,
+        };
+        self.accessor(&bytes, component_type, values.len(), "SCALAR")
+    }
+
+    // The whole document, the buffer being at uri (or in the GLB's binary chunk)
+    fn document(&self, uri: Option<&str>, rest: &str) -> String {
+        let uri = uri.map_or(String::new(), |uri| format!("\"uri\": \"{}\", ", uri));
+        format!(
+            "{{\"asset\": {{\"version\": \"2.0\", \"generator\": \"by hand \\u00e9\"}},\n\
+             \"buffers\": [{{{}\"byteLength\": {}}}],\n\
+             \"bufferViews\": [{}],\n\"accessors\": [{}],\n{}}}",
+            uri,
+            self.bytes.len(),
+            self.views.join(", "),
+            self.accessors.join(", "),
+            rest
+        )
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn glb(json: &str, binary: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut binary = binary.to_vec();
+    binary.resize(binary.len().next_multiple_of(4), 0);
+
+    let mut bytes = b"glTF".to_vec();
+    bytes.extend(2u32.to_le_bytes());
+    bytes.extend((12 + 8 + json.len() as u32 + 8 + binary.len() as u32).to_le_bytes());
+    bytes.extend((json.len() as u32).to_le_bytes());
+    bytes.extend(b"JSON");
+    bytes.extend(json);
+    bytes.extend((binary.len() as u32).to_le_bytes());
+    bytes.extend(b"BIN\0");
+    bytes.extend(binary);
+    bytes
+}
+
+fn write_asset(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("gltf");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+// A quad in two primitives (8 and 16 bit indices) with two materials, and a strip
+// (32 bit indices). The quad's node is the child of a moved and scaled one
+fn scene_buffer() -> (Buffer, String) {
+    let mut buffer = Buffer::default();
+    let positions = buffer.floats(
+        &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+        3,
+        "VEC3",
+    );
+    let normals = buffer.floats(&[0.0, 0.0, 1.0].repeat(4), 3, "VEC3");
+    let uvs = buffer.floats(&[0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.25], 2, "VEC2");
+    let tangents = buffer.floats(&[1.0, 0.0, 0.0, -1.0].repeat(4), 4, "VEC4");
+    let first = buffer.indices(&[0, 1, 2], 1);
+    let second = buffer.indices(&[0, 2, 3], 2);
+    let strip = buffer.indices(&[0, 1, 3, 2], 4);
+
+    let attributes = format!(
+        "{{\"POSITION\": {}, \"NORMAL\": {}, \"TEXCOORD_0\": {}, \"TANGENT\": {}}}",
+        positions, normals, uvs, tangents
+    );
+    let rest = format!(
+        "\"meshes\": [\
+           {{\"name\": \"quad\", \"primitives\": [\
+             {{\"attributes\": {a}, \"indices\": {first}, \"material\": 0}},\
+             {{\"attributes\": {a}, \"indices\": {second}, \"material\": 1}}]}},\
+           {{\"primitives\": [{{\"attributes\": {{\"POSITION\": {positions}}}, \
+             \"indices\": {strip}, \"mode\": 5}}]}}],\n\
+         \"materials\": [\
+           {{\"name\": \"red\", \"emissiveFactor\": [0, 0.5, 0], \"pbrMetallicRoughness\": \
+             {{\"baseColorFactor\": [1, 0, 0, 0.5], \"metallicFactor\": 0, \"roughnessFactor\": 0.5}}}},\
+           {{\"alphaMode\": \"BLEND\", \"pbrMetallicRoughness\": {{\"baseColorFactor\": [0.5, 0.5, 0.5, 0.5]}}}}],\n\
+         \"nodes\": [\
+           {{\"name\": \"parent\", \"translation\": [1, 0, 0], \"scale\": [2, 2, 2], \"children\": [1]}},\
+           {{\"name\": \"child\", \"mesh\": 0, \"translation\": [0, 1, 0], \
+             \"rotation\": [0, 0, 0.7071068, 0.7071068]}},\
+           {{\"mesh\": 1, \"matrix\": [1,0,0,0, 0,1,0,0, 0,0,1,0, 0,0,-5,1]}},\
+           {{\"name\": \"not in the scene\", \"mesh\": 1}}],\n\
+         \"scene\": 0,\n\"scenes\": [{{\"nodes\": [0, 2]}}]\n",
+        a = attributes
+    );
+    (buffer, rest)
+}
+
+fn assert_close(actual: &Matrix4x4, expected: &Matrix4x4) {
+    for row in 0..4 {
+        for col in 0..4 {
+            let (a, e) = (actual.data[row][col], expected.data[row][col]);
+            assert!(
+                (a - e).abs() < 1e-4,
+                "{:?} instead of {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn embedded_gltf() {
+    let (buffer, rest) = scene_buffer();
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64(&buffer.bytes)
+    );
+    let path = write_asset("embedded.gltf", buffer.document(Some(&uri), &rest));
+    let gltf = GltfScene::load(path).unwrap();
+
+    let quad = &gltf.meshes[0];
+    assert_eq!(quad.name, "quad");
+    // Both primitives have their own copy of the vertices
+    assert_eq!(quad.vertices.len(), 8);
+    assert_eq!(quad.indices, [0, 1, 2, 4, 6, 7]);
+    assert_eq!(quad.material_ids, [0, 1]);
+    assert_eq!(quad.groups[0].name, "quad");
+    assert_eq!(quad.groups[0].triangles, 0..2);
+    assert_eq!(quad.normals[5], Vec3::new(0.0, 0.0, 1.0));
+    assert_eq!(quad.tangents[3], Vec4::new(1.0, 0.0, 0.0, -1.0));
+    // v starts at the top of the image in glTF
+    assert_eq!(quad.uvs[3], Vec2::new(0.0, 0.75));
+    assert!(quad.colors.is_empty());
+
+    let red = &quad.materials[0];
+    assert_eq!(red.name, "red");
+    assert_eq!(red.diffuse, Vec4::new(1.0, 0.0, 0.0, 1.0));
+    assert!(
+        red.specular
+            .approx_eq(Vec4::new(0.04, 0.04, 0.04, 1.0), 1e-6)
+    );
+    assert_eq!(red.emissive, Vec4::new(0.0, 0.5, 0.0, 1.0));
+    assert_eq!((red.metallic, red.roughness), (0.0, 0.5));
+    // Opaque materials ignore the alpha of the base color
+    assert_eq!(red.opacity, 1.0);
+    assert!(red.shininess > 20.0);
+
+    let metal = &quad.materials[1];
+    assert_eq!(metal.name, "material 1");
+    assert!(metal.specular.approx_eq(metal.diffuse, 1e-6));
+    assert_eq!(metal.opacity, 0.5);
+    assert_eq!(metal.shininess, 0.0);
+
+    let strip = &gltf.meshes[1];
+    assert_eq!(strip.name, "mesh 1");
+    assert_eq!(strip.indices, [0, 1, 3, 3, 1, 2]);
+    assert!(strip.normals.is_empty() && strip.uvs.is_empty());
+    assert_eq!(strip.materials.len(), 1);
+
+    // Only the nodes of the scene that have a mesh
+    let names: Vec<&str> = gltf.nodes.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, ["child", "node 2"]);
+    assert_eq!(gltf.nodes[1].mesh, 1);
+
+    let parent = ModelMatrix::new(Vec3::new(1.0, 0.0, 0.0), Vec3::zero(), Vec3::splat(2.0));
+    let child = ModelMatrix::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, std::f32::consts::FRAC_PI_2),
+        Vec3::splat(1.0),
+    );
+    assert_close(
+        &gltf.nodes[0].transform(),
+        &(parent.get_model_matrix() * child.get_model_matrix()),
+    );
+    let moved = ModelMatrix::new(Vec3::new(0.0, 0.0, -5.0), Vec3::zero(), Vec3::splat(1.0));
+    assert_close(&gltf.nodes[1].transform(), &moved.get_model_matrix());
+}
+
+fn black() -> Color {
+    Color::new(0, 0, 0, 255)
+}
+
+fn white() -> Color {
+    Color::new(255, 255, 255, 255)
+}
+
+fn checkerboard() -> Image {
+    Image::new(vec![white(), black(), black(), white()], 2, 2)
+}
+
+#[test]
+fn external_buffer_and_textures() {
+    let (buffer, rest) = scene_buffer();
+    write_asset("scene buffer.bin", &buffer.bytes);
+    write_asset("checkerboard.png", checkerboard().encode_png());
+
+    // The first material uses the image with a sampler, the second one without
+    let rest = rest
+        .replace(
+            "\"name\": \"red\",",
+            "\"name\": \"red\", \"normalTexture\": {\"index\": 1},",
+        )
+        .replace(
+            "\"pbrMetallicRoughness\": {\"baseColorFactor\": [1",
+            "\"pbrMetallicRoughness\": {\"baseColorTexture\": {\"index\": 0}, \"baseColorFactor\": [1",
+        )
+        + ",\"images\": [{\"uri\": \"checkerboard.png\"}, {\"uri\": \"missing.png\"}],\n\
+           \"samplers\": [{\"wrapS\": 33071, \"wrapT\": 33071, \"minFilter\": 9728}],\n\
+           \"textures\": [{\"source\": 0, \"sampler\": 0}, {\"source\": 1}]\n";
+    let path = write_asset(
+        "external.gltf",
+        buffer.document(Some("scene%20buffer.bin"), &rest),
+    );
+    let gltf = GltfScene::load(path).unwrap();
+
+    assert_eq!(gltf.meshes[0].indices, [0, 1, 2, 4, 6, 7]);
+    let red = &gltf.meshes[0].materials[0];
+    let texture = red.diffuse_texture.as_ref().unwrap();
+    assert_eq!((texture.width(), texture.height()), (2, 2));
+    assert_eq!(texture.get(1, 0).format_as_u32(), black().format_as_u32());
+    assert_eq!(texture.address_mode, AddressMode::Clamp);
+    assert_eq!(texture.filter, Filter::Nearest);
+    assert_eq!(texture.mip_levels(), 1);
+
+    // The image that can't be found only leaves the texture out
+    assert!(red.normal_texture.is_none());
+    assert!(gltf.meshes[0].materials[1].diffuse_texture.is_none());
+}
+
+#[test]
+fn binary_glb() {
+    let (mut buffer, rest) = scene_buffer();
+    let image = buffer.view(&checkerboard().encode_png());
+    let rest = rest.replace(
+        "\"pbrMetallicRoughness\": {\"baseColorFactor\": [0.5",
+        "\"emissiveTexture\": {\"index\": 0}, \"pbrMetallicRoughness\": {\"baseColorFactor\": [0.5",
+    ) + &format!(
+        ",\"images\": [{{\"bufferView\": {}, \"mimeType\": \"image/png\"}}],\n\
+             \"textures\": [{{\"source\": 0}}]\n",
+        image
+    );
+    let bytes = glb(&buffer.document(None, &rest), &buffer.bytes);
+    let gltf = GltfScene::load(write_asset("scene.glb", bytes)).unwrap();
+
+    assert_eq!(gltf.meshes.len(), 2);
+    assert_eq!(gltf.meshes[1].indices, [0, 1, 3, 3, 1, 2]);
+    assert_eq!(gltf.nodes.len(), 2);
+
+    let texture = gltf.meshes[0].materials[1]
+        .emissive_texture
+        .as_ref()
+        .unwrap();
+    assert_eq!(texture.get(0, 0).format_as_u32(), white().format_as_u32());
+    // Without a sampler the texture is mipmapped
+    assert_eq!(texture.filter, Filter::Trilinear);
+    assert_eq!(texture.mip_levels(), 2);
+}
+
+#[test]
+fn scene_objects_follow_the_nodes() {
+    let (buffer, rest) = scene_buffer();
+    let uri = format!("data:;base64,{}", base64(&buffer.bytes));
+    let path = write_asset("scene.gltf", buffer.document(Some(&uri), &rest));
+
+    let identity = ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+    let camera = Camera::new(identity, 1.0, 1.0, 0.5, 100.0);
+    let mut scene = Scene::new(vec![Default::default()], vec![], camera);
+
+    let placement = ModelMatrix::new(Vec3::new(0.0, 0.0, -10.0), Vec3::zero(), Vec3::splat(1.0));
+    let objects = scene.load_gltf(&path, placement).unwrap();
+    assert_eq!(objects, 0..2);
+    assert_eq!(scene.meshes.len(), 3);
+    assert_eq!(scene.objects[0].object_id, 1);
+    assert_eq!(scene.objects[1].object_id, 2);
+
+    // The object's own transform is the node's, the parent holds the rest
+    let child = &scene.objects[0].model_matrix;
+    assert!(
+        child
+            .translation()
+            .approx_eq(Vec3::new(0.0, 1.0, 0.0), 1e-5)
+    );
+    assert!(child.scale().approx_eq(Vec3::splat(1.0), 1e-5));
+    assert!(translation(&child.get_model_matrix()).approx_eq(Vec3::new(1.0, 2.0, -10.0), 1e-5));
+    let strip = scene.objects[1].model_matrix.get_model_matrix();
+    assert!(translation(&strip).approx_eq(Vec3::new(0.0, 0.0, -15.0), 1e-5));
+}
+
+fn translation(matrix: &Matrix4x4) -> Vec3 {
+    Vec3::new(matrix.data[0][3], matrix.data[1][3], matrix.data[2][3])
+}
+
+// A non-uniform scale over a rotated child shears it, which a translation, a rotation
+// and a scale alone can't hold
+#[test]
+fn sheared_hierarchy() {
+    let (buffer, rest) = scene_buffer();
+    let uri = format!("data:;base64,{}", base64(&buffer.bytes));
+    let rest = rest
+        .replace("\"scale\": [2, 2, 2]", "\"scale\": [2, 1, 1]")
+        .replace(
+            "\"rotation\": [0, 0, 0.7071068, 0.7071068]",
+            "\"rotation\": [0, 0, 0.38268343, 0.9238795]",
+        );
+    let path = write_asset("sheared.gltf", buffer.document(Some(&uri), &rest));
+
+    let parent = ModelMatrix::new(
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::zero(),
+        Vec3::new(2.0, 1.0, 1.0),
+    );
+    let child = ModelMatrix::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, std::f32::consts::FRAC_PI_4),
+        Vec3::splat(1.0),
+    );
+    let placement = ModelMatrix::new(Vec3::new(0.0, 0.0, -10.0), Vec3::zero(), Vec3::splat(1.0));
+    let expected =
+        placement.get_model_matrix() * parent.get_model_matrix() * child.get_model_matrix();
+
+    let identity = ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+    let camera = Camera::new(identity, 1.0, 1.0, 0.5, 100.0);
+    let mut scene = Scene::new(vec![], vec![], camera);
+    scene.load_gltf(&path, placement).unwrap();
+
+    let object = &scene.objects[0].model_matrix;
+    assert_close(&object.get_model_matrix(), &expected);
+    // Splitting the whole transform would lose the shear
+    let split = ModelMatrix::from_matrix(&expected).get_model_matrix();
+    assert!((split.data[0][1] - expected.data[0][1]).abs() > 0.1);
+
+    // The normals use the sheared transform too
+    let inverse_transpose = Mat3::from(expected).inverse().unwrap().transpose();
+    assert_close(
+        &object.normal_matrix().to_matrix4(),
+        &inverse_transpose.to_matrix4(),
+    );
+}
+
+// A triangle in front of the camera with a fully transparent base color texture:
+// opaque materials ignore its alpha, masked ones compare it to their own cutoff
+#[test]
+fn alpha_modes() {
+    let cases = [
+        ("\"alphaMode\": \"OPAQUE\",", AlphaMode::Opaque, None, true),
+        ("", AlphaMode::Opaque, None, true),
+        (
+            "\"alphaMode\": \"MASK\",",
+            AlphaMode::Mask,
+            Some(0.5),
+            false,
+        ),
+        (
+            "\"alphaMode\": \"MASK\", \"alphaCutoff\": 0,",
+            AlphaMode::Mask,
+            Some(0.0),
+            true,
+        ),
+        ("\"alphaMode\": \"BLEND\",", AlphaMode::Mask, None, false),
+    ];
+
+    for (i, (mode, alpha_mode, alpha_cutoff, drawn)) in cases.into_iter().enumerate() {
+        let mut buffer = Buffer::default();
+        let positions = buffer.floats(
+            &[-1.0, -1.0, -3.0, 1.0, -1.0, -3.0, 0.0, 1.0, -3.0],
+            3,
+            "VEC3",
+        );
+        let transparent = Image::new(vec![Color::new(255, 255, 255, 0)], 1, 1);
+        let image = buffer.view(&transparent.encode_png());
+        let rest = format!(
+            "\"meshes\": [{{\"primitives\": [\
+               {{\"attributes\": {{\"POSITION\": {}}}, \"material\": 0}}]}}],\n\
+             \"materials\": [{{{} \"pbrMetallicRoughness\": \
+               {{\"baseColorTexture\": {{\"index\": 0}}}}}}],\n\
+             \"images\": [{{\"bufferView\": {}, \"mimeType\": \"image/png\"}}],\n\
+             \"textures\": [{{\"source\": 0}}],\n\
+             \"nodes\": [{{\"mesh\": 0}}]\n",
+            positions, mode, image
+        );
+        let bytes = glb(&buffer.document(None, &rest), &buffer.bytes);
+        let path = write_asset(&format!("alpha {}.glb", i), bytes);
+
+        let identity = || ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+        let camera = Camera::new(identity(), 1.0, 1.0, 0.5, 100.0);
+        let mut scene = Scene::new(vec![], vec![], camera);
+        scene.load_gltf(&path, identity()).unwrap();
+
+        let material = &scene.meshes[0].materials[0];
+        assert_eq!(material.alpha_mode, alpha_mode, "{}", mode);
+        assert_eq!(material.alpha_cutoff, alpha_cutoff, "{}", mode);
+
+        let mut rasterizer = Rasterizer::new(RenderTarget::new(16, 16));
+        rasterizer.target.clear(black());
+        rasterizer
+            .draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new())
+            .unwrap();
+        let any_drawn = rasterizer
+            .target
+            .draw_buffer
+            .buffer()
+            .iter()
+            .any(|&c| c != black().format_as_u32());
+        assert_eq!(any_drawn, drawn, "{}", mode);
+    }
+}
+
+#[test]
+fn malformed_gltf() {
+    let (buffer, rest) = scene_buffer();
+    let uri = format!("data:;base64,{}", base64(&buffer.bytes));
+    let document = buffer.document(Some(&uri), &rest);
+    let load = |name: &str, contents: &str| GltfScene::load(write_asset(name, contents));
+
+    let result = load("cut.gltf", &document[..document.len() / 2]);
+    assert!(matches!(result, Err(Error::Parse(_))));
+
+    // 5 elements of 12 bytes don't fit in the 48 bytes of the positions
+    let result = load(
+        "past_the_end.gltf",
+        &document.replacen("\"count\": 4", "\"count\": 5", 1),
+    );
+    assert!(matches!(result, Err(Error::Parse(_))));
+
+    // Accessors without a buffer view only make zeros where their size is known
+    let huge = "{\"componentType\": 5126, \"count\": 1e18,";
+    for (name, accessor) in [("huge_normals.gltf", 1), ("huge_positions.gltf", 0)] {
+        let view = format!(
+            "{{\"bufferView\": {}, \"componentType\": 5126, \"count\": 4,",
+            accessor
+        );
+        let result = load(name, &document.replace(&view, huge));
+        assert!(matches!(result, Err(Error::Parse(_))), "{}", name);
+    }
+
+    let result = load(
+        "cycle.gltf",
+        &document.replace("\"children\": [1]}", "\"children\": [1, 0]}"),
+    );
+    assert!(matches!(result, Err(Error::Parse(_))));
+
+    // Nodes listing the next one twice: every node is reached 2^n times unless the
+    // second parent is caught
+    let chain: Vec<String> = (0..40)
+        .map(|i| format!("{{\"children\": [{}, {}]}}", i + 1, i + 1))
+        .chain([String::from("{}")])
+        .collect();
+    let result = load(
+        "shared_children.gltf",
+        &format!(
+            "{{\"asset\": {{\"version\": \"2.0\"}}, \"nodes\": [{}]}}",
+            chain.join(", ")
+        ),
+    );
+    assert!(matches!(result, Err(Error::Parse(_))));
+
+    let result = load(
+        "no_mesh.gltf",
+        &document.replace("\"mesh\": 1, \"matrix\"", "\"mesh\": 7, \"matrix\""),
+    );
+    assert!(matches!(
+        result,
+        Err(Error::InvalidIndex { what: "mesh", .. })
+    ));
+
+    let result = load("version.gltf", &document.replace("\"2.0\"", "\"1.0\""));
+    assert!(matches!(result, Err(Error::UnsupportedFormat(_))));
+
+    let result = load(
+        "missing_buffer.gltf",
+        &buffer.document(Some("missing.bin"), &rest),
+    );
+    assert!(matches!(result, Err(Error::Io(_))));
+
+    let mut bytes = glb(&buffer.document(None, &rest), &buffer.bytes);
+    bytes[4] = 1;
+    let result = GltfScene::load(write_asset("version.glb", bytes));
+    assert!(matches!(result, Err(Error::UnsupportedFormat(_))));
+}
@@ -0,0 +1,387 @@
+// Golden-image regression tests: the bundled assets are rendered headlessly with fixed
+// cameras and compared to the reference images in tests/golden.
+//
+// After an intended change to the output, re-bless the references with
+//     HAYWIRE_BLESS=1 cargo test --test golden
+// and check the new images before committing them.
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use haywire_rasterizer::custom_data_types::camera::{Camera, Projection};
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::game_object::GameObject;
+use haywire_rasterizer::custom_data_types::image::Image;
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::mesh::Mesh;
+use haywire_rasterizer::custom_data_types::multisample::{AntiAliasing, SampleCount};
+use haywire_rasterizer::custom_data_types::rasterizer::Rasterizer;
+use haywire_rasterizer::custom_data_types::render_target::RenderTarget;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::shader::{DefaultFragmentShader, DefaultVertexShader};
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 180;
+
+// A pixel differs if one of its channels is off by more than this
+const PIXEL_TOLERANCE: u8 = 8;
+// Fraction of the pixels allowed to differ (edges can move a little between platforms)
+const MAX_DIFFERENT_PIXELS: f64 = 0.002;
+// Over the whole image, in dB
+const MIN_PSNR: f64 = 40.0;
+
+const PERSPECTIVE: Projection = Projection::Perspective {
+    fov: 60.0f32.to_radians(),
+};
+
+fn manifest_path(relative: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(relative)
+}
+
+// Renders one asset with the default shaders, seen by a camera at the origin looking down -z
+fn render_target(
+    asset: &str,
+    model: ModelMatrix,
+    width: usize,
+    height: usize,
+    threads: usize,
+    anti_aliasing: AntiAliasing,
+    projection: Projection,
+) -> RenderTarget {
+    let camera = Camera::with_projection(
+        ModelMatrix::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ),
+        projection,
+        width as f32 / height as f32,
+        0.5,
+        100.0,
+    );
+
+    let path = manifest_path(asset);
+    let mesh = Mesh::new(&path).unwrap();
+    let scene = Scene::new(vec![mesh], vec![GameObject::new(0, model)], camera);
+
+    let mut rasterizer = Rasterizer::new(RenderTarget::new(width, height));
+    rasterizer.threads = threads;
+    rasterizer.anti_aliasing = anti_aliasing;
+    rasterizer.target.clear(Color::new(0, 0, 0, 255));
+    rasterizer
+        .draw_scene(&scene, &DefaultVertexShader, &DefaultFragmentShader::new())
+        .unwrap();
+
+    rasterizer.target
+}
+
+fn render(asset: &str, model: ModelMatrix) -> Image {
+    render_anti_aliased(asset, model, AntiAliasing::Off)
+}
+
+fn render_anti_aliased(asset: &str, model: ModelMatrix, anti_aliasing: AntiAliasing) -> Image {
+    render_projected(asset, model, anti_aliasing, PERSPECTIVE)
+}
+
+fn render_projected(
+    asset: &str,
+    model: ModelMatrix,
+    anti_aliasing: AntiAliasing,
+    projection: Projection,
+) -> Image {
+    render_target(asset, model, WIDTH, HEIGHT, 1, anti_aliasing, projection)
+        .draw_buffer
+        .to_image()
+}
+
+fn psnr(actual: &Image, expected: &Image) -> f64 {
+    let squared_error: f64 = actual
+        .pixels()
+        .iter()
+        .zip(expected.pixels())
+        .map(|(a, e)| {
+            [(a.r(), e.r()), (a.g(), e.g()), (a.b(), e.b())]
+                .iter()
+                .map(|&(a, e)| (a as f64 - e as f64).powi(2))
+                .sum::<f64>()
+        })
+        .sum();
+    let mse = squared_error / (actual.pixels().len() * 3) as f64;
+
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+fn channel_difference(a: Color, e: Color) -> u8 {
+    a.r()
+        .abs_diff(e.r())
+        .max(a.g().abs_diff(e.g()))
+        .max(a.b().abs_diff(e.b()))
+}
+
+// The differences scaled up so they are visible, with the pixels over the tolerance in red
+fn diff_image(actual: &Image, expected: &Image) -> Image {
+    let pixels = actual
+        .pixels()
+        .iter()
+        .zip(expected.pixels())
+        .map(|(&a, &e)| {
+            let difference = channel_difference(a, e);
+            if difference > PIXEL_TOLERANCE {
+                Color::new(255, 0, 0, 255)
+            } else {
+                let value = difference.saturating_mul(16);
+                Color::new(value, value, value, 255)
+            }
+        })
+        .collect();
+
+    Image::new(pixels, actual.width(), actual.height())
+}
+
+fn check_golden(name: &str, actual: Image) {
+    let reference_path = manifest_path(&format!("tests/golden/{}.png", name));
+
+    if std::env::var_os("HAYWIRE_BLESS").is_some() {
+        std::fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual.save(&reference_path).unwrap();
+        println!("Blessed {}", reference_path.display());
+        return;
+    }
+
+    let expected = Image::load(&reference_path).unwrap_or_else(|err| {
+        panic!(
+            "Can't load the reference {} ({}), run HAYWIRE_BLESS=1 cargo test --test golden",
+            reference_path.display(),
+            err
+        )
+    });
+
+    let same_size = actual.width() == expected.width() && actual.height() == expected.height();
+    let (different, psnr) = if same_size {
+        let different = actual
+            .pixels()
+            .iter()
+            .zip(expected.pixels())
+            .filter(|&(&a, &e)| channel_difference(a, e) > PIXEL_TOLERANCE)
+            .count();
+        (different, psnr(&actual, &expected))
+    } else {
+        (actual.pixels().len(), 0.0)
+    };
+
+    let different_fraction = different as f64 / actual.pixels().len() as f64;
+    if different_fraction <= MAX_DIFFERENT_PIXELS && psnr >= MIN_PSNR {
+        return;
+    }
+
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&output).unwrap();
+    let actual_path = output.join(format!("{}_actual.png", name));
+    actual.save(&actual_path).unwrap();
+    if same_size {
+        diff_image(&actual, &expected)
+            .save(output.join(format!("{}_diff.png", name)))
+            .unwrap();
+    }
+
+    panic!(
+        "{} doesn't match its reference: {} pixels differ ({:.3}%), PSNR {:.2} dB. \
+         The output and the diff are in {}",
+        name,
+        different,
+        different_fraction * 100.0,
+        psnr,
+        output.display()
+    );
+}
+
+fn cube_model() -> ModelMatrix {
+    ModelMatrix::new(
+        Vec3::new(-0.2, -0.3, -3.0),
+        Vec3::new(0.5, 0.6, 0.0),
+        Vec3::new(1.0, 1.0, 1.0),
+    )
+}
+
+#[test]
+fn cube() {
+    check_golden("cube", render("assets/cube.obj", cube_model()));
+}
+
+#[test]
+fn cube_msaa_4x() {
+    let image = render_anti_aliased(
+        "assets/cube.obj",
+        cube_model(),
+        AntiAliasing::Msaa(SampleCount::X4),
+    );
+    check_golden("cube_msaa_4x", image);
+}
+
+#[test]
+fn cube_ssaa_2x() {
+    let image = render_anti_aliased(
+        "assets/cube.obj",
+        cube_model(),
+        AntiAliasing::Ssaa(NonZeroUsize::new(2).unwrap()),
+    );
+    check_golden("cube_ssaa_2x", image);
+}
+
+// The cube is 3 units away, where the 60 degree perspective shows about 3.5 units
+#[test]
+fn cube_orthographic() {
+    let projection = Projection::Orthographic { height: 3.5 };
+    let image = render_projected(
+        "assets/cube.obj",
+        cube_model(),
+        AntiAliasing::Off,
+        projection,
+    );
+    check_golden("cube_orthographic", image);
+}
+
+#[test]
+fn cube_oblique() {
+    let projection = Projection::Oblique {
+        height: 3.5,
+        angle: 45.0f32.to_radians(),
+        depth_scale: 0.5,
+        focus: 3.0,
+    };
+    let image = render_projected(
+        "assets/cube.obj",
+        cube_model(),
+        AntiAliasing::Off,
+        projection,
+    );
+    check_golden("cube_oblique", image);
+}
+
+// MSAA only changes the pixels on the edges: inside the cube and in the
+// background every sample gets the same color as without it
+#[test]
+fn msaa_only_changes_edges() {
+    let aliased = render("assets/cube.obj", cube_model());
+
+    for samples in [SampleCount::X2, SampleCount::X4, SampleCount::X8] {
+        let smooth =
+            render_anti_aliased("assets/cube.obj", cube_model(), AntiAliasing::Msaa(samples));
+        let different = aliased
+            .pixels()
+            .iter()
+            .zip(smooth.pixels())
+            .filter(|&(&a, &s)| channel_difference(a, s) != 0)
+            .count();
+
+        assert!(
+            different > 0,
+            "{}x MSAA didn't change anything",
+            samples.count()
+        );
+        assert!(
+            different < aliased.pixels().len() / 20,
+            "{}x MSAA changed {} pixels",
+            samples.count(),
+            different
+        );
+    }
+}
+
+#[test]
+fn suzzane() {
+    let image = render(
+        "assets/suzzane.obj",
+        ModelMatrix::new(
+            Vec3::new(2.5, -1.25, -8.5),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ),
+    );
+    check_golden("suzzane", image);
+}
+
+// Squashed and stretched, which needs the inverse-transpose normal matrix to light correctly
+#[test]
+fn suzzane_non_uniform_scale() {
+    let image = render(
+        "assets/suzzane.obj",
+        ModelMatrix::new(
+            Vec3::new(3.5, -1.0, -9.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.4, 0.8, 1.0),
+        ),
+    );
+    check_golden("suzzane_non_uniform_scale", image);
+}
+
+#[test]
+fn bunny() {
+    let image = render(
+        "assets/bunny.obj",
+        ModelMatrix::new(
+            Vec3::new(0.35, -2.2, -5.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(20.0, 20.0, 20.0),
+        ),
+    );
+    check_golden("bunny", image);
+}
+
+// The tiled path must give exactly the same color and depth as the single-threaded one,
+// also with a size that isn't a multiple of the tile size
+#[test]
+fn tiled_matches_single_threaded() {
+    let model = || {
+        ModelMatrix::new(
+            Vec3::new(0.35, -2.2, -1.8),
+            Vec3::new(0.0, 0.4, 0.0),
+            Vec3::new(20.0, 20.0, 20.0),
+        )
+    };
+
+    for anti_aliasing in [AntiAliasing::Off, AntiAliasing::Msaa(SampleCount::X4)] {
+        let single = render_target(
+            "assets/bunny.obj",
+            model(),
+            333,
+            187,
+            1,
+            anti_aliasing,
+            PERSPECTIVE,
+        );
+        for threads in [2, 3, 8] {
+            let tiled = render_target(
+                "assets/bunny.obj",
+                model(),
+                333,
+                187,
+                threads,
+                anti_aliasing,
+                PERSPECTIVE,
+            );
+
+            assert!(
+                single.draw_buffer.buffer() == tiled.draw_buffer.buffer(),
+                "the color buffer differs with {} threads ({:?})",
+                threads,
+                anti_aliasing
+            );
+            assert!(
+                single
+                    .depth_buffer
+                    .buffer()
+                    .iter()
+                    .zip(tiled.depth_buffer.buffer())
+                    .all(|(a, b)| a.to_bits() == b.to_bits()),
+                "the depth buffer differs with {} threads ({:?})",
+                threads,
+                anti_aliasing
+            );
+        }
+    }
+}
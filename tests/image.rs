@@ -0,0 +1,251 @@
+// Decoding PNG, TGA, BMP and PPM images, and rejecting broken ones with the right error
+use std::path::Path;
+
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::image::{Image, ImageError};
+
+fn rgba(color: Color) -> [u8; 4] {
+    [color.r(), color.g(), color.b(), color.a()]
+}
+
+fn assert_pixels(image: &Image, width: usize, height: usize, expected: &[[u8; 4]]) {
+    assert_eq!((image.width(), image.height()), (width, height));
+    let actual: Vec<[u8; 4]> = image.pixels().iter().map(|&c| rgba(c)).collect();
+    assert_eq!(actual, expected);
+}
+
+// 3x2, with a pixel that isn't opaque so the PNG encoder writes RGBA
+fn test_image() -> Image {
+    let pixels = [
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [10, 20, 30, 128],
+        [255, 255, 255, 255],
+        [0, 0, 0, 0],
+    ];
+    Image::new(
+        pixels
+            .iter()
+            .map(|p| Color::new(p[0], p[1], p[2], p[3]))
+            .collect(),
+        3,
+        2,
+    )
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut bytes = (data.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(chunk_type);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[4..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    bytes
+}
+
+// An 8 bit RGB PNG with the given size and compressed data
+fn png(width: u32, height: u32, idat: &[u8]) -> Vec<u8> {
+    let mut header = width.to_be_bytes().to_vec();
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    bytes.extend(chunk(b"IHDR", &header));
+    bytes.extend(chunk(b"IDAT", idat));
+    bytes.extend(chunk(b"IEND", &[]));
+    bytes
+}
+
+// The data of the IDAT chunks of an encoded PNG
+fn idat(png: &[u8]) -> Vec<u8> {
+    let mut data = vec![];
+    let mut position = 8;
+    while position < png.len() {
+        let length = u32::from_be_bytes(png[position..position + 4].try_into().unwrap()) as usize;
+        if &png[position + 4..position + 8] == b"IDAT" {
+            data.extend_from_slice(&png[position + 8..position + 8 + length]);
+        }
+        position += length + 12;
+    }
+    data
+}
+
+// A 2x2 BMP with 24 bits per pixel, the rows padded to 8 bytes
+fn bmp(height: i32, rows: [[u8; 6]; 2]) -> Vec<u8> {
+    let mut bytes = b"BM".to_vec();
+    bytes.extend_from_slice(&(54u32 + 16).to_le_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&54u32.to_le_bytes());
+
+    bytes.extend_from_slice(&40u32.to_le_bytes());
+    bytes.extend_from_slice(&2i32.to_le_bytes());
+    bytes.extend_from_slice(&height.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&24u16.to_le_bytes());
+    bytes.extend_from_slice(&[0; 24]);
+
+    for row in rows {
+        bytes.extend_from_slice(&row);
+        bytes.extend_from_slice(&[0; 2]);
+    }
+    bytes
+}
+
+#[test]
+fn png_round_trip() {
+    let image = test_image();
+    let decoded = Image::decode(&image.encode_png()).unwrap();
+    let expected: Vec<[u8; 4]> = image.pixels().iter().map(|&c| rgba(c)).collect();
+    assert_pixels(&decoded, 3, 2, &expected);
+}
+
+// PPM has no alpha channel, the pixels come back opaque
+#[test]
+fn ppm_round_trip() {
+    let image = test_image();
+    let decoded = Image::decode(&image.encode_ppm()).unwrap();
+    let expected: Vec<[u8; 4]> = image
+        .pixels()
+        .iter()
+        .map(|c| [c.r(), c.g(), c.b(), 255])
+        .collect();
+    assert_pixels(&decoded, 3, 2, &expected);
+}
+
+#[test]
+fn ascii_and_binary_ppm() {
+    let ascii = b"P3\n# a comment\n2 1\n15\n15 0 0  0 0 15\n";
+    assert_pixels(
+        &Image::decode(ascii).unwrap(),
+        2,
+        1,
+        &[[255, 0, 0, 255], [0, 0, 255, 255]],
+    );
+
+    let mut binary = b"P6\n2 1\n255\n".to_vec();
+    binary.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+    assert_pixels(
+        &Image::decode(&binary).unwrap(),
+        2,
+        1,
+        &[[1, 2, 3, 255], [4, 5, 6, 255]],
+    );
+
+    binary.pop();
+    assert!(matches!(
+        Image::decode(&binary),
+        Err(ImageError::UnexpectedEof)
+    ));
+}
+
+// The rows are stored from the bottom up unless the height is negative
+#[test]
+fn bottom_up_and_top_down_bmp() {
+    // BGR: blue, green / red, white
+    let rows = [[255, 0, 0, 0, 255, 0], [0, 0, 255, 255, 255, 255]];
+    let blue_green = [[0, 0, 255, 255], [0, 255, 0, 255]];
+    let red_white = [[255, 0, 0, 255], [255, 255, 255, 255]];
+
+    let bottom_up = Image::decode(&bmp(2, rows)).unwrap();
+    assert_pixels(&bottom_up, 2, 2, &[red_white, blue_green].concat());
+
+    let top_down = Image::decode(&bmp(-2, rows)).unwrap();
+    assert_pixels(&top_down, 2, 2, &[blue_green, red_white].concat());
+}
+
+// TGA has no magic bytes, it is only loaded from a file with the .tga extension
+#[test]
+fn rle_tga() {
+    // Type 10 (RLE true-color), 3x2, 24 bits per pixel, top to bottom
+    let mut bytes = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    bytes.extend_from_slice(&3u16.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&[24, 0x20]);
+    // A run of 4 red pixels going over the end of the first row, then 2 raw pixels
+    bytes.extend_from_slice(&[0x83, 0, 0, 255]);
+    bytes.extend_from_slice(&[0x01, 255, 0, 0, 0, 255, 0]);
+
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("rle.tga");
+    std::fs::write(&path, &bytes).unwrap();
+
+    let red = [255, 0, 0, 255];
+    assert_pixels(
+        &Image::load(&path).unwrap(),
+        3,
+        2,
+        &[red, red, red, red, [0, 0, 255, 255], [0, 255, 0, 255]],
+    );
+
+    // The last packet is missing a pixel
+    bytes.truncate(bytes.len() - 3);
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(matches!(Image::load(&path), Err(ImageError::UnexpectedEof)));
+}
+
+#[test]
+fn truncated_png() {
+    let bytes = test_image().encode_png();
+    assert!(matches!(
+        Image::decode(&bytes[..bytes.len() / 2]),
+        Err(ImageError::UnexpectedEof)
+    ));
+}
+
+#[test]
+fn png_with_a_bad_crc() {
+    let mut bytes = test_image().encode_png();
+    // The last byte of the IHDR CRC
+    bytes[32] ^= 1;
+    assert!(matches!(
+        Image::decode(&bytes),
+        Err(ImageError::Checksum("PNG chunk CRC"))
+    ));
+}
+
+#[test]
+fn oversized_dimensions() {
+    let huge = png(100_000, 100_000, &[]);
+    assert!(matches!(
+        Image::decode(&huge),
+        Err(ImageError::Malformed(_))
+    ));
+
+    let mut bytes = bmp(-2, [[0; 6]; 2]);
+    bytes[18..22].copy_from_slice(&100_000i32.to_le_bytes());
+    bytes[22..26].copy_from_slice(&(-100_000i32).to_le_bytes());
+    assert!(matches!(
+        Image::decode(&bytes),
+        Err(ImageError::Malformed(_))
+    ));
+
+    assert!(matches!(
+        Image::decode(b"P6\n100000 100000\n255\n"),
+        Err(ImageError::Malformed(_))
+    ));
+}
+
+// A 1x1 PNG whose data decompresses to a 512x512 image stops at the size of its header
+#[test]
+fn png_data_larger_than_the_header() {
+    let large = Image::new(vec![Color::new(0, 0, 0, 255); 512 * 512], 512, 512);
+    let bytes = png(1, 1, &idat(&large.encode_png()));
+    match Image::decode(&bytes) {
+        Err(ImageError::Malformed(what)) => assert!(what.contains("larger than expected")),
+        other => panic!("expected a malformed PNG, got {:?}", other.err()),
+    }
+}
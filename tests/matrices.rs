@@ -0,0 +1,172 @@
+// Checks of the general matrix operations and of the normal matrix of ModelMatrix
+use haywire_rasterizer::custom_data_types::matrices::{Matrix4x4, ModelMatrix};
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+
+fn assert_close(actual: &Matrix4x4, expected: &Matrix4x4) {
+    for row in 0..4 {
+        for col in 0..4 {
+            let (a, e) = (actual.data[row][col], expected.data[row][col]);
+            assert!(
+                (a - e).abs() < 1e-4,
+                "[{}][{}] is {} instead of {}\n{:?}",
+                row,
+                col,
+                a,
+                e,
+                actual
+            );
+        }
+    }
+}
+
+fn model() -> ModelMatrix {
+    ModelMatrix::new(
+        Vec3::new(1.0, -2.0, 3.5),
+        Vec3::new(0.3, -1.1, 2.0),
+        Vec3::new(2.0, 0.5, 3.0),
+    )
+}
+
+#[test]
+fn transpose_swaps_rows_and_columns() {
+    let matrix = Matrix4x4 {
+        data: [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ],
+    };
+    let expected = Matrix4x4 {
+        data: [
+            [1.0, 5.0, 9.0, 13.0],
+            [2.0, 6.0, 10.0, 14.0],
+            [3.0, 7.0, 11.0, 15.0],
+            [4.0, 8.0, 12.0, 16.0],
+        ],
+    };
+
+    assert_close(&matrix.transpose(), &expected);
+    assert_close(&matrix.transpose().transpose(), &matrix);
+}
+
+#[test]
+fn determinant() {
+    assert_eq!(Matrix4x4::identity().determinant(), 1.0);
+
+    // Rows 3 and 4 are linearly dependent
+    let singular = Matrix4x4 {
+        data: [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ],
+    };
+    assert_eq!(singular.determinant(), 0.0);
+    assert!(singular.inverse().is_none());
+
+    let matrix = Matrix4x4 {
+        data: [
+            [2.0, 0.0, 1.0, 3.0],
+            [1.0, 1.0, 0.0, 2.0],
+            [0.0, 4.0, 1.0, 1.0],
+            [3.0, 0.0, 2.0, 1.0],
+        ],
+    };
+    assert_eq!(matrix.determinant(), -20.0);
+    assert_eq!(matrix.transpose().determinant(), -20.0);
+
+    // A rotation keeps the volume, the scale multiplies it
+    assert!((model().get_model_matrix().determinant() - 3.0).abs() < 1e-4);
+}
+
+#[test]
+fn inverse_undoes_the_matrix() {
+    let matrices = [
+        Matrix4x4 {
+            data: [
+                [2.0, 0.0, 1.0, 3.0],
+                [1.0, 1.0, 0.0, 2.0],
+                [0.0, 4.0, 1.0, 1.0],
+                [3.0, 0.0, 2.0, 1.0],
+            ],
+        },
+        model().get_model_matrix(),
+    ];
+
+    for matrix in matrices {
+        let inverse = matrix.inverse().unwrap();
+        assert_close(&(matrix * inverse), &Matrix4x4::identity());
+        assert_close(&(inverse * matrix), &Matrix4x4::identity());
+    }
+}
+
+// A normal transformed by the normal matrix stays perpendicular to the transformed surface
+#[test]
+fn normal_matrix_keeps_normals_perpendicular() {
+    let model = model();
+    let model_matrix = model.get_model_matrix();
+
+    let tangents = [Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 2.0, -1.0)];
+    let normal = tangents[0].cross(tangents[1]);
+
+    let transformed = model.normal_matrix() * normal;
+    for tangent in tangents {
+        let dot = transformed.dot(model_matrix.transform_direction(tangent));
+        assert!(dot.abs() < 1e-4, "the normal is off by {}", dot);
+    }
+
+    // Moving the object doesn't change its normals
+    let mut moved = ModelMatrix::new(
+        Vec3::new(-7.0, 4.0, 0.0),
+        Vec3::new(0.3, -1.1, 2.0),
+        Vec3::new(2.0, 0.5, 3.0),
+    );
+    assert_close(
+        &moved.normal_matrix().to_matrix4(),
+        &model.normal_matrix().to_matrix4(),
+    );
+
+    // But scaling it does
+    moved.update_scale(Vec3::new(1.0, 1.0, 1.0));
+    let rotation = moved.get_model_matrix();
+    let transformed = moved.normal_matrix() * normal;
+    let expected = rotation.transform_direction(normal);
+    assert!(transformed.approx_eq(expected, 1e-4));
+}
+
+// A tiny scale has a tiny determinant (1e-15 here), but is still invertible: the normal
+// matrix keeps the rotation instead of falling back to the identity
+#[test]
+fn tiny_scales_keep_their_normal_matrix() {
+    let rotation = Vec3::new(0.3, -1.1, 2.0);
+    let tiny = ModelMatrix::new(Vec3::new(1.0, 2.0, 3.0), rotation, Vec3::splat(1e-5));
+    let rotated = ModelMatrix::new(Vec3::zero(), rotation, Vec3::splat(1.0));
+
+    let normal = Vec3::new(0.0, 0.6, 0.8);
+    let expected = rotated.get_model_matrix().transform_direction(normal);
+    let transformed = (tiny.normal_matrix() * normal).normalize();
+    assert!(transformed.approx_eq(expected, 1e-4), "{}", transformed);
+
+    let inverse = tiny.get_model_matrix().inverse().unwrap();
+    assert_close(&(tiny.get_model_matrix() * inverse), &Matrix4x4::identity());
+}
+
+#[test]
+fn from_matrix_splits_a_model_matrix() {
+    let model = model();
+    let split = ModelMatrix::from_matrix(&model.get_model_matrix());
+    assert_close(&split.get_model_matrix(), &model.get_model_matrix());
+    assert!(split.scale().approx_eq(Vec3::new(2.0, 0.5, 3.0), 1e-4));
+
+    // A mirroring comes back as a negative scale
+    let mirrored = ModelMatrix::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.5, 0.0, -0.2),
+        Vec3::new(1.0, -2.0, 1.0),
+    );
+    let split = ModelMatrix::from_matrix(&mirrored.get_model_matrix());
+    assert_close(&split.get_model_matrix(), &mirrored.get_model_matrix());
+    assert!(split.scale().x < 0.0);
+}
@@ -0,0 +1,141 @@
+// Files with several objects: loading them one by one, merged, and into a scene
+use std::path::{Path, PathBuf};
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::mesh::Mesh;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+
+// plain has no material, left is all red, right is split between red and blue
+// (so tobj makes two models of it), and empty only has vertices
+const SCENE_OBJ: &str = "\
+mtllib scene.mtl
+o plain
+v 5 0 0
+v 6 0 0
+v 5 1 0
+f 1 2 3
+o left
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+usemtl red
+f 4 5 6
+f 5 7 6
+o right
+v 2 0 0
+v 3 0 0
+v 2 1 0
+usemtl red
+f 8 9 10
+usemtl blue
+f 8 10 9
+o empty
+v 0 0 1
+v 1 0 1
+";
+
+const SCENE_MTL: &str = "\
+newmtl red
+Kd 1 0 0
+newmtl blue
+Kd 0 0 1
+";
+
+fn scene_path() -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("objects");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("scene.mtl"), SCENE_MTL).unwrap();
+    let path = directory.join("scene.obj");
+    std::fs::write(&path, SCENE_OBJ).unwrap();
+    path
+}
+
+fn material_names(mesh: &Mesh) -> Vec<&str> {
+    mesh.materials
+        .iter()
+        .map(|material| material.name.as_str())
+        .collect()
+}
+
+#[test]
+fn every_object_is_loaded() {
+    let meshes = Mesh::load_objects(scene_path()).unwrap();
+
+    let names: Vec<&str> = meshes.iter().map(|mesh| mesh.name.as_str()).collect();
+    assert_eq!(names, ["plain", "left", "right"]);
+
+    let triangles: Vec<usize> = meshes.iter().map(|mesh| mesh.indices.len() / 3).collect();
+    assert_eq!(triangles, [1, 2, 2]);
+
+    // Every mesh only has its own vertices
+    assert_eq!(meshes[0].vertices[0], Vec3::new(5.0, 0.0, 0.0));
+    assert_eq!(meshes[2].vertices.len(), 6);
+    assert!(meshes[2].vertices.iter().all(|v| v.x >= 2.0 && v.x <= 3.0));
+}
+
+#[test]
+fn objects_only_keep_their_materials() {
+    let meshes = Mesh::load_objects(scene_path()).unwrap();
+
+    assert_eq!(material_names(&meshes[0]), ["default"]);
+    assert_eq!(material_names(&meshes[1]), ["red"]);
+    assert_eq!(material_names(&meshes[2]), ["red", "blue"]);
+    assert_eq!(meshes[2].material(0).name, "red");
+    assert_eq!(meshes[2].material(1).name, "blue");
+}
+
+#[test]
+fn merged_mesh_lists_the_objects() {
+    let mesh = Mesh::new(scene_path()).unwrap();
+
+    assert_eq!(mesh.name, "scene");
+    assert_eq!(mesh.indices.len(), 15);
+    // right's vertices are there twice, once for each of its models
+    assert_eq!(mesh.vertices.len(), 13);
+
+    let groups: Vec<(&str, std::ops::Range<usize>)> = mesh
+        .groups
+        .iter()
+        .map(|group| (group.name.as_str(), group.triangles.clone()))
+        .collect();
+    assert_eq!(groups, [("plain", 0..1), ("left", 1..3), ("right", 3..5)]);
+    assert!(mesh.group("empty").is_none());
+
+    // The materials shared by several objects are only there once
+    assert_eq!(material_names(&mesh), ["default", "red", "blue"]);
+    let right = mesh.group("right").unwrap().triangles.clone();
+    let names: Vec<&str> = right.map(|t| mesh.material(t).name.as_str()).collect();
+    assert_eq!(names, ["red", "blue"]);
+}
+
+#[test]
+fn scene_instantiates_a_file() {
+    let model = ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+    let camera = Camera::new(model, 1.0, 1.0, 0.1, 100.0);
+    let mut scene = Scene::new(vec![Mesh::default()], vec![], camera);
+
+    let meshes = Mesh::load_objects(scene_path()).unwrap();
+    let translation = Vec3::new(0.0, 0.0, -5.0);
+    let objects = scene.instantiate(
+        meshes,
+        ModelMatrix::new(translation, Vec3::zero(), Vec3::splat(2.0)),
+    );
+
+    assert_eq!(objects, 0..3);
+    assert_eq!(scene.meshes.len(), 4);
+    for (index, object) in scene.objects[objects].iter().enumerate() {
+        assert_eq!(object.object_id, index + 1);
+        assert_eq!(object.model_matrix.translation(), translation);
+    }
+
+    // An object can be moved on its own afterwards
+    let left = scene.find_object("left").unwrap();
+    assert_eq!(left.object_id, 2);
+    left.model_matrix
+        .update_translate(Vec3::new(1.0, 0.0, -5.0));
+    assert_eq!(scene.objects[0].model_matrix.translation(), translation);
+    assert!(scene.find_object("empty").is_none());
+}
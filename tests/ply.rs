@@ -0,0 +1,315 @@
+// PLY import in every encoding, and drawing the vertex colors
+use std::path::{Path, PathBuf};
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::color::Color;
+use haywire_rasterizer::custom_data_types::error::Error;
+use haywire_rasterizer::custom_data_types::game_object::GameObject;
+use haywire_rasterizer::custom_data_types::material::Material;
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::mesh::{Mesh, MeshFormat};
+use haywire_rasterizer::custom_data_types::rasterizer::Rasterizer;
+use haywire_rasterizer::custom_data_types::render_target::RenderTarget;
+use haywire_rasterizer::custom_data_types::scene::Scene;
+use haywire_rasterizer::custom_data_types::shader::{
+    DefaultVertexShader, FragmentInput, FragmentShader,
+};
+use haywire_rasterizer::custom_data_types::vec2::Vec2;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+// A unit quad: position, normal, color, UV and confidence for every corner
+const POSITIONS: [[f32; 3]; 4] = [
+    [0.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [0.0, 1.0, 0.0],
+];
+const COLORS: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [51, 102, 204]];
+const UVS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+const CONFIDENCE: [f32; 4] = [0.5, 0.25, 1.0, 0.0];
+
+fn header(format: &str) -> String {
+    format!(
+        "ply\nformat {} 1.0\ncomment made by hand\n\
+         element vertex 4\n\
+         property float x\nproperty float y\nproperty float z\n\
+         property float nx\nproperty float ny\nproperty float nz\n\
+         property uchar red\nproperty uchar green\nproperty uchar blue\n\
+         property float s\nproperty float t\n\
+         property float confidence\n\
+         element face 2\nproperty list uchar int vertex_indices\n\
+         element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+         end_header\n",
+        format
+    )
+}
+
+// The quad, plus a face with 2 vertices and an edge that are both left out
+fn ascii() -> String {
+    let mut text = header("ascii");
+    for i in 0..4 {
+        let [x, y, z] = POSITIONS[i];
+        let [r, g, b] = COLORS[i];
+        let [s, t] = UVS[i];
+        text += &format!(
+            "{} {} {} 0 0 1 {} {} {} {} {} {}\n",
+            x, y, z, r, g, b, s, t, CONFIDENCE[i]
+        );
+    }
+    text + "4 0 1 2 3\n2 0 1\n0 2\n"
+}
+
+fn binary(big_endian: bool) -> Vec<u8> {
+    let format = if big_endian {
+        "binary_big_endian"
+    } else {
+        "binary_little_endian"
+    };
+    let mut bytes = header(format).into_bytes();
+
+    let float = |bytes: &mut Vec<u8>, value: f32| {
+        bytes.extend(if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        })
+    };
+    let int = |bytes: &mut Vec<u8>, value: i32| {
+        bytes.extend(if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        })
+    };
+
+    for i in 0..4 {
+        for value in POSITIONS[i].into_iter().chain([0.0, 0.0, 1.0]) {
+            float(&mut bytes, value);
+        }
+        bytes.extend(COLORS[i]);
+        for value in UVS[i].into_iter().chain([CONFIDENCE[i]]) {
+            float(&mut bytes, value);
+        }
+    }
+    for face in [&[0, 1, 2, 3][..], &[0, 1]] {
+        bytes.push(face.len() as u8);
+        for &index in face {
+            int(&mut bytes, index);
+        }
+    }
+    int(&mut bytes, 0);
+    int(&mut bytes, 2);
+    bytes
+}
+
+fn write_asset(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ply");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn check_quad(mesh: &Mesh) {
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(mesh.vertices[2], Vec3::new(1.0, 1.0, 0.0));
+    assert_eq!(mesh.normals, [Vec3::new(0.0, 0.0, 1.0); 4]);
+    assert_eq!(mesh.uvs[1], Vec2::new(1.0, 0.0));
+
+    assert_eq!(mesh.colors[0], Vec4::new(1.0, 0.0, 0.0, 1.0));
+    assert!(mesh.colors[3].approx_eq(Vec4::new(0.2, 0.4, 0.8, 1.0), 1e-6));
+
+    // The quad is fanned out, the face with 2 vertices is left out
+    assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+
+    assert_eq!(mesh.attributes.len(), 1);
+    assert_eq!(mesh.attribute("confidence").unwrap(), CONFIDENCE);
+}
+
+#[test]
+fn ascii_ply() {
+    let mesh = Mesh::new(write_asset("quad.ply", ascii())).unwrap();
+    check_quad(&mesh);
+    assert_eq!(mesh.name, "quad");
+}
+
+#[test]
+fn binary_ply() {
+    for big_endian in [false, true] {
+        let path = write_asset(&format!("quad_{}.ply", big_endian), binary(big_endian));
+        check_quad(&Mesh::new(path).unwrap());
+    }
+}
+
+// An element without properties takes no room in the body, whatever its count
+#[test]
+fn elements_without_properties() {
+    for (name, mut contents) in [
+        ("empty_element.ply", ascii().into_bytes()),
+        ("empty_element_binary.ply", binary(false)),
+    ] {
+        let end = b"end_header\n";
+        let at = contents
+            .windows(end.len())
+            .position(|window| window == end)
+            .unwrap();
+        contents.splice(at..at, b"element nothing 4000000000\n".iter().copied());
+        check_quad(&Mesh::new(write_asset(name, contents)).unwrap());
+    }
+}
+
+#[test]
+fn ply_detection() {
+    assert_eq!(
+        MeshFormat::from_contents(&binary(true)),
+        Some(MeshFormat::Ply)
+    );
+    check_quad(&Mesh::new(write_asset("scan.bin", binary(false))).unwrap());
+}
+
+// 16 bit and floating point colors, and alpha
+#[test]
+fn color_types() {
+    let text = "ply\nformat ascii 1.0\nelement vertex 3\n\
+                property float x\nproperty float y\nproperty float z\n\
+                property ushort red\nproperty ushort green\nproperty ushort blue\n\
+                property float alpha\n\
+                element face 1\nproperty list uchar uint vertex_index\nend_header\n\
+                0 0 0 65535 0 0 1\n1 0 0 0 65535 0 0.5\n0 1 0 0 0 0 0\n3 0 1 2\n";
+    let mesh = Mesh::new(write_asset("colors.ply", text)).unwrap();
+
+    assert_eq!(mesh.colors[0], Vec4::new(1.0, 0.0, 0.0, 1.0));
+    assert_eq!(mesh.colors[1], Vec4::new(0.0, 1.0, 0.0, 0.5));
+    assert!(mesh.attributes.is_empty());
+}
+
+#[test]
+fn malformed_ply() {
+    let parse_error = |name: &str, contents: Vec<u8>| {
+        let result = Mesh::new(write_asset(name, contents));
+        assert!(matches!(result, Err(Error::Parse(_))), "{}", name);
+    };
+
+    parse_error(
+        "no_end.ply",
+        header("ascii").replace("end_header\n", "").into(),
+    );
+    parse_error("truncated.ply", {
+        let mut bytes = binary(false);
+        bytes.truncate(bytes.len() - 10);
+        bytes
+    });
+    parse_error(
+        "letters.ply",
+        ascii().replace("0 0 1 255", "0 0 one 255").into(),
+    );
+    parse_error(
+        "negative.ply",
+        ascii().replace("4 0 1 2 3", "4 0 1 2 -3").into(),
+    );
+    parse_error(
+        "no_x.ply",
+        ascii()
+            .replace("property float x\n", "property float w\n")
+            .into(),
+    );
+
+    // A face claiming about 4 billion vertices
+    parse_error("huge_list.ply", {
+        let mut bytes = String::from(
+            "ply\nformat binary_little_endian 1.0\nelement vertex 3\n\
+             property float x\nproperty float y\nproperty float z\n\
+             element face 1\nproperty list uint int vertex_indices\nend_header\n",
+        )
+        .into_bytes();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(0xFFFF_FFF0u32.to_le_bytes());
+        for index in [0i32, 1, 2] {
+            bytes.extend(index.to_le_bytes());
+        }
+        bytes
+    });
+
+    let result = Mesh::new(write_asset(
+        "out_of_range.ply",
+        ascii().replace("4 0 1 2 3", "4 0 1 2 9"),
+    ));
+    assert!(matches!(
+        result,
+        Err(Error::InvalidIndex { what: "vertex", .. })
+    ));
+}
+
+// Shows the interpolated vertex color, unlit
+struct VertexColorShader;
+
+impl FragmentShader<(Vec3, Vec2, Vec3, Vec4)> for VertexColorShader {
+    fn shade(&self, f: &FragmentInput<(Vec3, Vec2, Vec3, Vec4)>) -> Option<Color> {
+        Some(Color::from_vec4(f.varyings.3))
+    }
+}
+
+#[test]
+fn vertex_colors_are_interpolated() {
+    let triangle = Mesh {
+        vertices: vec![
+            Vec3::new(-1.0, -1.0, -3.0),
+            Vec3::new(1.0, -1.0, -3.0),
+            Vec3::new(0.0, 1.0, -3.0),
+        ],
+        colors: vec![
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.0, 1.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, 1.0, 1.0),
+        ],
+        indices: vec![0, 1, 2],
+        materials: vec![Material::default()],
+        material_ids: vec![0],
+        ..Default::default()
+    };
+    let identity = || ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0));
+    let camera = Camera::new(identity(), 1.0, 1.0, 0.5, 100.0);
+    let scene = Scene::new(vec![triangle], vec![GameObject::new(0, identity())], camera);
+
+    let mut rasterizer = Rasterizer::new(RenderTarget::new(64, 64));
+    rasterizer.target.clear(Color::new(0, 0, 0, 255));
+    rasterizer
+        .draw_scene(&scene, &DefaultVertexShader, &VertexColorShader)
+        .unwrap();
+
+    let image = rasterizer.target.draw_buffer.to_image();
+    let covered: Vec<&Color> = image
+        .pixels()
+        .iter()
+        .filter(|c| (c.r(), c.g(), c.b()) != (0, 0, 0))
+        .collect();
+    assert!(covered.len() > 200);
+
+    // The weights of the corners add up to 1 everywhere
+    for color in &covered {
+        let sum = color.r() as i32 + color.g() as i32 + color.b() as i32;
+        assert!((sum - 255).abs() <= 3, "{:?}", color);
+    }
+
+    // Near every corner its color takes over, and on average they are equal
+    let mut average = [0.0; 3];
+    for color in &covered {
+        let channels = [color.r(), color.g(), color.b()];
+        for (channel, value) in average.iter_mut().zip(channels) {
+            *channel += value as f32 / covered.len() as f32;
+        }
+    }
+    for channel in 0..3 {
+        assert!(
+            covered
+                .iter()
+                .any(|color| [color.r(), color.g(), color.b()][channel] > 220),
+            "{}",
+            channel
+        );
+        assert!((average[channel] - 85.0).abs() < 8.0, "{:?}", average);
+    }
+}
@@ -0,0 +1,106 @@
+// Checks of the projections of Camera: the depth range, and what each one keeps
+use haywire_rasterizer::custom_data_types::camera::{Camera, Projection};
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+const Z_NEAR: f32 = 0.5;
+const Z_FAR: f32 = 50.0;
+
+const PROJECTIONS: [Projection; 3] = [
+    Projection::Perspective { fov: 1.2 },
+    Projection::Orthographic { height: 6.0 },
+    Projection::Oblique {
+        height: 6.0,
+        angle: 0.7,
+        depth_scale: 0.5,
+        focus: 10.0,
+    },
+];
+
+fn camera(projection: Projection) -> Camera {
+    Camera::with_projection(
+        ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0)),
+        projection,
+        2.0,
+        Z_NEAR,
+        Z_FAR,
+    )
+}
+
+// The point in NDC, after the perspective divide
+fn project(camera: &Camera, point: Vec3) -> Vec3 {
+    (camera.get_projection_matrix() * Vec4::point(point)).project()
+}
+
+// The near plane goes to -1 and the far plane to 1, with the depth increasing in between
+#[test]
+fn depth_range() {
+    for projection in PROJECTIONS {
+        let camera = camera(projection);
+        let depth = |distance: f32| project(&camera, Vec3::new(0.3, -0.2, -distance)).z;
+
+        assert!((depth(Z_NEAR) + 1.0).abs() < 1e-4, "{:?}", projection);
+        assert!((depth(Z_FAR) - 1.0).abs() < 1e-4, "{:?}", projection);
+        assert!(depth(2.0) < depth(3.0), "{:?}", projection);
+    }
+}
+
+// The clipper keeps what is between the planes: -w <= z <= w in clip space
+#[test]
+fn near_and_far_clipping() {
+    for projection in PROJECTIONS {
+        let matrix = camera(projection).get_projection_matrix();
+        let inside = |distance: f32| {
+            let clip = matrix * Vec4::point(Vec3::new(0.0, 0.0, -distance));
+            -clip.w <= clip.z && clip.z <= clip.w
+        };
+
+        assert!(!inside(Z_NEAR * 0.5), "{:?}", projection);
+        assert!(!inside(-1.0), "{:?}", projection);
+        assert!(inside(Z_NEAR * 1.01), "{:?}", projection);
+        assert!(inside(Z_FAR * 0.99), "{:?}", projection);
+        assert!(!inside(Z_FAR * 1.01), "{:?}", projection);
+    }
+}
+
+// An orthographic projection doesn't shrink things with the distance, and shows
+// height units vertically
+#[test]
+fn orthographic_keeps_sizes() {
+    let camera = camera(Projection::Orthographic { height: 6.0 });
+
+    for distance in [1.0, 10.0, 40.0] {
+        let top = project(&camera, Vec3::new(6.0, 3.0, -distance));
+        assert!(top.approx_eq(Vec3::new(1.0, 1.0, top.z), 1e-5));
+    }
+}
+
+// The oblique projection is the orthographic one on the focus plane, and moves what
+// is behind it along the angle
+#[test]
+fn oblique_shears_depth() {
+    let orthographic = camera(Projection::Orthographic { height: 6.0 });
+    let oblique = camera(PROJECTIONS[2]);
+
+    let on_focus = Vec3::new(1.0, -2.0, -10.0);
+    assert!(project(&oblique, on_focus).approx_eq(project(&orthographic, on_focus), 1e-5));
+
+    let behind = Vec3::new(1.0, -2.0, -14.0);
+    let offset = project(&oblique, behind) - project(&orthographic, behind);
+    // 4 units behind, by 0.5 per unit, then scaled to NDC (12 x 6 units)
+    let expected = Vec3::new(2.0 * 0.7f32.cos() / 6.0, 2.0 * 0.7f32.sin() / 3.0, 0.0);
+    assert!(offset.approx_eq(expected, 1e-5), "{}", offset);
+}
+
+// Switching projections with the view height keeps the size at that distance
+#[test]
+fn matching_view_height() {
+    let perspective = camera(PROJECTIONS[0]);
+    let height = perspective.projection.view_height(10.0);
+    let orthographic = camera(Projection::Orthographic { height });
+
+    let point = Vec3::new(1.5, -2.5, -10.0);
+    let (a, b) = (project(&perspective, point), project(&orthographic, point));
+    assert!(a.xy().approx_eq(b.xy(), 1e-5));
+}
@@ -0,0 +1,203 @@
+// Checks of the quaternion rotations against the Euler angle matrices they replace
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::mat3::Mat3;
+use haywire_rasterizer::custom_data_types::matrices::{Matrix4x4, ModelMatrix};
+use haywire_rasterizer::custom_data_types::quat::{EulerOrder, Quat};
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+
+const ORDERS: [EulerOrder; 6] = [
+    EulerOrder::XYZ,
+    EulerOrder::XZY,
+    EulerOrder::YXZ,
+    EulerOrder::YZX,
+    EulerOrder::ZXY,
+    EulerOrder::ZYX,
+];
+
+fn assert_close(actual: &Matrix4x4, expected: &Matrix4x4) {
+    for row in 0..4 {
+        for col in 0..4 {
+            let (a, e) = (actual.data[row][col], expected.data[row][col]);
+            assert!(
+                (a - e).abs() < 1e-4,
+                "[{}][{}] is {} instead of {}",
+                row,
+                col,
+                a,
+                e
+            );
+        }
+    }
+}
+
+fn assert_vec_close(actual: Vec3, expected: Vec3) {
+    let difference = actual - expected;
+    assert!(
+        difference.magnitude() < 1e-4,
+        "{} instead of {}",
+        actual,
+        expected
+    );
+}
+
+fn axis_rotation(axis: usize, angle: f32) -> Matrix4x4 {
+    let (sin, cos) = angle.sin_cos();
+    let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut matrix = Matrix4x4::identity();
+    matrix.data[a][a] = cos;
+    matrix.data[a][b] = -sin;
+    matrix.data[b][a] = sin;
+    matrix.data[b][b] = cos;
+    matrix
+}
+
+#[test]
+fn axis_angle_rotates_counterclockwise() {
+    let quarter_turn = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 2.0), FRAC_PI_2);
+
+    assert_vec_close(
+        quarter_turn.rotate(Vec3::new(1.0, 0.0, 0.0)),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    assert_vec_close(
+        quarter_turn.conjugate().rotate(Vec3::new(1.0, 0.0, 0.0)),
+        Vec3::new(0.0, -1.0, 0.0),
+    );
+
+    let point = Vec3::new(0.3, -2.0, 1.5);
+    assert_vec_close(
+        quarter_turn.to_matrix().transform_point(point),
+        quarter_turn.rotate(point),
+    );
+}
+
+// Every order is the product of the axis rotations, the first one on the right
+#[test]
+fn euler_orders() {
+    let angles = Vec3::new(0.4, -1.2, 2.5);
+    let values = [angles.x, angles.y, angles.z];
+
+    for order in ORDERS {
+        let axes: Vec<usize> = format!("{:?}", order)
+            .chars()
+            .map(|axis| (axis as u8 - b'X') as usize)
+            .collect();
+        let expected = axes.iter().fold(Matrix4x4::identity(), |matrix, &axis| {
+            axis_rotation(axis, values[axis]) * matrix
+        });
+
+        let rotation = Quat::from_euler(angles, order);
+        assert_close(&rotation.to_matrix(), &expected);
+
+        // The angles can come back different, but they are the same rotation
+        let back = Quat::from_euler(rotation.to_euler(order), order);
+        assert_close(&back.to_matrix(), &expected);
+    }
+}
+
+#[test]
+fn euler_gimbal_lock() {
+    for order in ORDERS {
+        for middle in [FRAC_PI_2, -FRAC_PI_2] {
+            let mut angles = [0.7, 0.7, 0.7];
+            let axes = format!("{:?}", order);
+            let middle_axis = (axes.as_bytes()[1] - b'X') as usize;
+            angles[middle_axis] = middle;
+
+            let angles = Vec3::new(angles[0], angles[1], angles[2]);
+            let rotation = Quat::from_euler(angles, order);
+            let back = Quat::from_euler(rotation.to_euler(order), order);
+
+            assert_close(&back.to_matrix(), &rotation.to_matrix());
+        }
+    }
+}
+
+// Back from the matrix, for rotations that take each of the branches (w, x, y or z largest)
+#[test]
+fn from_rotation_matrix() {
+    let rotations = [
+        Quat::from_axis_angle(Vec3::new(1.0, 2.0, -1.0), 0.4),
+        Quat::from_axis_angle(Vec3::new(1.0, 0.1, 0.2), 3.0),
+        Quat::from_axis_angle(Vec3::new(-0.1, 1.0, 0.3), 3.0),
+        Quat::from_axis_angle(Vec3::new(0.2, 0.1, -1.0), 3.0),
+        Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), PI),
+    ];
+
+    for rotation in rotations {
+        let back = Quat::from_rotation_matrix(&Mat3::from(rotation.to_matrix()));
+        assert!((back.dot(rotation).abs() - 1.0).abs() < 1e-5, "{:?}", back);
+    }
+}
+
+#[test]
+fn slerp_and_nlerp() {
+    let axis = Vec3::new(1.0, 2.0, -1.0);
+    let from = Quat::from_axis_angle(axis, 0.2);
+    let to = Quat::from_axis_angle(axis, 2.2);
+
+    for interpolate in [Quat::slerp, Quat::nlerp] {
+        assert_close(&interpolate(from, to, 0.0).to_matrix(), &from.to_matrix());
+        assert_close(&interpolate(from, to, 1.0).to_matrix(), &to.to_matrix());
+        // Halfway is the same rotation for both, around the same axis
+        assert_close(
+            &interpolate(from, to, 0.5).to_matrix(),
+            &Quat::from_axis_angle(axis, 1.2).to_matrix(),
+        );
+        // -to is the same rotation, the interpolation goes the short way anyway
+        assert_close(
+            &interpolate(from, -to, 0.5).to_matrix(),
+            &Quat::from_axis_angle(axis, 1.2).to_matrix(),
+        );
+    }
+
+    // Constant speed: a quarter of the way is a quarter of the angle
+    assert_close(
+        &from.slerp(to, 0.25).to_matrix(),
+        &Quat::from_axis_angle(axis, 0.7).to_matrix(),
+    );
+
+    // Close rotations fall back to nlerp
+    let close = Quat::from_axis_angle(axis, 0.2001);
+    let halfway = from.slerp(close, 0.5);
+    assert!((halfway.magnitude() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn model_matrix_keeps_the_euler_accessors() {
+    let angle = Vec3::new(0.5, 0.6, -PI / 3.0);
+    let mut model = ModelMatrix::new(Vec3::new(1.0, 2.0, 3.0), angle, Vec3::new(1.0, 1.0, 1.0));
+
+    assert_vec_close(model.angle(), angle);
+    assert_close(
+        &model.rotation().to_matrix(),
+        &Quat::from_euler(angle, EulerOrder::XYZ).to_matrix(),
+    );
+
+    let rotation = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 1.0);
+    model.update_rotation(rotation);
+    assert_vec_close(model.angle(), Vec3::new(0.0, 1.0, 0.0));
+}
+
+// The view matrix undoes the camera's model matrix
+#[test]
+fn view_matrix_is_the_inverse_of_the_camera() {
+    let camera = Camera::new(
+        ModelMatrix::new(
+            Vec3::new(4.0, -1.0, 2.5),
+            Vec3::new(0.3, -2.0, 0.8),
+            Vec3::new(1.0, 1.0, 1.0),
+        ),
+        60.0f32.to_radians(),
+        16.0 / 9.0,
+        0.5,
+        100.0,
+    );
+
+    assert_close(
+        &(camera.get_view_matrix() * camera.model.get_model_matrix()),
+        &Matrix4x4::identity(),
+    );
+}
@@ -0,0 +1,231 @@
+// STL import and export, and finding the format of mesh files
+use std::path::{Path, PathBuf};
+
+use haywire_rasterizer::custom_data_types::error::Error;
+use haywire_rasterizer::custom_data_types::mesh::{Mesh, MeshFormat};
+use haywire_rasterizer::custom_data_types::vec2::Vec2;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+
+const CORNERS: [[f32; 3]; 4] = [
+    [1.0, 1.0, 1.0],
+    [1.0, -1.0, -1.0],
+    [-1.0, 1.0, -1.0],
+    [-1.0, -1.0, 1.0],
+];
+// Counter-clockwise seen from outside
+const FACES: [[usize; 3]; 4] = [[1, 3, 2], [0, 2, 3], [0, 3, 1], [0, 1, 2]];
+
+// A tetrahedron around the origin, every facet repeating its vertices like STL does
+fn tetrahedron(name: &str) -> String {
+    let mut text = format!("solid {}\n", name);
+    for face in FACES {
+        text += "  facet normal 0 0 0\n    outer loop\n";
+        for corner in face {
+            let [x, y, z] = CORNERS[corner];
+            text += &format!("      vertex {} {} {}\n", x, y, z);
+        }
+        text += "    endloop\n  endfacet\n";
+    }
+    text + &format!("endsolid {}\n", name)
+}
+
+fn write_asset(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("stl");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+// The triangles as their vertex positions, to compare meshes indexed differently
+fn triangles(mesh: &Mesh) -> Vec<[Vec3; 3]> {
+    mesh.indices
+        .chunks_exact(3)
+        .map(|triangle| [0, 1, 2].map(|i| mesh.vertices[triangle[i]]))
+        .collect()
+}
+
+#[test]
+fn ascii_is_welded_and_smoothed() {
+    let mesh = Mesh::new(write_asset("tetrahedron.stl", tetrahedron("part"))).unwrap();
+
+    assert_eq!(mesh.name, "tetrahedron");
+    assert_eq!(mesh.groups[0].name, "part");
+    assert_eq!(mesh.indices.len(), 12);
+    // The 12 vertices of the file are the 4 corners
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(mesh.materials.len(), 1);
+
+    // The smooth normals point away from the center, through the corners
+    for (vertex, normal) in mesh.vertices.iter().zip(&mesh.normals) {
+        assert!(normal.approx_eq(vertex.normalize(), 1e-5), "{}", normal);
+    }
+}
+
+#[test]
+fn binary_round_trip() {
+    let ascii = Mesh::new(write_asset("ascii.stl", tetrahedron("part"))).unwrap();
+
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("stl/binary.stl");
+    ascii.save(&path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(bytes.len(), 84 + 4 * 50);
+    assert!(!bytes.starts_with(b"solid"));
+
+    let binary = Mesh::new(&path).unwrap();
+    assert_eq!(triangles(&binary), triangles(&ascii));
+    assert_eq!(binary.vertices.len(), 4);
+    assert_eq!(binary.groups[0].name, "binary");
+}
+
+#[test]
+fn ascii_round_trip_with_several_solids() {
+    let text = tetrahedron("first") + &tetrahedron("second");
+    let meshes = Mesh::load_objects(write_asset("two.stl", text)).unwrap();
+    let names: Vec<&str> = meshes.iter().map(|mesh| mesh.name.as_str()).collect();
+    assert_eq!(names, ["first", "second"]);
+
+    let merged = Mesh::new(write_asset(
+        "two.stl",
+        tetrahedron("first") + &tetrahedron("second"),
+    ))
+    .unwrap();
+    assert_eq!(merged.groups.len(), 2);
+    assert_eq!(merged.groups[1].triangles, 4..8);
+
+    let written = String::from_utf8(meshes[1].encode_stl_ascii()).unwrap();
+    assert!(written.starts_with("solid second\n"));
+    let reloaded = Mesh::new(write_asset("written.stl", written)).unwrap();
+    assert_eq!(triangles(&reloaded), triangles(&meshes[1]));
+}
+
+#[test]
+fn format_detection() {
+    // Binary files may start with "solid" too, their size gives them away
+    let mut binary = Mesh::new(write_asset("header.stl", tetrahedron("part")))
+        .unwrap()
+        .encode_stl_binary();
+    binary[..5].copy_from_slice(b"solid");
+    assert_eq!(MeshFormat::from_contents(&binary), Some(MeshFormat::Stl));
+    let mesh = Mesh::new(write_asset("header.dat", &binary)).unwrap();
+    assert_eq!(mesh.indices.len(), 12);
+
+    let obj = "# exported\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+    assert_eq!(
+        MeshFormat::from_contents(obj.as_bytes()),
+        Some(MeshFormat::Obj)
+    );
+    assert_eq!(
+        Mesh::new(write_asset("obj.txt", obj))
+            .unwrap()
+            .indices
+            .len(),
+        3
+    );
+
+    // The extension comes first
+    assert_eq!(
+        MeshFormat::detect(Path::new("part.STL"), obj.as_bytes()),
+        Some(MeshFormat::Stl)
+    );
+    assert_eq!(MeshFormat::from_contents(b"Kaydara FBX Binary"), None);
+}
+
+#[test]
+fn malformed_stl() {
+    let mut truncated = Mesh::new(write_asset("whole.stl", tetrahedron("part")))
+        .unwrap()
+        .encode_stl_binary();
+    truncated.truncate(84 + 3 * 50);
+    let path = write_asset("truncated.stl", &truncated);
+    assert!(matches!(Mesh::new(&path), Err(Error::Parse(_))));
+
+    let text = "solid line\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet\nendsolid\n";
+    let path = write_asset("line.stl", text);
+    assert!(matches!(Mesh::new(&path), Err(Error::Parse(_))));
+
+    let path = write_asset(
+        "letters.stl",
+        tetrahedron("part").replace("1 1 1", "1 one 1"),
+    );
+    assert!(matches!(Mesh::new(&path), Err(Error::Parse(_))));
+
+    // Infinite coordinates, written out or too large for an f32
+    for (name, vertex) in [("inf.stl", "inf 1 1"), ("overflow.stl", "1e39 1 1")] {
+        let path = write_asset(name, tetrahedron("part").replace("1 1 1", vertex));
+        assert!(matches!(Mesh::new(&path), Err(Error::Parse(_))));
+    }
+
+    let mut infinite = Mesh::new(write_asset("finite.stl", tetrahedron("part")))
+        .unwrap()
+        .encode_stl_binary();
+    // The x of the first vertex of the first triangle
+    infinite[84 + 12..84 + 16].copy_from_slice(&f32::INFINITY.to_le_bytes());
+    let path = write_asset("infinite.stl", &infinite);
+    assert!(matches!(Mesh::new(&path), Err(Error::Parse(_))));
+}
+
+// Coordinates far from the origin saturate the welding grid, but still load and weld
+#[test]
+fn huge_coordinates() {
+    let text = tetrahedron("part").replace("vertex 1 1 1", "vertex 1e30 1 1");
+    let mesh = Mesh::new(write_asset("huge.stl", text)).unwrap();
+    assert_eq!(mesh.vertices.len(), 4);
+    assert!(mesh.vertices.contains(&Vec3::new(1e30, 1.0, 1.0)));
+
+    let binary = Mesh::new(write_asset("huge_binary.stl", mesh.encode_stl_binary())).unwrap();
+    assert_eq!(binary.vertices.len(), 4);
+}
+
+#[test]
+fn welding_keeps_uv_seams() {
+    let mut mesh = Mesh {
+        vertices: vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.000001),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+        ],
+        uvs: vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 1.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.5, 1.0),
+            Vec2::new(1.0, 1.0),
+        ],
+        indices: vec![0, 1, 2, 3, 5, 4],
+        ..Default::default()
+    };
+
+    mesh.weld(1e-5);
+    // Vertex 3 is close enough to 1, vertex 4 has another UV than 2
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.indices, [0, 1, 2, 1, 4, 3]);
+    assert_eq!(mesh.uvs[3], Vec2::new(0.5, 1.0));
+}
+
+// Without a tolerance only the vertices at exactly the same position are merged
+#[test]
+fn welding_without_tolerance() {
+    for tolerance in [0.0, -1.0, f32::NAN] {
+        let mut mesh = Mesh {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.000001),
+                Vec3::new(0.0, 1.0, -0.0),
+                Vec3::new(1e30, 0.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 3, 5, 4],
+            ..Default::default()
+        };
+
+        mesh.weld(tolerance);
+        assert_eq!(mesh.vertices.len(), 5, "tolerance {}", tolerance);
+        assert_eq!(mesh.indices, [0, 1, 2, 3, 4, 2]);
+    }
+}
@@ -0,0 +1,139 @@
+// Checks of FrameTimer and FixedTimestep, with recorded frame times instead of the clock
+use std::time::Duration;
+
+use haywire_rasterizer::custom_data_types::camera::Camera;
+use haywire_rasterizer::custom_data_types::camera_controller::{
+    CameraController, ControllerInput, FlyController,
+};
+use haywire_rasterizer::custom_data_types::frame_timer::{FixedTimestep, FrameTimer};
+use haywire_rasterizer::custom_data_types::matrices::ModelMatrix;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+
+fn ms(milliseconds: u64) -> Duration {
+    Duration::from_millis(milliseconds)
+}
+
+#[test]
+fn delta_and_elapsed() {
+    let mut timer = FrameTimer::new(10);
+    assert_eq!(timer.fps(), 0.0);
+
+    timer.record(ms(16));
+    assert_eq!(timer.delta(), ms(16));
+    assert!((timer.delta_seconds() - 0.016).abs() < 1e-6);
+
+    // A long pause is cut in the delta, but counted in the statistics
+    timer.record(ms(2000));
+    assert_eq!(timer.delta(), timer.max_delta);
+    assert_eq!(timer.elapsed(), ms(2016));
+    assert_eq!(timer.frame_count(), 2);
+}
+
+#[test]
+fn smoothed_fps() {
+    let mut timer = FrameTimer::new(10);
+    for _ in 0..100 {
+        timer.record(ms(20));
+    }
+    assert!((timer.fps() - 50.0).abs() < 0.01);
+
+    // One slow frame only moves it a bit
+    timer.record(ms(100));
+    assert!(timer.fps() < 50.0 && timer.fps() > 30.0, "{}", timer.fps());
+}
+
+#[test]
+fn percentiles_of_the_recent_frames() {
+    let mut timer = FrameTimer::new(100);
+    // 1 ms to 100 ms, in a scrambled order
+    for i in 0..100 {
+        timer.record(ms((i * 37) % 100 + 1));
+    }
+
+    assert_eq!(timer.frame_time_percentile(50.0), ms(50));
+    assert_eq!(timer.frame_time_percentile(99.0), ms(99));
+    assert_eq!(timer.frame_time_percentile(100.0), ms(100));
+    assert_eq!(timer.frame_time_percentile(0.0), ms(1));
+    assert_eq!(timer.average_frame_time(), Duration::from_micros(50500));
+
+    // Only the last 100 frames count
+    for _ in 0..100 {
+        timer.record(ms(5));
+    }
+    assert_eq!(timer.frame_time_percentile(100.0), ms(5));
+}
+
+#[test]
+fn fixed_timestep_carries_the_remainder() {
+    let mut timestep = FixedTimestep::new(100.0);
+    assert_eq!(timestep.step(), ms(10));
+
+    assert_eq!(timestep.advance(ms(25)), 2);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+    assert_eq!(timestep.advance(ms(4)), 0);
+    assert_eq!(timestep.advance(ms(1)), 1);
+
+    // Too far behind: at most max_steps, and the rest is dropped
+    assert_eq!(timestep.advance(ms(1000)), timestep.max_steps);
+    assert_eq!(timestep.alpha(), 0.0);
+}
+
+// Rates that would give a zero, negative or infinite step are clamped
+#[test]
+fn fixed_timestep_clamps_the_rate() {
+    let slowest = Duration::from_secs_f32(1.0 / FixedTimestep::MIN_RATE);
+    let fastest = Duration::from_secs_f32(1.0 / FixedTimestep::MAX_RATE);
+
+    for rate in [0.0, -60.0, f32::NEG_INFINITY, f32::NAN] {
+        assert_eq!(FixedTimestep::new(rate).step(), slowest, "rate {}", rate);
+    }
+    assert_eq!(FixedTimestep::new(f32::INFINITY).step(), fastest);
+    assert_eq!(FixedTimestep::new(1e9).step(), fastest);
+
+    let mut timestep = FixedTimestep::new(0.0);
+    assert_eq!(timestep.advance(ms(5)), 0);
+    assert!(timestep.alpha().is_finite());
+}
+
+// The same second of movement goes as far at 20 fps as at 200 fps, and as far
+// with a fixed timestep
+#[test]
+fn movement_is_frame_rate_independent() {
+    let fly_for = |frame_time: Duration, fixed: Option<FixedTimestep>| {
+        let mut camera = Camera::new(
+            ModelMatrix::new(Vec3::zero(), Vec3::zero(), Vec3::splat(1.0)),
+            1.0,
+            1.0,
+            0.5,
+            100.0,
+        );
+        let mut fly = FlyController::new(6.0, 0.005);
+        fly.sync(&camera);
+        let input = ControllerInput {
+            movement: Vec3::new(0.0, 0.0, 1.0),
+            ..Default::default()
+        };
+
+        let mut timer = FrameTimer::default();
+        let mut fixed = fixed;
+        while timer.elapsed() < Duration::from_secs(1) {
+            timer.record(frame_time);
+            match &mut fixed {
+                Some(timestep) => {
+                    for _ in 0..timestep.advance(timer.delta()) {
+                        fly.update(&mut camera, &input, timestep.step_seconds());
+                    }
+                }
+                None => fly.update(&mut camera, &input, timer.delta_seconds()),
+            }
+        }
+        camera.position()
+    };
+
+    let expected = Vec3::new(0.0, 0.0, -6.0);
+    for frame_time in [ms(5), ms(50)] {
+        assert!(fly_for(frame_time, None).approx_eq(expected, 1e-3));
+        let fixed = fly_for(frame_time, Some(FixedTimestep::new(100.0)));
+        assert!(fixed.approx_eq(expected, 1e-3), "{}", fixed);
+    }
+}
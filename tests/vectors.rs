@@ -0,0 +1,160 @@
+// Checks of Vec2, Vec3, Vec4 and Mat3
+use haywire_rasterizer::custom_data_types::mat3::Mat3;
+use haywire_rasterizer::custom_data_types::matrices::Matrix4x4;
+use haywire_rasterizer::custom_data_types::vec2::Vec2;
+use haywire_rasterizer::custom_data_types::vec3::Vec3;
+use haywire_rasterizer::custom_data_types::vec4::Vec4;
+
+fn assert_mat3_close(actual: &Mat3, expected: &Mat3) {
+    for row in 0..3 {
+        assert!(
+            actual.row(row).approx_eq(expected.row(row), 1e-4),
+            "row {} is {} instead of {}",
+            row,
+            actual.row(row),
+            expected.row(row)
+        );
+    }
+}
+
+#[test]
+fn dot_and_cross() {
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+
+    assert_eq!(x.cross(y), Vec3::new(0.0, 0.0, 1.0));
+    assert_eq!(y.cross(x), Vec3::new(0.0, 0.0, -1.0));
+    assert_eq!(
+        Vec3::new(1.0, 2.0, 3.0).dot(Vec3::new(4.0, -5.0, 6.0)),
+        12.0
+    );
+    assert_eq!(Vec2::new(1.0, 0.0).perp_dot(Vec2::new(0.0, 1.0)), 1.0);
+    assert_eq!(Vec3::new(3.0, 0.0, 4.0).magnitude(), 5.0);
+}
+
+// Points are moved by a translation, directions aren't
+#[test]
+fn points_and_directions() {
+    let mut translation = Matrix4x4::identity();
+    translation.data[0][3] = 2.0;
+    translation.data[1][3] = -1.0;
+    let v = Vec3::new(1.0, 1.0, 1.0);
+
+    assert_eq!(translation.transform_point(v), Vec3::new(3.0, 0.0, 1.0));
+    assert_eq!(translation.transform_direction(v), v);
+    assert_eq!(Vec4::point(v).w, 1.0);
+    assert_eq!(Vec4::direction(v).w, 0.0);
+    assert_eq!(
+        Vec4::new(2.0, 4.0, -6.0, 2.0).project(),
+        Vec3::new(1.0, 2.0, -3.0)
+    );
+}
+
+#[test]
+fn swizzles_and_conversions() {
+    let v = Vec4::new(1.0, 2.0, 3.0, 4.0);
+
+    assert_eq!(v.xyz(), Vec3::new(1.0, 2.0, 3.0));
+    assert_eq!(v.xy(), Vec2::new(1.0, 2.0));
+    assert_eq!(v.xyz().xz(), Vec2::new(1.0, 3.0));
+    assert_eq!(v.xyz().yz().yx(), Vec2::new(3.0, 2.0));
+    assert_eq!(v.xyz().zyx(), Vec3::new(3.0, 2.0, 1.0));
+    assert_eq!(Vec2::new(1.0, 2.0).extend(3.0).extend(4.0), v);
+    assert_eq!(Vec4::from(v.to_array()), v);
+}
+
+#[test]
+fn component_wise() {
+    let a = Vec3::new(1.0, -2.0, 3.0);
+    let b = Vec3::new(2.0, 4.0, -1.0);
+
+    assert_eq!(a * b, Vec3::new(2.0, -8.0, -3.0));
+    assert_eq!(a / b, Vec3::new(0.5, -0.5, -3.0));
+    assert_eq!(a.min(b), Vec3::new(1.0, -2.0, -1.0));
+    assert_eq!(a.max(b), Vec3::new(2.0, 4.0, 3.0));
+    assert_eq!(a.abs(), Vec3::new(1.0, 2.0, 3.0));
+    assert_eq!(2.0 * a, a * 2.0);
+    assert_eq!(a.lerp(b, 0.5), Vec3::new(1.5, 1.0, 1.0));
+    assert_eq!(
+        Vec4::new(1.0, 2.0, 3.0, 4.0) * Vec4::new(2.0, 2.0, 2.0, 0.5),
+        Vec4::new(2.0, 4.0, 6.0, 2.0)
+    );
+}
+
+#[test]
+fn approximate_equality() {
+    let v = Vec3::new(1.0, 2.0, 3.0);
+
+    assert!(v.approx_eq(v + Vec3::splat(1e-5), 1e-4));
+    assert!(!v.approx_eq(v + Vec3::new(0.0, 0.0, 1e-3), 1e-4));
+    assert!(Vec2::new(0.1 + 0.2, 0.0).approx_eq(Vec2::new(0.3, 0.0), 1e-6));
+}
+
+#[test]
+fn reflect_and_refract() {
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let incoming = Vec3::new(1.0, -1.0, 0.0).normalize();
+
+    assert!(
+        incoming
+            .reflect(up)
+            .approx_eq(Vec3::new(1.0, 1.0, 0.0).normalize(), 1e-6)
+    );
+
+    // Going straight through doesn't bend, whatever the indices
+    let down = Vec3::new(0.0, -1.0, 0.0);
+    assert!(down.refract(up, 1.5).unwrap().approx_eq(down, 1e-6));
+
+    // Snell's law: sin(out) = eta * sin(in)
+    let eta = 1.0 / 1.33;
+    let refracted = incoming.refract(up, eta).unwrap();
+    assert!((refracted.magnitude() - 1.0).abs() < 1e-5);
+    assert!((refracted.x - eta * incoming.x).abs() < 1e-5);
+    assert!(refracted.y < 0.0);
+
+    // Leaving water at a grazing angle is totally reflected
+    let grazing = Vec3::new(1.0, -0.2, 0.0).normalize();
+    assert_eq!(grazing.refract(up, 1.33), None);
+}
+
+#[test]
+fn mat3_inverse() {
+    let matrix = Mat3::from_rows(
+        Vec3::new(2.0, 0.5, -1.0),
+        Vec3::new(0.0, 3.0, 1.0),
+        Vec3::new(1.0, -1.0, 4.0),
+    );
+
+    assert!((matrix.determinant() - 29.5).abs() < 1e-4);
+    let inverse = matrix.inverse().unwrap();
+    assert_mat3_close(&(matrix * inverse), &Mat3::identity());
+    assert_mat3_close(&(inverse * matrix), &Mat3::identity());
+    assert_mat3_close(&matrix.transpose().transpose(), &matrix);
+
+    let v = Vec3::new(1.0, -2.0, 0.5);
+    assert!((inverse * (matrix * v)).approx_eq(v, 1e-5));
+
+    let flat = Mat3::from_scale(Vec3::new(1.0, 0.0, 2.0));
+    assert!(flat.inverse().is_none());
+
+    // Tiny is not singular
+    let tiny = Mat3::from_scale(Vec3::splat(1e-5));
+    assert_mat3_close(&(tiny * tiny.inverse().unwrap()), &Mat3::identity());
+}
+
+// Mat3 is the top left of a Matrix4x4, and transforms Vec3s the same way
+#[test]
+fn mat3_matches_matrix4x4() {
+    let mut matrix = Matrix4x4::identity();
+    matrix.data[0] = [0.0, -1.0, 0.0, 5.0];
+    matrix.data[1] = [2.0, 0.0, 0.0, 6.0];
+    matrix.data[2] = [0.0, 0.0, 3.0, 7.0];
+
+    let linear = Mat3::from(matrix);
+    let v = Vec3::new(1.0, 2.0, 3.0);
+    assert_eq!(linear * v, matrix.transform_direction(v));
+
+    let back = linear.to_matrix4();
+    assert_eq!(back.data[0], [0.0, -1.0, 0.0, 0.0]);
+    assert_eq!(back.data[3], [0.0, 0.0, 0.0, 1.0]);
+}